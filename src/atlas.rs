@@ -0,0 +1,139 @@
+// Texture atlas shared by the tile map and sprites, generated so there are no image files to ship
+use crate::texture;
+
+// Pixels along each side of a tile in the atlas
+pub const TILE_PIXELS: u32 = 16;
+// Tiles are laid out side by side in a single row, must match ATLAS_TILES in tilemap.wgsl
+pub const ATLAS_TILES: u32 = 5;
+
+// Columns of the atlas
+pub const GRASS: u32 = 0;
+pub const WATER: u32 = 1;
+pub const STONE: u32 = 2;
+pub const SAND: u32 = 3;
+// Drawn as a sprite, the background is transparent
+pub const CHARACTER: u32 = 4;
+
+pub struct Atlas {
+    pub bind_group_layout: wgpu::BindGroupLayout,
+    pub bind_group: wgpu::BindGroup,
+}
+
+impl Atlas {
+    pub fn new(device: &wgpu::Device, queue: &wgpu::Queue) -> Self {
+        // Nearest keeps the pixel art crisp and stops neighbouring tiles bleeding in at the edges
+        Self::from_pixels(
+            device,
+            queue,
+            [TILE_PIXELS * ATLAS_TILES, TILE_PIXELS],
+            wgpu::TextureFormat::Rgba8UnormSrgb,
+            &create_atlas_pixels(),
+            wgpu::FilterMode::Nearest,
+        )
+    }
+
+    // Any RGBA texture bound the same way, like the font's distance field
+    pub fn from_pixels(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        [width, height]: [u32; 2],
+        format: wgpu::TextureFormat,
+        pixels: &[u8],
+        filter: wgpu::FilterMode,
+    ) -> Self {
+        let texture = texture::create_texture(device, queue, width, height, format, pixels);
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("atlas_sampler"),
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            mag_filter: filter,
+            min_filter: filter,
+            ..Default::default()
+        });
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("atlas_bind_group_layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("atlas_bind_group"),
+            layout: &bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&sampler),
+                },
+            ],
+        });
+        Self {
+            bind_group_layout,
+            bind_group,
+        }
+    }
+}
+
+// Where a tile sits in the atlas as x, y, width and height in texture coordinates
+pub fn tile_rect(tile: u32) -> [f32; 4] {
+    let width = 1.0 / ATLAS_TILES as f32;
+    [tile as f32 * width, 0.0, width, 1.0]
+}
+
+fn create_atlas_pixels() -> Vec<u8> {
+    let width = TILE_PIXELS * ATLAS_TILES;
+    let mut pixels = Vec::with_capacity((width * TILE_PIXELS * 4) as usize);
+    for y in 0..TILE_PIXELS {
+        for x in 0..width {
+            let (tile, tx) = (x / TILE_PIXELS, x % TILE_PIXELS);
+            // Cheap per-pixel noise so flat colours don't look like placeholders
+            let noise = ((x.wrapping_mul(73) ^ y.wrapping_mul(151)) % 7) as u8 * 4;
+            let edge = tx == 0 || y == 0 || tx == TILE_PIXELS - 1 || y == TILE_PIXELS - 1;
+            let pixel = match tile {
+                GRASS => [60, 140 + noise, 50, 255],
+                WATER if (tx + y / 4) % 8 == 0 => [120, 170, 230, 255],
+                WATER => [40, 90, 180 + noise, 255],
+                STONE if edge => [70, 70, 75, 255],
+                STONE => [120 + noise, 120 + noise, 125 + noise, 255],
+                SAND => [210 + noise, 190 + noise, 130, 255],
+                _ => character_pixel(tx, y),
+            };
+            pixels.extend_from_slice(&pixel);
+        }
+    }
+    pixels
+}
+
+// A little figure with a head, tunic and legs, rows count down from the top
+fn character_pixel(x: u32, y: u32) -> [u8; 4] {
+    let (dx, dy) = (x as i32 - 8, y as i32 - 4);
+    if dx * dx + dy * dy <= 9 {
+        [230, 190, 150, 255]
+    } else if (5..11).contains(&x) && (7..13).contains(&y) {
+        [180, 40, 40, 255]
+    } else if [5, 6, 9, 10].contains(&x) && (13..16).contains(&y) {
+        [90, 60, 30, 255]
+    } else {
+        [0, 0, 0, 0]
+    }
+}
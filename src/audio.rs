@@ -0,0 +1,95 @@
+// Sound effects and music through rodio, files are looked up by name in the audio folder
+use anyhow::Context;
+use rodio::{Decoder, OutputStream, OutputStreamHandle, Sink, Source};
+use std::collections::HashMap;
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+// Relative to the working directory, the game is run from the repository while developing
+const AUDIO_DIR: &str = "assets/audio";
+// Tried in order for a name without an extension
+const EXTENSIONS: [&str; 2] = ["ogg", "wav"];
+
+pub struct Audio {
+    // Dropping the stream silences everything played through its handle, so it's kept for as
+    // long as the audio is
+    _stream: OutputStream,
+    handle: OutputStreamHandle,
+    // Encoded files already read from disk, sound effects are played often
+    sounds: HashMap<String, Arc<[u8]>>,
+    // The music playing now, replaced by the next `play_music`
+    music: Option<Sink>,
+}
+
+impl Audio {
+    // None when there's no output device to play on
+    pub fn new() -> Option<Self> {
+        match OutputStream::try_default() {
+            Ok((stream, handle)) => Some(Self {
+                _stream: stream,
+                handle,
+                sounds: HashMap::new(),
+                music: None,
+            }),
+            Err(error) => {
+                log::warn!("Audio unavailable: {error}");
+                None
+            }
+        }
+    }
+
+    // Play a sound once over anything else that's playing
+    pub fn play_sound(&mut self, name: &str) -> anyhow::Result<()> {
+        let decoder = self.decoder(name)?;
+        self.handle
+            .play_raw(decoder.convert_samples())
+            .with_context(|| format!("Failed to play sound {name}"))
+    }
+
+    // Start a music track in place of the current one, `looping` repeats it until replaced
+    #[allow(dead_code)] // Nothing plays music yet, it's there for the game to call
+    pub fn play_music(&mut self, name: &str, looping: bool) -> anyhow::Result<()> {
+        let decoder = self.decoder(name)?;
+        let sink =
+            Sink::try_new(&self.handle).with_context(|| format!("Failed to play music {name}"))?;
+        if looping {
+            sink.append(decoder.repeat_infinite());
+        } else {
+            sink.append(decoder);
+        }
+        // Dropping the old sink stops it
+        self.music = Some(sink);
+        Ok(())
+    }
+
+    fn decoder(&mut self, name: &str) -> anyhow::Result<Decoder<Cursor<Arc<[u8]>>>> {
+        let bytes = match self.sounds.get(name) {
+            Some(bytes) => bytes.clone(),
+            None => {
+                let path = find_audio_file(Path::new(AUDIO_DIR), name)?;
+                let bytes: Arc<[u8]> = std::fs::read(&path)
+                    .with_context(|| format!("Failed to read {}", path.display()))?
+                    .into();
+                self.sounds.insert(name.to_string(), bytes.clone());
+                bytes
+            }
+        };
+        Decoder::new(Cursor::new(bytes)).with_context(|| format!("Failed to decode {name}"))
+    }
+}
+
+// The first file in `dir` called `name` with a supported extension
+fn find_audio_file(dir: &Path, name: &str) -> anyhow::Result<PathBuf> {
+    EXTENSIONS
+        .iter()
+        .map(|extension| dir.join(name).with_extension(extension))
+        .find(|path| path.is_file())
+        .with_context(|| {
+            format!(
+                "No {} file called {name} in {}",
+                EXTENSIONS.join(" or "),
+                dir.display()
+            )
+        })
+}
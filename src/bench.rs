@@ -0,0 +1,70 @@
+// Rendering a fixed number of frames offscreen as fast as possible and reporting how long they
+// took, a number that can be compared across commits
+use crate::headless::Offscreen;
+use crate::options::Options;
+use crate::state::FrameTime;
+
+// Frames drawn before timing starts, the first ones pay for driver shader compilation
+const WARMUP_FRAMES: u32 = 10;
+// Every frame advances the clock by the same step so runs draw the same frames
+const STEP_DT: f32 = 1.0 / 60.0;
+
+pub async fn run(options: &Options, frames: u32) -> anyhow::Result<()> {
+    let mut offscreen = Offscreen::new(options).await?;
+    for frame in 0..WARMUP_FRAMES {
+        render_frame(&mut offscreen, frame);
+    }
+    let mut frame_times = Vec::with_capacity(frames as usize);
+    let start = instant::Instant::now();
+    for frame in WARMUP_FRAMES..WARMUP_FRAMES + frames {
+        let frame_start = instant::Instant::now();
+        render_frame(&mut offscreen, frame);
+        frame_times.push(frame_start.elapsed().as_secs_f32() * 1000.0);
+    }
+    let total = start.elapsed().as_secs_f32();
+
+    let summary = Summary::new(&mut frame_times);
+    let size = offscreen.size();
+    println!(
+        "Rendered {frames} frames at {}x{} in {total:.3}s",
+        size.width, size.height
+    );
+    println!(
+        "Frame time ms: min {:.3} max {:.3} mean {:.3} p99 {:.3}",
+        summary.min, summary.max, summary.mean, summary.p99
+    );
+    Ok(())
+}
+
+// Wait for the GPU so the time covers the whole frame rather than just submitting it
+fn render_frame(offscreen: &mut Offscreen, frame: u32) {
+    offscreen.advance_simulation(STEP_DT);
+    offscreen.render(FrameTime {
+        time: frame as f32 * STEP_DT,
+        dt: STEP_DT,
+        frame,
+    });
+    offscreen.device.poll(wgpu::Maintain::Wait);
+}
+
+// Frame times in milliseconds
+struct Summary {
+    min: f32,
+    max: f32,
+    mean: f32,
+    p99: f32,
+}
+
+impl Summary {
+    // Sorts `frame_times`, which must not be empty
+    fn new(frame_times: &mut [f32]) -> Self {
+        frame_times.sort_by(f32::total_cmp);
+        let p99_index = (frame_times.len() * 99).div_ceil(100) - 1;
+        Self {
+            min: frame_times[0],
+            max: frame_times[frame_times.len() - 1],
+            mean: frame_times.iter().sum::<f32>() / frame_times.len() as f32,
+            p99: frame_times[p99_index],
+        }
+    }
+}
@@ -0,0 +1,352 @@
+// 2D camera that pans and zooms the shader's view of the world
+
+use crate::input::{KeyStates, Movement};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct Camera {
+    // World position at the centre of the screen
+    pub offset: [f32; 2],
+    // Magnification, 2.0 shows half as much of the world
+    pub zoom: f32,
+    // Yaw and pitch in radians from mouselook, yaw turns right and pitch looks up
+    pub look: [f32; 2],
+}
+
+impl Default for Camera {
+    fn default() -> Self {
+        Self {
+            offset: [0.0, 0.0],
+            zoom: 1.0,
+            look: [0.0, 0.0],
+        }
+    }
+}
+
+impl Camera {
+    // Default zoom with `position` in the middle of the screen
+    pub fn centred_on(position: [f32; 2]) -> Self {
+        // The shader puts `offset + 0.5` at the centre
+        Self {
+            offset: [position[0] - 0.5, position[1] - 0.5],
+            ..Self::default()
+        }
+    }
+
+    // World position in the middle of the screen
+    pub fn centre(&self) -> [f32; 2] {
+        [self.offset[0] + 0.5, self.offset[1] + 0.5]
+    }
+
+    // The pan and zoom without the mouselook
+    pub fn to_2d(self) -> Camera2D {
+        Camera2D {
+            centre: self.centre(),
+            zoom: self.zoom,
+        }
+    }
+
+    // Screen widths per second the camera pans at, independent of zoom
+    const PAN_SPEED: f32 = 0.5;
+    // Zoom multiplier applied per scroll wheel line
+    const ZOOM_STEP: f32 = 1.1;
+    // Scroll wheel lines per second a fully pulled trigger zooms by
+    const TRIGGER_ZOOM_SPEED: f32 = 10.0;
+    // Zoom limits, past these floats lose precision and the view degenerates
+    const MIN_ZOOM: f32 = 0.01;
+    const MAX_ZOOM: f32 = 1000.0;
+    // Radians the view turns per pixel of mouse movement
+    const LOOK_SENSITIVITY: f32 = 0.002;
+
+    // Pan from the held WASD or arrow keys plus an analog stick, scaled by the frame time
+    pub fn update(&mut self, keys: &KeyStates, stick: [f32; 2], dt: f32) {
+        let travel = self.travel(keys, stick, dt);
+        self.offset[0] += travel[0];
+        self.offset[1] += travel[1];
+    }
+
+    // How far the keys and stick move whatever they steer this frame in world units, the camera
+    // or with --follow the player. It's the same share of the screen at any zoom.
+    pub fn travel(&self, keys: &KeyStates, stick: [f32; 2], dt: f32) -> [f32; 2] {
+        let mut direction = [0.0_f32, 0.0];
+        if keys.moving(Movement::Up) {
+            direction[1] += 1.0;
+        }
+        if keys.moving(Movement::Down) {
+            direction[1] -= 1.0;
+        }
+        if keys.moving(Movement::Right) {
+            direction[0] += 1.0;
+        }
+        if keys.moving(Movement::Left) {
+            direction[0] -= 1.0;
+        }
+
+        // Normalise so diagonal movement isn't faster, the stick can then only slow it down
+        let length = direction[0].hypot(direction[1]);
+        if length > 0.0 {
+            direction = [direction[0] / length, direction[1] / length];
+        }
+        direction = [direction[0] + stick[0], direction[1] + stick[1]];
+        let length = direction[0].hypot(direction[1]);
+        if length > 1.0 {
+            direction = [direction[0] / length, direction[1] / length];
+        }
+
+        let distance = Self::PAN_SPEED * dt / self.zoom;
+        [direction[0] * distance, direction[1] * distance]
+    }
+
+    // Zoom continuously from an analog input between -1 and 1, like holding a trigger
+    pub fn zoom_by(&mut self, rate: f32, dt: f32) {
+        self.scroll(rate * Self::TRIGGER_ZOOM_SPEED * dt);
+    }
+
+    // Zoom in for positive scroll lines and out for negative, around the centre of the screen
+    pub fn scroll(&mut self, lines: f32) {
+        self.scroll_at(lines, [0.0, 0.0]);
+    }
+
+    // Zoom while keeping the world point under `anchor` still, like a cursor. The anchor is in
+    // screen widths and heights from the centre, with up positive.
+    pub fn scroll_at(&mut self, lines: f32, anchor: [f32; 2]) {
+        self.scale_at(Self::ZOOM_STEP.powf(lines), anchor);
+    }
+
+    // Multiply the zoom by `factor` around `anchor`, like `scroll_at`
+    pub fn scale_at(&mut self, factor: f32, anchor: [f32; 2]) {
+        let zoom = (self.zoom * factor).clamp(Self::MIN_ZOOM, Self::MAX_ZOOM);
+        // The shader maps a screen point to `anchor / zoom + 0.5 + offset`, so move the offset by
+        // however much that point shifted
+        let shift = 1.0 / self.zoom - 1.0 / zoom;
+        self.offset[0] += anchor[0] * shift;
+        self.offset[1] += anchor[1] * shift;
+        self.zoom = zoom;
+    }
+
+    // Turn the view by a relative mouse movement in pixels, y runs down like the cursor. Pitch
+    // stops short of straight up and down so the direction never flips over.
+    pub fn look_by(&mut self, delta: [f32; 2]) {
+        let max_pitch = std::f32::consts::FRAC_PI_2 - 0.01;
+        self.look[0] =
+            (self.look[0] + delta[0] * Self::LOOK_SENSITIVITY).rem_euclid(std::f32::consts::TAU);
+        self.look[1] =
+            (self.look[1] - delta[1] * Self::LOOK_SENSITIVITY).clamp(-max_pitch, max_pitch);
+    }
+}
+
+// Eases the camera after a target with --follow instead of keeping it locked on, so it trails
+// behind a moving player and settles once they stop
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Follow {
+    // How quickly the camera catches up, the gap shrinks by a factor of e every 1 / damping seconds
+    pub damping: f32,
+    // Width and height of the box around the centre the target can move in without the camera
+    // following, in screen widths and heights
+    pub dead_zone: [f32; 2],
+}
+
+impl Follow {
+    // Move the camera `dt` seconds closer to having `target` inside the dead zone. The ease is the
+    // same however the time is split into frames.
+    pub fn update(&self, camera: &mut Camera, target: [f32; 2], dt: f32) {
+        let centre = camera.centre();
+        let ease = 1.0 - (-self.damping * dt).exp();
+        for axis in 0..2 {
+            // The shader shows 1 / zoom world units across the screen both ways
+            let half = 0.5 * self.dead_zone[axis] / camera.zoom;
+            let gap = target[axis] - centre[axis];
+            // Just far enough to bring the target back to the edge of the dead zone
+            let wanted = gap - gap.clamp(-half, half);
+            camera.offset[axis] += wanted * ease;
+        }
+    }
+}
+
+// The orthographic view the overlays are drawn with and the scene shader looks through. At zoom 1
+// the screen spans one world unit both ways whatever its aspect, with y pointing up.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Camera2D {
+    pub centre: [f32; 2],
+    pub zoom: f32,
+}
+
+impl Camera2D {
+    // World to clip space, in columns like WGSL's mat4x4<f32>
+    pub fn to_matrix(self) -> [[f32; 4]; 4] {
+        let scale = 2.0 * self.zoom;
+        [
+            [scale, 0.0, 0.0, 0.0],
+            [0.0, scale, 0.0, 0.0],
+            [0.0, 0.0, 1.0, 0.0],
+            [-scale * self.centre[0], -scale * self.centre[1], 0.0, 1.0],
+        ]
+    }
+
+    // Clip to world space, undoing `to_matrix` for shaders that start from the screen
+    pub fn to_inverse_matrix(self) -> [[f32; 4]; 4] {
+        let scale = 0.5 / self.zoom;
+        [
+            [scale, 0.0, 0.0, 0.0],
+            [0.0, scale, 0.0, 0.0],
+            [0.0, 0.0, 1.0, 0.0],
+            [self.centre[0], self.centre[1], 0.0, 1.0],
+        ]
+    }
+}
+
+// Flip a world to clip matrix's depth for --reverse-z, so the near plane lands on 1 and the far on
+// 0. Clip depth becomes w - z, which for these orthographic views is 1 - z.
+pub fn reverse_depth(matrix: [[f32; 4]; 4]) -> [[f32; 4]; 4] {
+    matrix.map(|[x, y, z, w]| [x, y, w - z, w])
+}
+
+// The same for a clip to world matrix, undoing the flip before it's applied
+pub fn reverse_depth_inverse(matrix: [[f32; 4]; 4]) -> [[f32; 4]; 4] {
+    let [x, y, z, w] = matrix;
+    [
+        x,
+        y,
+        z.map(|value| -value),
+        std::array::from_fn(|i| z[i] + w[i]),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // `matrix * point` for column major matrices
+    fn transform(matrix: [[f32; 4]; 4], point: [f32; 2]) -> [f32; 2] {
+        let v = [point[0], point[1], 0.0, 1.0];
+        let row = |i: usize| {
+            (0..4)
+                .map(|column| matrix[column][i] * v[column])
+                .sum::<f32>()
+        };
+        [row(0) / row(3), row(1) / row(3)]
+    }
+
+    #[test]
+    fn default_camera_shows_the_unit_square() {
+        let camera = Camera::default().to_2d();
+        let matrix = camera.to_matrix();
+        assert_eq!(transform(matrix, [0.5, 0.5]), [0.0, 0.0]);
+        assert_eq!(transform(matrix, [0.0, 0.0]), [-1.0, -1.0]);
+        assert_eq!(transform(matrix, [1.0, 1.0]), [1.0, 1.0]);
+    }
+
+    #[test]
+    fn zoom_and_pan_match_the_scene_shader() {
+        let mut camera = Camera::centred_on([3.0, -2.0]);
+        camera.zoom = 4.0;
+        let matrix = camera.to_2d().to_matrix();
+        assert_eq!(transform(matrix, [3.0, -2.0]), [0.0, 0.0]);
+        // A quarter of a world unit fills half the screen at zoom 4
+        assert_eq!(transform(matrix, [3.125, -1.875]), [1.0, 1.0]);
+        // The shader's `(tex_coords - 0.5) / zoom + 0.5 + offset` for the top right corner
+        let corner = 0.5 / camera.zoom;
+        assert_eq!(
+            transform(camera.to_2d().to_inverse_matrix(), [1.0, 1.0]),
+            [
+                corner + 0.5 + camera.offset[0],
+                corner + 0.5 + camera.offset[1]
+            ]
+        );
+    }
+
+    #[test]
+    fn inverse_undoes_the_matrix() {
+        let camera = Camera2D {
+            centre: [10.0, 0.25],
+            zoom: 0.5,
+        };
+        for point in [[0.0, 0.0], [10.0, 0.25], [-7.0, 3.5]] {
+            let clip = transform(camera.to_matrix(), point);
+            assert_eq!(transform(camera.to_inverse_matrix(), clip), point);
+        }
+    }
+
+    #[test]
+    fn follow_eases_towards_a_moving_target() {
+        let follow = Follow {
+            damping: 4.0,
+            dead_zone: [0.0, 0.0],
+        };
+        let mut camera = Camera::centred_on([0.0, 0.0]);
+        let mut target = [0.0, 0.0];
+        let mut last_gap = f32::INFINITY;
+        for _ in 0..60 {
+            target[0] += 0.01;
+            follow.update(&mut camera, target, 1.0 / 60.0);
+            let gap = target[0] - camera.centre()[0];
+            // Behind the target but never past it
+            assert!(gap > 0.0);
+            assert!(camera.centre()[1].abs() < 1e-6);
+            last_gap = gap;
+        }
+        // Moving at a steady speed the camera trails by about speed / damping
+        assert!((last_gap - 0.6 / 4.0).abs() < 0.02, "{last_gap}");
+        for _ in 0..600 {
+            follow.update(&mut camera, target, 1.0 / 60.0);
+        }
+        assert!((camera.centre()[0] - target[0]).abs() < 1e-4);
+
+        // Splitting the time differently ends up in the same place
+        let mut once = Camera::centred_on([0.0, 0.0]);
+        let mut twice = once;
+        follow.update(&mut once, [1.0, 2.0], 0.5);
+        follow.update(&mut twice, [1.0, 2.0], 0.25);
+        follow.update(&mut twice, [1.0, 2.0], 0.25);
+        for axis in 0..2 {
+            assert!((once.centre()[axis] - twice.centre()[axis]).abs() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn follow_ignores_moves_inside_the_dead_zone() {
+        let follow = Follow {
+            damping: 10.0,
+            dead_zone: [0.5, 0.25],
+        };
+        let mut camera = Camera::centred_on([0.0, 0.0]);
+        camera.zoom = 2.0;
+        // The dead zone spans 0.125 world units either side across and 0.0625 up and down
+        follow.update(&mut camera, [0.1, -0.05], 1.0);
+        assert_eq!(camera.centre(), [0.0, 0.0]);
+        // Leaving it, the camera settles with the target on its edge
+        for _ in 0..100 {
+            follow.update(&mut camera, [1.0, -1.0], 0.1);
+        }
+        let centre = camera.centre();
+        assert!((centre[0] - 0.875).abs() < 1e-4, "{centre:?}");
+        assert!((centre[1] + 0.9375).abs() < 1e-4, "{centre:?}");
+    }
+
+    // `matrix * point` with the depth kept
+    fn transform_depth(matrix: [[f32; 4]; 4], point: [f32; 3]) -> [f32; 3] {
+        let v = [point[0], point[1], point[2], 1.0];
+        let row = |i: usize| {
+            (0..4)
+                .map(|column| matrix[column][i] * v[column])
+                .sum::<f32>()
+        };
+        [row(0) / row(3), row(1) / row(3), row(2) / row(3)]
+    }
+
+    #[test]
+    fn reversed_depth_flips_only_depth() {
+        let camera = Camera::centred_on([2.0, 1.0]).to_2d();
+        let matrix = reverse_depth(camera.to_matrix());
+        let inverse = reverse_depth_inverse(camera.to_inverse_matrix());
+        for (point, depth) in [([2.0, 1.0, 0.0], 1.0), ([2.25, 0.5, 1.0], 0.0)] {
+            let clip = transform_depth(matrix, point);
+            assert_eq!(clip[2], depth);
+            assert_eq!(
+                [clip[0], clip[1]],
+                transform(camera.to_matrix(), [point[0], point[1]])
+            );
+            assert_eq!(transform_depth(inverse, clip), point);
+        }
+    }
+}
@@ -0,0 +1,59 @@
+// Draws the compute simulation's grid over the scene, living cells are opaque and the rest is
+// cut away
+
+// Must match GRID_SIZE in compute.rs
+const GRID_SIZE: u32 = 128u;
+
+// Same layout as shader.wgsl, only the camera is used here
+struct Uniforms {
+    resolution: vec2<f32>,
+    mouse: vec2<f32>,
+    time: f32,
+    dt: f32,
+    dpi: f32,
+    zoom: f32,
+    offset: vec2<f32>,
+    frame: u32,
+    aspect: f32,
+    params: vec4<f32>,
+    look: vec2<f32>,
+    click: vec2<f32>,
+    mouse_buttons: u32,
+    seed: u32,
+    padding: vec2<u32>,
+    view_projection: mat4x4<f32>,
+    inverse_view_projection: mat4x4<f32>,
+};
+@group(0) @binding(0) var<uniform> uniforms: Uniforms;
+@group(1) @binding(0)
+var<storage, read> cells: array<u32>;
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) tex_coords: vec2<f32>,
+};
+
+// One triangle covering the whole screen, the parts outside get clipped
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> VertexOutput {
+    let position = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+    var out: VertexOutput;
+    out.position = vec4<f32>(position * 2.0 - 1.0, 0.0, 1.0);
+    out.tex_coords = position;
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    // The same zoom and pan as the scene shader, the grid covers the world from 0 to 1
+    let clip = in.tex_coords * 2.0 - 1.0;
+    let world = (uniforms.inverse_view_projection * vec4<f32>(clip, 0.0, 1.0)).xy;
+    if any(world < vec2<f32>(0.0)) || any(world >= vec2<f32>(1.0)) {
+        discard;
+    }
+    let cell = vec2<u32>(world * f32(GRID_SIZE));
+    if cells[cell.y * GRID_SIZE + cell.x] == 0u {
+        discard;
+    }
+    return vec4<f32>(0.2, 0.9, 0.4, 1.0);
+}
@@ -0,0 +1,110 @@
+// Colours given on the command line, like --clear-color ff8800. Hex colours are sRGB, how they
+// should look on screen, while a target holding linear colour expects light values. These get
+// decoded for whichever target they go to, so they look the same with any surface format.
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Color {
+    pub r: f64,
+    pub g: f64,
+    pub b: f64,
+    pub a: f64,
+}
+
+impl Color {
+    pub const BLACK: Self = Self::opaque(0.0, 0.0, 0.0);
+    pub const WHITE: Self = Self::opaque(1.0, 1.0, 1.0);
+
+    pub const fn opaque(r: f64, g: f64, b: f64) -> Self {
+        Self { r, g, b, a: 1.0 }
+    }
+
+    // Parse a hex colour like ff8800 or #ff8800 into values between 0 and 1
+    pub fn from_hex(value: &str) -> Option<Self> {
+        let hex = value.strip_prefix('#').unwrap_or(value);
+        if hex.len() != 6 || !hex.bytes().all(|byte| byte.is_ascii_hexdigit()) {
+            return None;
+        }
+        let channel = |index: usize| {
+            u8::from_str_radix(&hex[index..index + 2], 16).map(|value| f64::from(value) / 255.0)
+        };
+        Some(Self::opaque(
+            channel(0).ok()?,
+            channel(2).ok()?,
+            channel(4).ok()?,
+        ))
+    }
+
+    // The same colour as light, alpha isn't encoded
+    pub fn to_linear(self) -> Self {
+        Self {
+            r: srgb_to_linear(self.r),
+            g: srgb_to_linear(self.g),
+            b: srgb_to_linear(self.b),
+            a: self.a,
+        }
+    }
+
+    // What to write to a target for the colour to look right. One holding linear colour, like an
+    // sRGB one that encodes on write or an HDR one, needs it decoded first.
+    pub fn for_target(self, linear: bool) -> wgpu::Color {
+        let Self { r, g, b, a } = if linear { self.to_linear() } else { self };
+        wgpu::Color { r, g, b, a }
+    }
+
+    // As presets keep it
+    #[cfg_attr(target_arch = "wasm32", allow(dead_code))] // Presets are native only
+    pub fn to_array(self) -> [f64; 4] {
+        [self.r, self.g, self.b, self.a]
+    }
+
+    #[cfg_attr(target_arch = "wasm32", allow(dead_code))] // Like `to_array`
+    pub fn from_array([r, g, b, a]: [f64; 4]) -> Self {
+        Self { r, g, b, a }
+    }
+}
+
+// The sRGB transfer function's inverse, from an encoded value between 0 and 1 to linear light
+pub fn srgb_to_linear(value: f64) -> f64 {
+    if value <= 0.04045 {
+        value / 12.92
+    } else {
+        ((value + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!((actual - expected).abs() < 1e-6, "{actual} != {expected}");
+    }
+
+    #[test]
+    fn decodes_known_srgb_values() {
+        assert_eq!(srgb_to_linear(0.0), 0.0);
+        assert_close(srgb_to_linear(1.0), 1.0);
+        // Either side of where the curve meets the straight segment
+        assert_close(srgb_to_linear(0.04045), 0.003_130_8);
+        assert_close(srgb_to_linear(0.5), 0.214_041_1);
+        // 0x80, the usual mid grey, is a little over a fifth of the light of white
+        assert_close(srgb_to_linear(128.0 / 255.0), 0.215_860_5);
+    }
+
+    #[test]
+    fn hex_colours_are_decoded_only_for_linear_targets() {
+        let orange = Color::from_hex("#ff8800").unwrap();
+        assert_eq!(orange, Color::opaque(1.0, 136.0 / 255.0, 0.0));
+        assert_eq!(orange, Color::from_hex("FF8800").unwrap());
+        let written = orange.for_target(false);
+        assert_eq!([written.r, written.g, written.b], [1.0, 136.0 / 255.0, 0.0]);
+        let linear = orange.for_target(true);
+        assert_close(linear.r, 1.0);
+        assert_close(linear.g, 0.246_201_2);
+        assert_eq!([linear.b, linear.a], [0.0, 1.0]);
+
+        for invalid in ["ff880", "#ff88000", "gg8800", ""] {
+            assert_eq!(Color::from_hex(invalid), None, "{invalid:?}");
+        }
+    }
+}
@@ -0,0 +1,210 @@
+// Optional GPU simulation with --compute, a cellular automaton stepped in a compute pass before
+// the scene is drawn and then shown over it
+use crate::state::{create_validated_render_pipeline, PipelineSettings};
+use anyhow::Context;
+use std::borrow::Cow;
+use wgpu::util::DeviceExt;
+
+// Cells along each side of the grid, must match compute.wgsl and cells.wgsl
+const GRID_SIZE: u32 = 128;
+// Invocations along each side of a workgroup, 8x8 stays under every backend's limit of 256
+const WORKGROUP_SIZE: u32 = 8;
+// Generations per second of shader time, stepping every frame is too fast to follow
+const STEPS_PER_SECOND: f32 = 15.0;
+
+pub struct Simulation {
+    compute_pipeline: wgpu::ComputePipeline,
+    render_pipeline: wgpu::RenderPipeline,
+    // Ping-pong pairs, step i reads buffer i and writes the other one
+    step_bind_groups: [wgpu::BindGroup; 2],
+    // Reading buffer i, for drawing whichever was written last
+    draw_bind_groups: [wgpu::BindGroup; 2],
+    // Which buffer holds the latest generation
+    current: usize,
+    // Shader time not yet turned into steps
+    accumulator: f32,
+    // Whether the next `step` call runs a generation
+    step_due: bool,
+}
+
+impl Simulation {
+    // `bind_group_layout` is the scene's, the overlay reads the camera from its uniforms
+    pub async fn new(
+        device: &wgpu::Device,
+        bind_group_layout: &wgpu::BindGroupLayout,
+        settings: &PipelineSettings,
+    ) -> anyhow::Result<Self> {
+        let cells = create_initial_cells();
+        let buffers = [0, 1].map(|index| {
+            device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some(["cells_a", "cells_b"][index]),
+                contents: bytemuck::cast_slice(&cells),
+                usage: wgpu::BufferUsages::STORAGE,
+            })
+        });
+
+        let step_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("step_bind_group_layout"),
+            entries: &[
+                storage_layout_entry(0, wgpu::ShaderStages::COMPUTE, true),
+                storage_layout_entry(1, wgpu::ShaderStages::COMPUTE, false),
+            ],
+        });
+        let step_bind_groups = [0, 1].map(|index| {
+            device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("step_bind_group"),
+                layout: &step_layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: buffers[index].as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: buffers[1 - index].as_entire_binding(),
+                    },
+                ],
+            })
+        });
+        let draw_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("cells_bind_group_layout"),
+            entries: &[storage_layout_entry(0, wgpu::ShaderStages::FRAGMENT, true)],
+        });
+        let draw_bind_groups = [0, 1].map(|index| {
+            device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("cells_bind_group"),
+                layout: &draw_layout,
+                entries: &[wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: buffers[index].as_entire_binding(),
+                }],
+            })
+        });
+
+        let compute_pipeline = create_compute_pipeline(device, &step_layout);
+        // Like the tile map, the overlay always reads the uniform buffer
+        let settings = PipelineSettings {
+            push_constants: false,
+            ..*settings
+        };
+        let render_pipeline = create_validated_render_pipeline(
+            device,
+            include_str!("cells.wgsl"),
+            &[bind_group_layout, &draw_layout],
+            &[],
+            &settings,
+        )
+        .await
+        .context("Failed to build the simulation overlay pipeline")?;
+
+        Ok(Self {
+            compute_pipeline,
+            render_pipeline,
+            step_bind_groups,
+            draw_bind_groups,
+            current: 0,
+            accumulator: 0.0,
+            step_due: false,
+        })
+    }
+
+    // Let `dt` seconds of shader time pass, pausing the clock pauses the simulation too
+    pub fn advance(&mut self, dt: f32) {
+        self.accumulator += dt * STEPS_PER_SECOND;
+        if self.accumulator >= 1.0 {
+            // At most one step per frame, dropping the rest after a stall
+            self.accumulator = (self.accumulator - 1.0).min(1.0);
+            self.step_due = true;
+        }
+    }
+
+    // Run a generation if one is due, must be encoded before the pass that draws the overlay
+    pub fn step(&mut self, encoder: &mut wgpu::CommandEncoder) {
+        if !std::mem::take(&mut self.step_due) {
+            return;
+        }
+        {
+            let mut cpass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                label: Some("simulation_pass"),
+            });
+            cpass.set_pipeline(&self.compute_pipeline);
+            cpass.set_bind_group(0, &self.step_bind_groups[self.current], &[]);
+            // Round up so a grid that isn't a multiple of the workgroup size is still covered,
+            // the shader skips the invocations past the edge
+            let workgroups = GRID_SIZE.div_ceil(WORKGROUP_SIZE);
+            cpass.dispatch_workgroups(workgroups, workgroups, 1);
+        }
+        self.current = 1 - self.current;
+    }
+
+    // Draw the latest generation, inside the scene's pass so it shares the depth and MSAA
+    // targets
+    pub fn draw<'a>(
+        &'a self,
+        rpass: &mut wgpu::RenderPass<'a>,
+        bind_group: &'a wgpu::BindGroup,
+        uniform_offset: u32,
+    ) {
+        rpass.set_pipeline(&self.render_pipeline);
+        rpass.set_bind_group(0, bind_group, &[uniform_offset]);
+        rpass.set_bind_group(1, &self.draw_bind_groups[self.current], &[]);
+        rpass.draw(0..3, 0..1);
+    }
+}
+
+// Compute shaders and the storage buffers they need are missing on WebGL2 and some older GPUs
+pub fn supports_compute(adapter: &wgpu::Adapter) -> bool {
+    adapter
+        .get_downlevel_capabilities()
+        .flags
+        .contains(wgpu::DownlevelFlags::COMPUTE_SHADERS)
+        && wgpu::Limits::downlevel_defaults().check_limits(&adapter.limits())
+}
+
+fn storage_layout_entry(
+    binding: u32,
+    visibility: wgpu::ShaderStages,
+    read_only: bool,
+) -> wgpu::BindGroupLayoutEntry {
+    wgpu::BindGroupLayoutEntry {
+        binding,
+        visibility,
+        ty: wgpu::BindingType::Buffer {
+            ty: wgpu::BufferBindingType::Storage { read_only },
+            has_dynamic_offset: false,
+            min_binding_size: wgpu::BufferSize::new(u64::from(GRID_SIZE * GRID_SIZE) * 4),
+        },
+        count: None,
+    }
+}
+
+fn create_compute_pipeline(
+    device: &wgpu::Device,
+    bind_group_layout: &wgpu::BindGroupLayout,
+) -> wgpu::ComputePipeline {
+    let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some("compute_shader"),
+        source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(include_str!("compute.wgsl"))),
+    });
+    let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+        label: Some("compute_pipeline_layout"),
+        bind_group_layouts: &[bind_group_layout],
+        push_constant_ranges: &[],
+    });
+    device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+        label: Some("compute_pipeline"),
+        layout: Some(&pipeline_layout),
+        module: &shader,
+        entry_point: "step",
+    })
+}
+
+// Roughly a third of the cells start alive, from a fixed hash so every run starts the same
+fn create_initial_cells() -> Vec<u32> {
+    (0..GRID_SIZE * GRID_SIZE)
+        .map(|index| {
+            let hash = index.wrapping_mul(2_654_435_761).rotate_left(13) ^ index;
+            u32::from(hash % 3 == 0)
+        })
+        .collect()
+}
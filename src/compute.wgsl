@@ -0,0 +1,38 @@
+// One generation of Conway's Game of Life, reading the current grid and writing the next
+
+// Must match GRID_SIZE in compute.rs
+const GRID_SIZE: u32 = 128u;
+
+// One u32 per cell, 1 for alive and 0 for dead, row by row from the bottom
+@group(0) @binding(0)
+var<storage, read> current: array<u32>;
+@group(0) @binding(1)
+var<storage, read_write> next: array<u32>;
+
+// The grid wraps around so the edges have neighbours too
+fn cell(x: i32, y: i32) -> u32 {
+    let size = i32(GRID_SIZE);
+    let wrapped = vec2<i32>((x + size) % size, (y + size) % size);
+    return current[u32(wrapped.y) * GRID_SIZE + u32(wrapped.x)];
+}
+
+// Has to be a literal here, keep it in step with WORKGROUP_SIZE in compute.rs
+@compute @workgroup_size(8, 8)
+fn step(@builtin(global_invocation_id) id: vec3<u32>) {
+    // The dispatch rounds up to whole workgroups, so some invocations fall off the grid
+    if id.x >= GRID_SIZE || id.y >= GRID_SIZE {
+        return;
+    }
+    let x = i32(id.x);
+    let y = i32(id.y);
+    var neighbours = 0u;
+    for (var dy = -1; dy <= 1; dy++) {
+        for (var dx = -1; dx <= 1; dx++) {
+            if dx != 0 || dy != 0 {
+                neighbours += cell(x + dx, y + dy);
+            }
+        }
+    }
+    let alive = cell(x, y) == 1u;
+    next[id.y * GRID_SIZE + id.x] = u32(neighbours == 3u || (alive && neighbours == 2u));
+}
@@ -0,0 +1,67 @@
+// Noticing the GPU device was lost, after a driver reset or update. wgpu 0.17 has no callback for
+// it, errors mention it instead and the calls that can't report errors panic.
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+// wgpu-core's wording, somewhere in the error's chain or the panic message
+const LOST_MESSAGE: &str = "device is lost";
+
+// The device is gone and everything made from it has to be created again
+#[derive(Debug, Clone, Copy)]
+pub struct DeviceLost;
+
+// Log uncaptured errors like `create_device_queue` does, and raise the returned flag when one
+// says the device was lost
+pub fn watch(device: &wgpu::Device) -> Arc<AtomicBool> {
+    let lost = Arc::new(AtomicBool::new(false));
+    let flag = lost.clone();
+    device.on_uncaptured_error(Box::new(move |error| {
+        log::error!("Uncaptured wgpu error: {error}");
+        let mut source: Option<&dyn std::error::Error> = Some(&error);
+        while let Some(error) = source {
+            if error.to_string().contains(LOST_MESSAGE) {
+                flag.store(true, Ordering::Relaxed);
+            }
+            source = error.source();
+        }
+    }));
+    lost
+}
+
+pub fn is_lost(flag: &AtomicBool) -> bool {
+    flag.load(Ordering::Relaxed)
+}
+
+// Run `f`, turning a panic about the lost device into an error. Other panics carry on
+// unwinding. Browsers abort on panic, so there's nothing to catch there.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn catch<T>(f: impl FnOnce() -> T) -> Result<T, DeviceLost> {
+    std::panic::catch_unwind(std::panic::AssertUnwindSafe(f)).map_err(|payload| {
+        let message = payload
+            .downcast_ref::<String>()
+            .map(String::as_str)
+            .or_else(|| payload.downcast_ref::<&str>().copied());
+        if message.is_some_and(|message| message.contains(LOST_MESSAGE)) {
+            DeviceLost
+        } else {
+            std::panic::resume_unwind(payload)
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lost_device_panic_is_caught() {
+        let result = catch(|| panic!("Error in Queue::submit: Parent device is lost"));
+        assert!(result.is_err());
+    }
+
+    #[test]
+    #[should_panic(expected = "something else")]
+    fn other_panics_keep_unwinding() {
+        let _ = catch(|| panic!("something else"));
+    }
+}
@@ -0,0 +1,124 @@
+// With --target-ms the scene is drawn smaller when frames take longer than the target and back up
+// when they're comfortably under it, and the post chain scales it up to fill the window. Each
+// change rebuilds the scene's targets, so the scale moves in coarse steps and only after a run of
+// frames has shown it should.
+
+// Scale as a percentage of the window's size, drawing below half looks too blurry to be worth it
+const MIN_PERCENT: u32 = 50;
+const MAX_PERCENT: u32 = 100;
+const STEP_PERCENT: u32 = 10;
+
+// Frames averaged for each decision. The count starts again after a change, so frames drawn at
+// the old scale don't count towards the next one.
+const SAMPLES: u32 = 30;
+
+// Scale up only when the frames it predicts would still be this far under the target, so going up
+// doesn't put them straight back over it and cause a step down again
+const HEADROOM: f32 = 0.9;
+
+#[derive(Debug, Clone, Copy)]
+pub struct DynamicResolution {
+    // Seconds a frame should take at most
+    target: f32,
+    percent: u32,
+    total: f32,
+    count: u32,
+}
+
+impl DynamicResolution {
+    pub fn new(target_ms: f32) -> Self {
+        Self {
+            target: target_ms / 1000.0,
+            percent: MAX_PERCENT,
+            total: 0.0,
+            count: 0,
+        }
+    }
+
+    // How much of the window's width and height the scene is drawn at, from 0.5 to 1
+    pub fn scale(&self) -> f32 {
+        self.percent as f32 / 100.0
+    }
+
+    // Count a frame that took `frame_time` seconds, stepping the scale once enough have been
+    pub fn record(&mut self, frame_time: f32) {
+        self.total += frame_time;
+        self.count += 1;
+        if self.count < SAMPLES {
+            return;
+        }
+        let average = self.total / self.count as f32;
+        self.total = 0.0;
+        self.count = 0;
+
+        let percent = if average > self.target {
+            self.percent.saturating_sub(STEP_PERCENT).max(MIN_PERCENT)
+        } else {
+            let up = (self.percent + STEP_PERCENT).min(MAX_PERCENT);
+            // The cost goes with the number of pixels, so with the square of the scale
+            let growth = (up as f32 / self.percent as f32).powi(2);
+            if average * growth < self.target * HEADROOM {
+                up
+            } else {
+                self.percent
+            }
+        };
+        if percent != self.percent {
+            log::info!(
+                "Frames averaged {:.2} ms for a {:.2} ms target, drawing the scene at {percent}%",
+                average * 1000.0,
+                self.target * 1000.0
+            );
+            self.percent = percent;
+        }
+    }
+}
+
+// `size` scaled by `scale`, never below a pixel
+pub fn scaled((width, height): (u32, u32), scale: f32) -> (u32, u32) {
+    let scale = |length: u32| ((length as f32 * scale).round() as u32).max(1);
+    (scale(width), scale(height))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(resolution: &mut DynamicResolution, frame_time: f32, frames: u32) {
+        for _ in 0..frames {
+            resolution.record(frame_time);
+        }
+    }
+
+    #[test]
+    fn slow_frames_step_down_to_the_limit() {
+        let mut resolution = DynamicResolution::new(16.0);
+        run(&mut resolution, 0.020, SAMPLES - 1);
+        assert_eq!(resolution.scale(), 1.0);
+        run(&mut resolution, 0.020, 1);
+        assert_eq!(resolution.scale(), 0.9);
+        run(&mut resolution, 0.040, SAMPLES * 10);
+        assert_eq!(resolution.scale(), 0.5);
+    }
+
+    #[test]
+    fn steps_up_only_with_headroom() {
+        let mut resolution = DynamicResolution::new(16.0);
+        run(&mut resolution, 0.020, SAMPLES * 2);
+        assert_eq!(resolution.scale(), 0.8);
+        // 80% to 90% is about 27% more pixels, 13 ms would become 16.5 and go over again
+        run(&mut resolution, 0.013, SAMPLES * 5);
+        assert_eq!(resolution.scale(), 0.8);
+        run(&mut resolution, 0.010, SAMPLES);
+        assert_eq!(resolution.scale(), 0.9);
+        run(&mut resolution, 0.005, SAMPLES * 5);
+        assert_eq!(resolution.scale(), 1.0);
+    }
+
+    #[test]
+    fn scaled_sizes_round_and_stay_positive() {
+        assert_eq!(scaled((1280, 720), 0.5), (640, 360));
+        assert_eq!(scaled((1279, 721), 0.9), (1151, 649));
+        assert_eq!(scaled((1, 1), 0.5), (1, 1));
+    }
+}
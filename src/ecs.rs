@@ -0,0 +1,326 @@
+// Entities and their components, the game's logic runs on these and knows nothing about drawing.
+// Each component type has a column indexed by entity, which is plenty for a few thousand.
+use crate::save;
+
+// A handle to an entity, the generation tells a despawned entity from the next one in its slot
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Entity {
+    index: u32,
+    generation: u32,
+}
+
+impl Entity {
+    // What its sprite writes to the id target with --picking, 0 is left for nothing
+    pub fn pick_id(self) -> u32 {
+        self.index + 1
+    }
+}
+
+// Where an entity is, in world units like the camera's centre
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Transform {
+    pub position: [f32; 2],
+    // Counterclockwise radians
+    pub rotation: f32,
+    // Multiplies the size of a tile
+    pub scale: f32,
+}
+
+impl Transform {
+    pub fn at(position: [f32; 2]) -> Self {
+        Self {
+            position,
+            rotation: 0.0,
+            scale: 1.0,
+        }
+    }
+
+    // `t` of the way from `self` to `other`
+    pub fn lerp(&self, other: &Self, t: f32) -> Self {
+        let mix = |from: f32, to: f32| from + (to - from) * t;
+        Self {
+            position: [
+                mix(self.position[0], other.position[0]),
+                mix(self.position[1], other.position[1]),
+            ],
+            rotation: mix(self.rotation, other.rotation),
+            scale: mix(self.scale, other.scale),
+        }
+    }
+}
+
+// Drawn with an atlas tile, see atlas.rs
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Sprite {
+    pub tile: u32,
+}
+
+// World units per second
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Velocity(pub [f32; 2]);
+
+pub trait Component: Sized {
+    #[allow(dead_code)] // Only reading components by entity uses it, see below
+    fn column(world: &World) -> &Vec<Option<Self>>;
+    fn column_mut(world: &mut World) -> &mut Vec<Option<Self>>;
+}
+
+macro_rules! components {
+    ($($component:ty => $column:ident),* $(,)?) => {
+        #[derive(Debug, Default)]
+        pub struct World {
+            // Current generation of each slot, odd while the slot is free
+            generations: Vec<u32>,
+            // Slots to reuse before growing the columns
+            free: Vec<u32>,
+            // Where everything was before the last logic step, drawing blends towards the
+            // transforms from there
+            previous_transforms: Vec<Option<Transform>>,
+            $($column: Vec<Option<$component>>,)*
+        }
+
+        $(impl Component for $component {
+            fn column(world: &World) -> &Vec<Option<Self>> {
+                &world.$column
+            }
+
+            fn column_mut(world: &mut World) -> &mut Vec<Option<Self>> {
+                &mut world.$column
+            }
+        })*
+
+        impl World {
+            // Clear every component of the slot at `index`
+            #[allow(dead_code)] // For despawning, see below
+            fn clear_slot(&mut self, index: usize) {
+                self.previous_transforms[index] = None;
+                $(self.$column[index] = None;)*
+            }
+
+            // Add a slot to every column
+            fn push_slot(&mut self) {
+                self.previous_transforms.push(None);
+                $(self.$column.push(None);)*
+            }
+        }
+    };
+}
+
+components!(
+    Transform => transforms,
+    Sprite => sprites,
+    Velocity => velocities,
+);
+
+impl World {
+    // The entities of a saved world, with the sprites they're drawn with
+    pub fn from_save(saved: &[save::Entity]) -> Self {
+        let mut world = Self::default();
+        for entity in saved {
+            let spawned = world.spawn();
+            world.insert(spawned, Transform::at(entity.position));
+            world.insert(
+                spawned,
+                Sprite {
+                    tile: entity.sprite,
+                },
+            );
+        }
+        world
+    }
+
+    // A new entity with no components
+    pub fn spawn(&mut self) -> Entity {
+        match self.free.pop() {
+            Some(index) => {
+                let generation = &mut self.generations[index as usize];
+                *generation += 1;
+                Entity {
+                    index,
+                    generation: *generation,
+                }
+            }
+            None => {
+                let index = self.generations.len() as u32;
+                self.generations.push(0);
+                self.push_slot();
+                Entity {
+                    index,
+                    generation: 0,
+                }
+            }
+        }
+    }
+
+    pub fn contains(&self, entity: Entity) -> bool {
+        self.generations.get(entity.index as usize) == Some(&entity.generation)
+    }
+
+    // Add or replace a component, ignored for an entity that's gone
+    pub fn insert<C: Component>(&mut self, entity: Entity, component: C) {
+        if self.contains(entity) {
+            C::column_mut(self)[entity.index as usize] = Some(component);
+        }
+    }
+
+    // Remember the transforms as they are, call before each logic step
+    pub fn begin_step(&mut self) {
+        self.previous_transforms.clone_from(&self.transforms);
+    }
+
+    // Every entity that's drawn, in the order they were spawned, `alpha` of the way from before
+    // the last logic step to after it. Entities spawned since are where they are.
+    pub fn sprites(&self, alpha: f32) -> impl Iterator<Item = (Entity, Transform, &Sprite)> {
+        self.transforms
+            .iter()
+            .zip(&self.previous_transforms)
+            .zip(&self.sprites)
+            .enumerate()
+            .filter_map(move |(index, ((transform, previous), sprite))| {
+                let transform = transform.as_ref()?;
+                let drawn = previous.map_or(*transform, |previous| previous.lerp(transform, alpha));
+                let entity = Entity {
+                    index: index as u32,
+                    generation: self.generations[index],
+                };
+                Some((entity, drawn, sprite.as_ref()?))
+            })
+    }
+
+    // The entity in the slot whose sprite wrote `id`, None if it's been despawned since
+    #[cfg_attr(target_arch = "wasm32", allow(dead_code))] // Picking is native only
+    pub fn picked(&self, id: u32) -> Option<Entity> {
+        let index = id.checked_sub(1)?;
+        let entity = Entity {
+            index,
+            generation: *self.generations.get(index as usize)?,
+        };
+        // The slot may have been freed since the frame was drawn
+        entity.generation.is_multiple_of(2).then_some(entity)
+    }
+}
+
+// Looking up and removing single entities, nothing in the game does it yet
+#[allow(dead_code)]
+impl World {
+    // Remove an entity and its components, false if it was already gone
+    pub fn despawn(&mut self, entity: Entity) -> bool {
+        if !self.contains(entity) {
+            return false;
+        }
+        self.generations[entity.index as usize] += 1;
+        self.clear_slot(entity.index as usize);
+        self.free.push(entity.index);
+        true
+    }
+
+    pub fn remove<C: Component>(&mut self, entity: Entity) -> Option<C> {
+        if !self.contains(entity) {
+            return None;
+        }
+        C::column_mut(self)[entity.index as usize].take()
+    }
+
+    pub fn get<C: Component>(&self, entity: Entity) -> Option<&C> {
+        if !self.contains(entity) {
+            return None;
+        }
+        C::column(self)[entity.index as usize].as_ref()
+    }
+
+    pub fn get_mut<C: Component>(&mut self, entity: Entity) -> Option<&mut C> {
+        if !self.contains(entity) {
+            return None;
+        }
+        C::column_mut(self)[entity.index as usize].as_mut()
+    }
+}
+
+// Move everything with a velocity, `dt` is in seconds
+pub fn system_movement(world: &mut World, dt: f32) {
+    for (transform, velocity) in world.transforms.iter_mut().zip(&world.velocities) {
+        if let (Some(transform), Some(Velocity([x, y]))) = (transform, velocity) {
+            transform.position[0] += x * dt;
+            transform.position[1] += y * dt;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn movement_moves_only_entities_with_velocity() {
+        let mut world = World::default();
+        let moving = world.spawn();
+        world.insert(moving, Transform::at([0.0, 0.0]));
+        world.insert(moving, Velocity([1.0, -2.0]));
+        let still = world.spawn();
+        world.insert(still, Transform::at([0.5, 0.5]));
+        for _ in 0..4 {
+            system_movement(&mut world, 0.25);
+        }
+        assert_eq!(
+            world.get::<Transform>(moving).unwrap().position,
+            [1.0, -2.0]
+        );
+        assert_eq!(world.get::<Transform>(still).unwrap().position, [0.5, 0.5]);
+    }
+
+    #[test]
+    fn despawned_handles_go_stale() {
+        let mut world = World::default();
+        let first = world.spawn();
+        world.insert(first, Sprite { tile: 1 });
+        assert!(world.despawn(first));
+        let second = world.spawn();
+        assert!(!world.contains(first));
+        assert!(world.get::<Sprite>(first).is_none());
+        assert!(world.get::<Sprite>(second).is_none());
+        assert!(!world.despawn(first));
+    }
+
+    #[test]
+    fn pick_ids_find_live_entities() {
+        let mut world = World::default();
+        world.spawn();
+        let second = world.spawn();
+        world.insert(second, Transform::at([0.0, 0.0]));
+        world.insert(second, Sprite { tile: 1 });
+        let (entity, _, _) = world.sprites(1.0).next().unwrap();
+        let id = entity.pick_id();
+        assert_eq!(world.picked(id), Some(second));
+        world.despawn(second);
+        assert_eq!(world.picked(id), None);
+        assert_eq!(world.picked(0), None);
+    }
+
+    #[test]
+    fn sprites_need_a_transform() {
+        let mut world = World::default();
+        let drawn = world.spawn();
+        world.insert(drawn, Transform::at([1.0, 2.0]));
+        world.insert(drawn, Sprite { tile: 3 });
+        let hidden = world.spawn();
+        world.insert(hidden, Sprite { tile: 4 });
+        let sprites: Vec<_> = world
+            .sprites(1.0)
+            .map(|(_, _, sprite)| sprite.tile)
+            .collect();
+        assert_eq!(sprites, [3]);
+    }
+
+    #[test]
+    fn sprites_blend_between_steps() {
+        let mut world = World::default();
+        let moving = world.spawn();
+        world.insert(moving, Transform::at([0.0, 0.0]));
+        world.insert(moving, Velocity([4.0, 0.0]));
+        world.insert(moving, Sprite { tile: 1 });
+        world.begin_step();
+        system_movement(&mut world, 0.5);
+        let (_, halfway, _) = world.sprites(0.25).next().unwrap();
+        assert_eq!(halfway.position, [0.5, 0.0]);
+    }
+}
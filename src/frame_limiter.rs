@@ -0,0 +1,61 @@
+// Paces continuous rendering to a target frame rate on top of whatever the present mode does
+use instant::{Duration, Instant};
+
+pub struct FrameLimiter {
+    interval: Duration,
+    next_frame: Instant,
+}
+
+impl FrameLimiter {
+    pub fn new(max_fps: u32, now: Instant) -> Self {
+        Self {
+            interval: Duration::from_secs(1) / max_fps.max(1),
+            next_frame: now,
+        }
+    }
+
+    // When the event loop should wake up for the next frame
+    pub fn deadline(&self) -> Instant {
+        self.next_frame
+    }
+
+    // Whether a frame is due, scheduling the one after it if so
+    pub fn ready(&mut self, now: Instant) -> bool {
+        if now < self.next_frame {
+            return false;
+        }
+        // Step from the previous deadline rather than from now so wake-up latency doesn't add up,
+        // unless we've fallen a whole frame behind and catching up would mean a burst of frames
+        self.next_frame += self.interval;
+        if self.next_frame < now {
+            self.next_frame = now + self.interval;
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn waits_for_the_deadline() {
+        let start = Instant::now();
+        let mut limiter = FrameLimiter::new(50, start);
+        assert!(limiter.ready(start));
+        assert!(!limiter.ready(start + Duration::from_millis(19)));
+        assert!(limiter.ready(start + Duration::from_millis(20)));
+        assert_eq!(limiter.deadline(), start + Duration::from_millis(40));
+    }
+
+    #[test]
+    fn does_not_catch_up_after_a_stall() {
+        let start = Instant::now();
+        let mut limiter = FrameLimiter::new(100, start);
+        assert!(limiter.ready(start));
+        let late = start + Duration::from_millis(500);
+        assert!(limiter.ready(late));
+        assert!(!limiter.ready(late + Duration::from_millis(5)));
+        assert_eq!(limiter.deadline(), late + Duration::from_millis(10));
+    }
+}
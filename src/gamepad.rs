@@ -0,0 +1,87 @@
+// Controller input through gilrs, mirroring the keyboard and scroll wheel camera controls
+use gilrs::{Axis, Button, EventType, GamepadId, Gilrs};
+
+// Stick deflection below this counts as centred, worn sticks rarely rest at exactly zero
+const DEAD_ZONE: f32 = 0.15;
+
+pub struct Gamepads {
+    gilrs: Gilrs,
+    // The controller used most recently, its sticks and triggers drive the camera
+    active: Option<GamepadId>,
+}
+
+impl Gamepads {
+    // None when the platform's gamepad API isn't available
+    pub fn new() -> Option<Self> {
+        match Gilrs::new() {
+            Ok(gilrs) => {
+                let active = gilrs.gamepads().next().map(|(id, _)| id);
+                Some(Self { gilrs, active })
+            }
+            Err(error) => {
+                log::warn!("Gamepad support unavailable: {error}");
+                None
+            }
+        }
+    }
+
+    // Drain pending events without blocking, following controllers as they're plugged in and out
+    pub fn poll(&mut self) {
+        while let Some(event) = self.gilrs.next_event() {
+            match event.event {
+                EventType::Connected => {
+                    log::info!("Connected gamepad {}", self.gilrs.gamepad(event.id).name());
+                    self.active.get_or_insert(event.id);
+                }
+                EventType::Disconnected => {
+                    log::info!(
+                        "Disconnected gamepad {}",
+                        self.gilrs.gamepad(event.id).name()
+                    );
+                    if self.active == Some(event.id) {
+                        self.active = self
+                            .gilrs
+                            .gamepads()
+                            .map(|(id, _)| id)
+                            .find(|&id| id != event.id);
+                    }
+                }
+                _ => self.active = Some(event.id),
+            }
+        }
+    }
+
+    // Left stick position with the dead zone removed, up and right are positive
+    pub fn movement(&self) -> [f32; 2] {
+        self.active_gamepad().map_or([0.0, 0.0], |gamepad| {
+            apply_dead_zone([
+                gamepad.value(Axis::LeftStickX),
+                gamepad.value(Axis::LeftStickY),
+            ])
+        })
+    }
+
+    // Right trigger zooms in and left zooms out, from -1 to 1
+    pub fn zoom(&self) -> f32 {
+        self.active_gamepad().map_or(0.0, |gamepad| {
+            let trigger = |button| gamepad.button_data(button).map_or(0.0, |data| data.value());
+            trigger(Button::RightTrigger2) - trigger(Button::LeftTrigger2)
+        })
+    }
+
+    fn active_gamepad(&self) -> Option<gilrs::Gamepad<'_>> {
+        self.active
+            .map(|id| self.gilrs.gamepad(id))
+            .filter(gilrs::Gamepad::is_connected)
+    }
+}
+
+// Radial dead zone, rescaled so movement still starts from zero at its edge
+fn apply_dead_zone(stick: [f32; 2]) -> [f32; 2] {
+    let length = stick[0].hypot(stick[1]);
+    if length <= DEAD_ZONE {
+        return [0.0, 0.0];
+    }
+    let scale = ((length - DEAD_ZONE) / (1.0 - DEAD_ZONE)).min(1.0) / length;
+    [stick[0] * scale, stick[1] * scale]
+}
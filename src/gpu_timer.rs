@@ -0,0 +1,199 @@
+// Measures how long the GPU spends on a frame with timestamp queries, and with --profile on each
+// of its passes
+use std::sync::mpsc;
+
+// The parts of a frame --profile times, in the order they're encoded. Each is timed from the end
+// of the one before, so one that doesn't run that frame comes out as zero.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Pass {
+    Compute,
+    Particles,
+    Scene,
+    Post,
+    Hud,
+    Minimap,
+    Screenshot,
+    // Last, it ends with the frame
+    Ui,
+}
+
+impl Pass {
+    pub const ALL: [Self; 8] = [
+        Self::Compute,
+        Self::Particles,
+        Self::Scene,
+        Self::Post,
+        Self::Hud,
+        Self::Minimap,
+        Self::Screenshot,
+        Self::Ui,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Compute => "compute",
+            Self::Particles => "particles",
+            Self::Scene => "scene",
+            Self::Post => "post",
+            Self::Hud => "HUD",
+            Self::Minimap => "minimap",
+            Self::Screenshot => "screenshot",
+            Self::Ui => "UI",
+        }
+    }
+}
+
+// Seconds the GPU spent on a frame, and on each pass in `Pass::ALL` with --profile
+pub struct GpuTime {
+    pub frame: f32,
+    pub passes: Option<[f32; Pass::ALL.len()]>,
+}
+
+// Timestamps are read back asynchronously, so only one measurement is in flight at a time
+enum Stage {
+    Idle,
+    Recording,
+    Mapping(mpsc::Receiver<Result<(), wgpu::BufferAsyncError>>),
+}
+
+pub struct GpuTimer {
+    query_set: wgpu::QuerySet,
+    resolve_buffer: wgpu::Buffer,
+    readback_buffer: wgpu::Buffer,
+    // Nanoseconds per timestamp tick
+    period: f32,
+    // Timestamps the frame before every pass as well as after the last, or just the frame's start
+    // and end
+    profile: bool,
+    stage: Stage,
+}
+
+impl GpuTimer {
+    const MAX_QUERIES: usize = Pass::ALL.len() + 1;
+
+    // None when the device wasn't created with timestamp query support
+    pub fn new(device: &wgpu::Device, queue: &wgpu::Queue, profile: bool) -> Option<Self> {
+        if !device.features().contains(wgpu::Features::TIMESTAMP_QUERY) {
+            return None;
+        }
+        let query_count = if profile { Self::MAX_QUERIES } else { 2 };
+        let query_set = device.create_query_set(&wgpu::QuerySetDescriptor {
+            label: Some("frame_timestamps"),
+            ty: wgpu::QueryType::Timestamp,
+            count: query_count as u32,
+        });
+        let buffer_size = (query_count * wgpu::QUERY_SIZE as usize) as wgpu::BufferAddress;
+        let resolve_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("timestamp_resolve_buffer"),
+            size: buffer_size,
+            usage: wgpu::BufferUsages::QUERY_RESOLVE | wgpu::BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+        let readback_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("timestamp_readback_buffer"),
+            size: buffer_size,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+        Some(Self {
+            query_set,
+            resolve_buffer,
+            readback_buffer,
+            period: queue.get_timestamp_period(),
+            profile,
+            stage: Stage::Idle,
+        })
+    }
+
+    fn query_count(&self) -> u32 {
+        if self.profile {
+            Self::MAX_QUERIES as u32
+        } else {
+            2
+        }
+    }
+
+    // Mark the start of the frame's GPU work, skipped while the last measurement is being read
+    pub fn begin(&mut self, encoder: &mut wgpu::CommandEncoder) {
+        if let Stage::Idle = self.stage {
+            encoder.write_timestamp(&self.query_set, 0);
+            self.stage = Stage::Recording;
+        }
+    }
+
+    // Mark the end of `pass` with --profile, between `begin` and `end` and outside any pass.
+    // `Pass::Ui` is left to `end`.
+    pub fn end_pass(&self, encoder: &mut wgpu::CommandEncoder, pass: Pass) {
+        if let (true, Stage::Recording) = (self.profile, &self.stage) {
+            let index = Pass::ALL
+                .iter()
+                .position(|&other| other == pass)
+                .unwrap_or(0);
+            encoder.write_timestamp(&self.query_set, index as u32 + 1);
+        }
+    }
+
+    // Mark the end of the frame's GPU work and queue the copy out of the query set
+    pub fn end(&mut self, encoder: &mut wgpu::CommandEncoder) {
+        let Stage::Recording = self.stage else {
+            return;
+        };
+        let query_count = self.query_count();
+        encoder.write_timestamp(&self.query_set, query_count - 1);
+        encoder.resolve_query_set(&self.query_set, 0..query_count, &self.resolve_buffer, 0);
+        encoder.copy_buffer_to_buffer(
+            &self.resolve_buffer,
+            0,
+            &self.readback_buffer,
+            0,
+            self.readback_buffer.size(),
+        );
+    }
+
+    // Start mapping the timestamps, must be called after the encoder from `end` is submitted
+    pub fn after_submit(&mut self) {
+        let Stage::Recording = self.stage else {
+            return;
+        };
+        let (sender, receiver) = mpsc::channel();
+        self.readback_buffer
+            .slice(..)
+            .map_async(wgpu::MapMode::Read, move |result| {
+                sender.send(result).ok();
+            });
+        self.stage = Stage::Mapping(receiver);
+    }
+
+    // What the GPU took for the last measured frame, once the readback has finished
+    pub fn read(&mut self, device: &wgpu::Device) -> Option<GpuTime> {
+        let Stage::Mapping(receiver) = &self.stage else {
+            return None;
+        };
+        device.poll(wgpu::Maintain::Poll);
+        match receiver.try_recv() {
+            Ok(Ok(())) => {}
+            Err(mpsc::TryRecvError::Empty) => return None,
+            // Mapping failed, drop this measurement and take another
+            Ok(Err(_)) | Err(mpsc::TryRecvError::Disconnected) => {
+                self.stage = Stage::Idle;
+                return None;
+            }
+        }
+        let timestamps: Vec<u64> = {
+            let data = self.readback_buffer.slice(..).get_mapped_range();
+            data.chunks_exact(wgpu::QUERY_SIZE as usize)
+                .map(bytemuck::pod_read_unaligned)
+                .collect()
+        };
+        self.readback_buffer.unmap();
+        self.stage = Stage::Idle;
+        let seconds = |start: u64, end: u64| end.saturating_sub(start) as f32 * self.period / 1e9;
+        let (first, last) = (timestamps[0], timestamps[timestamps.len() - 1]);
+        Some(GpuTime {
+            frame: seconds(first, last),
+            passes: self
+                .profile
+                .then(|| std::array::from_fn(|i| seconds(timestamps[i], timestamps[i + 1]))),
+        })
+    }
+}
@@ -0,0 +1,194 @@
+// A render graph in miniature. Each pass names what it reads and writes, and `schedule` runs every
+// writer before its readers and finds textures for what's written. A resource one pass writes and
+// another draws over gets a new name for the result, so each is written once and the order follows
+// from the names alone.
+use std::collections::HashMap;
+use std::hash::Hash;
+
+#[derive(Debug, Clone)]
+pub struct Node<P, R> {
+    pub pass: P,
+    pub reads: Vec<R>,
+    pub writes: Vec<R>,
+}
+
+impl<P, R> Node<P, R> {
+    pub fn new(pass: P, reads: impl Into<Vec<R>>, writes: impl Into<Vec<R>>) -> Self {
+        Self {
+            pass,
+            reads: reads.into(),
+            writes: writes.into(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Schedule<P, R> {
+    // The passes in an order they can run in
+    pub order: Vec<P>,
+    // The texture each resource written in the graph is drawn into, numbered from 0. A texture
+    // is handed on once everything reading what it holds has run, so two resources only share one
+    // when they're never needed at the same time.
+    pub slots: HashMap<R, usize>,
+    pub slot_count: usize,
+}
+
+// Order `nodes` so every pass comes after the ones writing what it reads, keeping the order
+// they're given in where it's free to. Resources nothing writes come from outside the graph, as do
+// the `external` ones it writes, like the surface, and neither get a slot. Slots are only shared
+// between resources of the same kind, so a graph should hold one kind of texture or share none.
+pub fn schedule<P: Copy + Eq + std::fmt::Debug, R: Copy + Eq + Hash + std::fmt::Debug>(
+    nodes: &[Node<P, R>],
+    external: &[R],
+) -> anyhow::Result<Schedule<P, R>> {
+    let mut writers = HashMap::new();
+    for (index, node) in nodes.iter().enumerate() {
+        for &resource in &node.writes {
+            if let Some(other) = writers.insert(resource, index) {
+                anyhow::bail!(
+                    "{:?} and {:?} both write {resource:?}, give one of them a new resource to \
+                     write",
+                    nodes[other].pass,
+                    node.pass
+                );
+            }
+        }
+    }
+
+    // Kahn's algorithm, taking the first ready pass each time so independent passes stay in order
+    let dependencies: Vec<Vec<usize>> = nodes
+        .iter()
+        .enumerate()
+        .map(|(index, node)| {
+            let mut dependencies: Vec<usize> = node
+                .reads
+                .iter()
+                .filter_map(|resource| writers.get(resource).copied())
+                .filter(|&writer| writer != index)
+                .collect();
+            dependencies.sort_unstable();
+            dependencies.dedup();
+            dependencies
+        })
+        .collect();
+    let mut done = vec![false; nodes.len()];
+    let mut order = Vec::with_capacity(nodes.len());
+    while order.len() < nodes.len() {
+        let Some(next) = (0..nodes.len()).find(|&index| {
+            !done[index]
+                && dependencies[index]
+                    .iter()
+                    .all(|&dependency| done[dependency])
+        }) else {
+            let stuck: Vec<P> = (0..nodes.len())
+                .filter(|&index| !done[index])
+                .map(|index| nodes[index].pass)
+                .collect();
+            anyhow::bail!("The passes {stuck:?} depend on each other in a cycle");
+        };
+        done[next] = true;
+        order.push(next);
+    }
+
+    // When each resource is last read, a slot is free again after that pass
+    let mut last_read = HashMap::new();
+    for (step, &index) in order.iter().enumerate() {
+        for &resource in &nodes[index].reads {
+            last_read.insert(resource, step);
+        }
+    }
+    let mut slots = HashMap::new();
+    let mut free = Vec::new();
+    let mut slot_count = 0;
+    for (step, &index) in order.iter().enumerate() {
+        let node = &nodes[index];
+        // Taken before the pass's own reads are freed, a pass never writes what it's reading
+        for &resource in &node.writes {
+            if external.contains(&resource) {
+                continue;
+            }
+            let slot = free.pop().unwrap_or_else(|| {
+                slot_count += 1;
+                slot_count - 1
+            });
+            slots.insert(resource, slot);
+        }
+        for resource in node.reads.iter().chain(&node.writes) {
+            let finished = last_read.get(resource).is_none_or(|&last| last <= step);
+            if let (true, Some(&slot)) = (finished, slots.get(resource)) {
+                if !free.contains(&slot) {
+                    free.push(slot);
+                }
+            }
+        }
+    }
+
+    Ok(Schedule {
+        order: order.into_iter().map(|index| nodes[index].pass).collect(),
+        slots,
+        slot_count,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Where `pass` comes in the order
+    fn position(schedule: &Schedule<&str, &str>, pass: &str) -> usize {
+        schedule
+            .order
+            .iter()
+            .position(|&other| other == pass)
+            .unwrap()
+    }
+
+    #[test]
+    fn a_diamond_runs_both_sides_between_its_ends() {
+        // Declared out of order, the join first
+        let nodes = [
+            Node::new("join", ["left", "right"], ["output"]),
+            Node::new("right", ["top"], ["right"]),
+            Node::new("top", [], ["top"]),
+            Node::new("left", ["top"], ["left"]),
+        ];
+        let schedule = schedule(&nodes, &["output"]).unwrap();
+        assert_eq!(schedule.order.len(), nodes.len());
+        for node in &nodes {
+            for read in &node.reads {
+                let writer = nodes.iter().find(|other| other.writes.contains(read));
+                let writer = writer.unwrap().pass;
+                assert!(position(&schedule, writer) < position(&schedule, node.pass));
+            }
+        }
+        // The sides keep the order they were given in
+        assert_eq!(schedule.order, ["top", "right", "left", "join"]);
+        // Both sides are read together, so neither can reuse the top's texture until they've run
+        assert_eq!(schedule.slot_count, 3);
+        assert!(!schedule.slots.contains_key("output"));
+    }
+
+    #[test]
+    fn textures_are_reused_along_a_chain() {
+        let nodes = [
+            Node::new("scene", [], ["scene"]),
+            Node::new("bloom", ["scene"], ["bloom"]),
+            Node::new("blur", ["bloom"], ["blur"]),
+            Node::new("tonemap", ["blur"], ["surface"]),
+        ];
+        let schedule = schedule(&nodes, &["surface"]).unwrap();
+        assert_eq!(schedule.order, ["scene", "bloom", "blur", "tonemap"]);
+        // Ping-ponging between two
+        assert_eq!(schedule.slot_count, 2);
+        assert_eq!(schedule.slots["scene"], schedule.slots["blur"]);
+        assert_ne!(schedule.slots["scene"], schedule.slots["bloom"]);
+    }
+
+    #[test]
+    fn cycles_and_double_writes_are_errors() {
+        let cycle = [Node::new("a", ["b"], ["a"]), Node::new("b", ["a"], ["b"])];
+        assert!(schedule(&cycle, &[]).is_err());
+        let twice = [Node::new("a", [], ["x"]), Node::new("b", [], ["x"])];
+        assert!(schedule(&twice, &[]).is_err());
+    }
+}
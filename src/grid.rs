@@ -0,0 +1,46 @@
+// A world space grid drawn over everything else in the scene, a line along every tile edge and a
+// heavier one every ten, for lining things up while building the world. G toggles it.
+use crate::state::{create_validated_render_pipeline, PipelineSettings};
+use anyhow::Context;
+
+pub struct Grid {
+    render_pipeline: wgpu::RenderPipeline,
+}
+
+impl Grid {
+    // `bind_group_layout` is the scene's, the grid reads the camera from its uniforms
+    pub async fn new(
+        device: &wgpu::Device,
+        bind_group_layout: &wgpu::BindGroupLayout,
+        settings: &PipelineSettings,
+    ) -> anyhow::Result<Self> {
+        // Blended over the scene whether or not the window is transparent
+        let settings = PipelineSettings {
+            push_constants: false,
+            blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+            ..*settings
+        };
+        let render_pipeline = create_validated_render_pipeline(
+            device,
+            include_str!("grid.wgsl"),
+            &[bind_group_layout],
+            &[],
+            &settings,
+        )
+        .await
+        .context("Failed to build the grid pipeline")?;
+        Ok(Self { render_pipeline })
+    }
+
+    // One fullscreen triangle, last in the scene's pass so it's over the world and particles
+    pub fn draw<'a>(
+        &'a self,
+        rpass: &mut wgpu::RenderPass<'a>,
+        bind_group: &'a wgpu::BindGroup,
+        uniform_offset: u32,
+    ) {
+        rpass.set_pipeline(&self.render_pipeline);
+        rpass.set_bind_group(0, bind_group, &[uniform_offset]);
+        rpass.draw(0..3, 0..1);
+    }
+}
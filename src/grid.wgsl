@@ -0,0 +1,78 @@
+// A line along every tile edge and a heavier one every ten tiles, in world space so it pans and
+// zooms with the camera, but kept the same number of pixels wide at any zoom
+
+// Must match TILE_SIZE in tilemap.wgsl, minor lines are a tile apart
+const SPACING: f32 = 0.0625;
+const MAJOR_EVERY: f32 = 10.0;
+// Widths in pixels
+const MINOR_WIDTH: f32 = 1.0;
+const MAJOR_WIDTH: f32 = 2.0;
+// Below this many pixels apart the minor lines fade out rather than blurring into a solid grey
+const MINOR_MIN_GAP: f32 = 4.0;
+
+// Same layout as shader.wgsl, only the resolution and camera are used here
+struct Uniforms {
+    resolution: vec2<f32>,
+    mouse: vec2<f32>,
+    time: f32,
+    dt: f32,
+    dpi: f32,
+    zoom: f32,
+    offset: vec2<f32>,
+    frame: u32,
+    aspect: f32,
+    params: vec4<f32>,
+    look: vec2<f32>,
+    click: vec2<f32>,
+    mouse_buttons: u32,
+    seed: u32,
+    padding: vec2<u32>,
+    view_projection: mat4x4<f32>,
+    inverse_view_projection: mat4x4<f32>,
+};
+@group(0) @binding(0) var<uniform> uniforms: Uniforms;
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) tex_coords: vec2<f32>,
+};
+
+// One triangle covering the whole screen, the parts outside get clipped. It's at the depth of the
+// world plane so it passes the depth test over the world with --reverse-z too.
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> VertexOutput {
+    let position = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+    var out: VertexOutput;
+    out.position = vec4<f32>(position * 2.0 - 1.0, uniforms.view_projection[3].z, 1.0);
+    out.tex_coords = position;
+    return out;
+}
+
+// How much of the pixel a set of lines `spacing` world units apart and `width` pixels wide
+// covers, `pixel` being the world size of a pixel along each axis
+fn line_coverage(world: vec2<f32>, spacing: f32, width: f32, pixel: vec2<f32>) -> f32 {
+    // Pixels to the nearest line on each axis
+    let distance = abs(fract(world / spacing + 0.5) - 0.5) * spacing / pixel;
+    let coverage = clamp(width * 0.5 + 0.5 - distance, vec2<f32>(0.0), vec2<f32>(1.0));
+    return max(coverage.x, coverage.y);
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let clip = in.tex_coords * 2.0 - 1.0;
+    let world = (uniforms.inverse_view_projection * vec4<f32>(clip, 0.0, 1.0)).xy;
+    // Clip space spans 2 across the screen, so the camera's scale on each axis gives the world size
+    // of a pixel along it. The camera stretches with the window, so the two can differ.
+    let scale = vec2<f32>(uniforms.view_projection[0].x, uniforms.view_projection[1].y);
+    let pixel = 2.0 / (scale * uniforms.resolution);
+
+    let gap = SPACING / max(pixel.x, pixel.y);
+    let minor_fade = clamp(gap / MINOR_MIN_GAP - 1.0, 0.0, 1.0);
+    let minor = line_coverage(world, SPACING, MINOR_WIDTH, pixel) * minor_fade * 0.25;
+    let major = line_coverage(world, SPACING * MAJOR_EVERY, MAJOR_WIDTH, pixel) * 0.6;
+    let alpha = max(minor, major);
+    if alpha <= 0.0 {
+        discard;
+    }
+    return vec4<f32>(vec3<f32>(1.0), alpha);
+}
@@ -0,0 +1,423 @@
+// Rendering frames into a texture without creating a window
+
+use crate::camera::Camera;
+use crate::capture;
+use crate::compute::Simulation;
+use crate::ecs;
+use crate::grid::Grid;
+use crate::minimap::Minimap;
+use crate::options::{ColorSpace, Options};
+use crate::particles::Particles;
+use crate::picking::PickTarget;
+use crate::post::{self, PostChain};
+use crate::state::{
+    begin_render_pass, create_bind_group, create_bind_group_layout, create_depth_texture,
+    create_device_queue, create_instance, create_msaa_view, create_particles, create_scaling,
+    create_simulation, create_uniforms, create_validated_render_pipeline, depth_clear, draw_scene,
+    draws_world, load_input_textures, load_shader_source, load_world, scene_size, shader_path,
+    starts_wireframe, supports_push_constants, uses_push_constants, validate_sample_count,
+    FrameTime, Mouse, PipelineSettings,
+};
+use crate::texture;
+use crate::ui::PARAM_COUNT;
+use crate::uniform_slots::UniformSlots;
+use crate::world::WorldRenderer;
+use anyhow::Context;
+
+const DEFAULT_SIZE: (u32, u32) = (1920, 1080);
+const DEFAULT_OUTPUT: &str = "out.png";
+
+// A single frame to an image file, the camera starts centred on the player
+pub async fn run(options: &Options) -> anyhow::Result<()> {
+    let output = options
+        .output
+        .clone()
+        .unwrap_or_else(|| DEFAULT_OUTPUT.into());
+    let mut offscreen = Offscreen::new(options).await?;
+    // A single frame shows the first generation after the starting pattern
+    offscreen.advance_simulation(1.0);
+    offscreen.render(FrameTime::default());
+    let image = offscreen.read_frame()?;
+    image
+        .save(&output)
+        .with_context(|| format!("Failed to save {}", output.display()))?;
+    log::info!(
+        "Saved {}x{} frame to {}",
+        image.width(),
+        image.height(),
+        output.display()
+    );
+    Ok(())
+}
+
+// Everything needed to draw frames into a texture instead of a window, also used by --bench
+pub struct Offscreen {
+    pub device: wgpu::Device,
+    pub queue: wgpu::Queue,
+    size: winit::dpi::PhysicalSize<u32>,
+    // What the scene is drawn at, smaller than `size` with --internal-res
+    scene_size: winit::dpi::PhysicalSize<u32>,
+    camera: Camera,
+    seed: u32,
+    // A copy of the uniforms at offset 0, and the minimap's after it
+    uniform_slots: UniformSlots,
+    bind_group: wgpu::BindGroup,
+    render_pipeline: wgpu::RenderPipeline,
+    // Draws the world as lines, see --wireframe
+    wireframe: bool,
+    // See `PipelineSettings::reverse_z`
+    reverse_z: bool,
+    push_uniforms: bool,
+    clear_color: wgpu::Color,
+    tint: wgpu::Color,
+    world_renderer: Option<WorldRenderer>,
+    simulation: Option<Simulation>,
+    particles: Option<Particles>,
+    // With --grid, there's no key to toggle it
+    grid: Option<Grid>,
+    minimap: Option<Minimap>,
+    // There's no cursor, the particles spray from the middle instead of the corner
+    mouse: Mouse,
+    msaa_view: Option<wgpu::TextureView>,
+    depth_view: wgpu::TextureView,
+    post: Option<PostChain>,
+    // What the frames are drawn into, it can be copied out afterwards
+    texture: wgpu::Texture,
+    view: wgpu::TextureView,
+}
+
+impl Offscreen {
+    pub async fn new(options: &Options) -> anyhow::Result<Self> {
+        let (width, height) = options.size.unwrap_or(DEFAULT_SIZE);
+        // PNGs store sRGB so by default let the GPU encode the shader's linear output, linear
+        // writes the values as they are
+        let format = match options.color_space {
+            Some(ColorSpace::Linear) => wgpu::TextureFormat::Rgba8Unorm,
+            Some(ColorSpace::Srgb) | None => wgpu::TextureFormat::Rgba8UnormSrgb,
+        };
+
+        let shader = load_shader_source(shader_path(options).as_deref(), &options.include_dirs)?;
+        let world = load_world(options)?;
+
+        let instance = create_instance(options.backends);
+        let (adapter, device, queue) = create_device_queue(&instance, None, options).await?;
+
+        let size = winit::dpi::PhysicalSize::new(width, height);
+        let camera = Camera::centred_on(world.player);
+        let mut uniforms = create_uniforms(
+            size,
+            1.0,
+            FrameTime::default(),
+            Mouse::default(),
+            &camera,
+            [0.0; PARAM_COUNT],
+            options.seed,
+        );
+        if options.reverse_z {
+            uniforms.reverse_depth();
+        }
+        uniforms.set_tint(options.tint.for_target(format.is_srgb()));
+        let draws_minimap = options.minimap && draws_world(options);
+        let uniform_slots = UniformSlots::new(&device, 1 + u32::from(draws_minimap));
+        uniform_slots.write(&queue, 0, &uniforms);
+        let textures = load_input_textures(&device, &queue, options)?;
+        let sampler = texture::create_sampler(&device);
+        let bind_group_layout = create_bind_group_layout(&device);
+        // Offscreen frames have no feedback, the previous frame is always black
+        let bind_group = create_bind_group(
+            &device,
+            &bind_group_layout,
+            uniform_slots.buffer(),
+            &textures,
+            &sampler,
+            &post::create_empty_frame(&device),
+            &post::create_frame_sampler(&device),
+        );
+        let settings = PipelineSettings {
+            format,
+            sample_count: validate_sample_count(&adapter, format, options.msaa),
+            push_constants: supports_push_constants(&device),
+            blend: None,
+            depth_test: true,
+            polygon_mode: wgpu::PolygonMode::Fill,
+            reverse_z: options.reverse_z,
+            pick_target: PickTarget::None,
+        };
+        let render_pipeline = create_validated_render_pipeline(
+            &device,
+            &shader.text,
+            &[&bind_group_layout],
+            &[],
+            &settings,
+        )
+        .await
+        .map_err(|error| shader.map.annotate(error))?;
+        let mut world_renderer = if draws_world(options) {
+            Some(WorldRenderer::new(&device, &queue, &bind_group_layout, &world, &settings).await?)
+        } else {
+            None
+        };
+        let minimap = match &mut world_renderer {
+            Some(world_renderer) if draws_minimap => {
+                let minimap = Minimap::new(
+                    &device,
+                    &settings,
+                    options.minimap_size,
+                    options.minimap_every,
+                    1,
+                );
+                world_renderer
+                    .add_dots(&device, &bind_group_layout, &settings, minimap.dot_size())
+                    .await?;
+                Some(minimap)
+            }
+            _ => None,
+        };
+        if let Some(world_renderer) = &mut world_renderer {
+            let entities = ecs::World::from_save(&world.entities);
+            world_renderer.update(&device, &queue, &entities, world.player, 1.0);
+        }
+        let simulation =
+            create_simulation(&adapter, &device, &bind_group_layout, &settings, options).await?;
+        let particles =
+            create_particles(&adapter, &device, &bind_group_layout, &settings, options).await?;
+        let grid = if options.grid {
+            Some(Grid::new(&device, &bind_group_layout, &settings).await?)
+        } else {
+            None
+        };
+        let post = (!options.single_pass).then(|| {
+            PostChain::new(
+                &device,
+                format,
+                width,
+                height,
+                false,
+                create_scaling(options),
+                false,
+            )
+        });
+        let (scene_width, scene_height) = scene_size(post.as_ref(), size);
+        let scene_size = winit::dpi::PhysicalSize::new(scene_width, scene_height);
+        let msaa_view = create_msaa_view(&device, &settings, scene_width, scene_height);
+        let depth_view = create_depth_texture(&device, &settings, scene_width, scene_height);
+
+        let texture = capture::create_capture_texture(&device, format, width, height);
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        Ok(Self {
+            size,
+            scene_size,
+            camera,
+            seed: options.seed,
+            uniform_slots,
+            bind_group,
+            render_pipeline,
+            wireframe: starts_wireframe(&device, options),
+            reverse_z: options.reverse_z,
+            push_uniforms: uses_push_constants(&shader.text, &settings),
+            clear_color: options.clear_color.for_target(format.is_srgb()),
+            tint: options.tint.for_target(format.is_srgb()),
+            world_renderer,
+            simulation,
+            particles,
+            grid,
+            minimap,
+            mouse: Mouse {
+                position: [scene_width as f32 / 2.0, scene_height as f32 / 2.0],
+                ..Mouse::default()
+            },
+            msaa_view,
+            depth_view,
+            post,
+            texture,
+            view,
+            device,
+            queue,
+        })
+    }
+
+    pub fn size(&self) -> winit::dpi::PhysicalSize<u32> {
+        self.size
+    }
+
+    // Copy out the last frame, waiting for the GPU to finish it
+    pub fn read_frame(&self) -> anyhow::Result<image::RgbaImage> {
+        capture::read_texture(&self.device, &self.queue, &self.texture)
+    }
+
+    // Let `dt` seconds pass for the simulation, it steps in the next `render`
+    pub fn advance_simulation(&mut self, dt: f32) {
+        if let Some(simulation) = &mut self.simulation {
+            simulation.advance(dt);
+        }
+    }
+
+    // Draw a frame at `frame_time` and submit it, without waiting for the GPU to finish
+    pub fn render(&mut self, frame_time: FrameTime) {
+        let mut uniforms = create_uniforms(
+            self.scene_size,
+            1.0,
+            frame_time,
+            self.mouse,
+            &self.camera,
+            [0.0; PARAM_COUNT],
+            self.seed,
+        );
+        if self.reverse_z {
+            uniforms.reverse_depth();
+        }
+        uniforms.set_tint(self.tint);
+        self.uniform_slots.write(&self.queue, 0, &uniforms);
+
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+        if let Some(simulation) = &mut self.simulation {
+            simulation.step(&mut encoder);
+        }
+        // Every offscreen frame is a new update
+        if let Some(particles) = &mut self.particles {
+            particles.advance();
+            particles.step(&mut encoder, &self.bind_group, 0);
+        }
+        {
+            let mut rpass = begin_render_pass(
+                &mut encoder,
+                self.post.as_ref().map_or(&self.view, PostChain::input_view),
+                self.msaa_view.as_ref(),
+                &self.depth_view,
+                None,
+                self.clear_color,
+                depth_clear(self.reverse_z),
+            );
+            draw_scene(
+                &mut rpass,
+                &self.render_pipeline,
+                &self.bind_group,
+                0,
+                self.push_uniforms.then_some(&uniforms),
+            );
+            if let Some(simulation) = &self.simulation {
+                simulation.draw(&mut rpass, &self.bind_group, 0);
+            }
+            if let Some(particles) = &self.particles {
+                particles.draw(&mut rpass, &self.bind_group, 0);
+            }
+            if let Some(world_renderer) = &self.world_renderer {
+                world_renderer.draw(&mut rpass, &self.bind_group, 0, self.wireframe);
+            }
+            if let Some(grid) = &self.grid {
+                grid.draw(&mut rpass, &self.bind_group, 0);
+            }
+        }
+        if let Some(post) = &self.post {
+            post.encode(&mut encoder, &self.view);
+        }
+        if let (Some(minimap), Some(world_renderer)) = (&mut self.minimap, &self.world_renderer) {
+            if minimap.due() {
+                let size = minimap.size();
+                let mut uniforms = create_uniforms(
+                    winit::dpi::PhysicalSize::new(size, size),
+                    1.0,
+                    frame_time,
+                    Mouse::default(),
+                    &minimap.camera(self.camera.centre()),
+                    [0.0; PARAM_COUNT],
+                    self.seed,
+                );
+                if self.reverse_z {
+                    uniforms.reverse_depth();
+                }
+                self.uniform_slots
+                    .write(&self.queue, minimap.slot(), &uniforms);
+                let mut rpass =
+                    minimap.begin_pass(&mut encoder, self.clear_color, depth_clear(self.reverse_z));
+                world_renderer.draw_minimap(
+                    &mut rpass,
+                    &self.bind_group,
+                    self.uniform_slots.offset(minimap.slot()),
+                );
+            }
+            minimap.composite(
+                &mut encoder,
+                &self.view,
+                (self.size.width, self.size.height),
+            );
+        }
+        self.queue.submit(Some(encoder.finish()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::NoAdapter;
+
+    // Fills the screen with one colour, the same vertices as shader.wgsl's
+    const SOLID_SHADER: &str = "
+        @vertex
+        fn vs_main(@builtin(vertex_index) index: u32) -> @builtin(position) vec4<f32> {
+            var tri = array<vec2<f32>, 6u>(
+                vec2<f32>(-1.0, -1.0),
+                vec2<f32>(1.0, -1.0),
+                vec2<f32>(-1.0, 1.0),
+                vec2<f32>(-1.0, 1.0),
+                vec2<f32>(1.0, -1.0),
+                vec2<f32>(1.0, 1.0),
+            );
+            return vec4<f32>(tri[index], 0.0, 1.0);
+        }
+
+        @fragment
+        fn fs_main() -> @location(0) vec4<f32> {
+            return vec4<f32>(1.0, 0.5, 0.25, 1.0);
+        }
+    ";
+
+    // A frame of `shader` at 4x4, None without an adapter to draw it on. Software ones count, so
+    // this only skips on machines with no GPU at all. The post passes are left out and the
+    // output is linear, so the pixels are what the shader returned.
+    fn render_frame(shader: &str, name: &str) -> Option<image::RgbaImage> {
+        let path = std::env::temp_dir().join(format!("danrpg_{name}_{}.wgsl", std::process::id()));
+        std::fs::write(&path, shader).unwrap();
+        let options = Options {
+            shader: Some(path.clone()),
+            size: Some((4, 4)),
+            color_space: Some(ColorSpace::Linear),
+            single_pass: true,
+            ..Options::default()
+        };
+        let offscreen = pollster::block_on(Offscreen::new(&options));
+        std::fs::remove_file(&path).ok();
+        // Only one device is made, a second in the same test fails to initialise on some GL drivers
+        let mut offscreen = match offscreen {
+            Ok(offscreen) => offscreen,
+            Err(error) if error.is::<NoAdapter>() => {
+                eprintln!("No adapter, skipping");
+                return None;
+            }
+            Err(error) => panic!("{error:#}"),
+        };
+        offscreen.render(FrameTime::default());
+        Some(offscreen.read_frame().unwrap())
+    }
+
+    #[test]
+    fn solid_colour_fills_the_frame() {
+        let Some(frame) = render_frame(SOLID_SHADER, "solid") else {
+            return;
+        };
+        assert_eq!(frame.dimensions(), (4, 4));
+        // Every pixel, so rows read back with the wrong stride or padding show up
+        for (x, y, pixel) in frame.enumerate_pixels() {
+            // Within a step of rounding of the shader's colour
+            for (channel, expected) in pixel.0.into_iter().zip([255, 128, 64, 255]) {
+                assert!(
+                    channel.abs_diff(expected) <= 1,
+                    "Pixel ({x}, {y}) was {:?}",
+                    pixel.0
+                );
+            }
+        }
+    }
+}
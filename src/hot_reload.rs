@@ -0,0 +1,70 @@
+// Watching the shader source on disk so it can be rebuilt while running
+
+use notify::Watcher;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+
+// File watcher kept alive alongside the channel it sends change events to
+pub struct ShaderWatcher {
+    watcher: notify::RecommendedWatcher,
+    receiver: mpsc::Receiver<notify::Result<notify::Event>>,
+    // The shader and every file it includes, and the directories holding them
+    files: Vec<PathBuf>,
+    dirs: HashSet<PathBuf>,
+}
+
+impl ShaderWatcher {
+    pub fn new(files: &[PathBuf]) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        let watcher = notify::recommended_watcher(sender).expect("Failed to create file watcher");
+        let mut shader_watcher = Self {
+            watcher,
+            receiver,
+            files: Vec::new(),
+            dirs: HashSet::new(),
+        };
+        shader_watcher.watch(files);
+        shader_watcher
+    }
+
+    // Watch the directories holding `files` from now on, editors often save by replacing the
+    // file. Directories stay watched once they are.
+    pub fn watch(&mut self, files: &[PathBuf]) {
+        for file in files {
+            // A bare file name has an empty parent, which means the working directory
+            let dir = match file.parent() {
+                Some(dir) if !dir.as_os_str().is_empty() => dir,
+                _ => Path::new("."),
+            };
+            if self.dirs.contains(dir) {
+                continue;
+            }
+            match self.watcher.watch(dir, notify::RecursiveMode::NonRecursive) {
+                Ok(()) => {
+                    self.dirs.insert(dir.to_path_buf());
+                }
+                Err(error) => log::warn!("Failed to watch {}: {error}", dir.display()),
+            }
+        }
+        self.files = files.to_vec();
+    }
+
+    // Drain pending file events and report whether any of them touched the shader's files
+    pub fn changed(&self) -> bool {
+        let mut changed = false;
+        for event in self.receiver.try_iter().flatten() {
+            if !event.kind.is_access()
+                && event.paths.iter().any(|path| {
+                    self.files
+                        .iter()
+                        .filter_map(|file| file.file_name())
+                        .any(|name| path.ends_with(name))
+                })
+            {
+                changed = true;
+            }
+        }
+        changed
+    }
+}
@@ -0,0 +1,351 @@
+// Keyboard shortcuts, each a key plus the exact modifiers held with it mapped to a named action.
+// Shortcuts go by what a key types, so Ctrl+S is wherever S is on the layout, but movement goes by
+// where the key is, see `Movement`.
+use std::collections::HashSet;
+use winit::event::{ElementState, ModifiersState, VirtualKeyCode};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    ToggleFullscreen,
+    Screenshot,
+    SaveWorld,
+    ReloadShader,
+    TogglePause,
+    // Advance the paused clock by a frame
+    StepFrame,
+    ToggleCursorCapture,
+    ReleaseCursor,
+    // Replace the shaders' seed with the next one
+    RerollSeed,
+    // Draw the tile map and sprites as lines, see --wireframe
+    ToggleWireframe,
+    // Show the world space grid over the scene
+    ToggleGrid,
+    // Show the last few hundred frame times as a graph
+    TogglePerfGraph,
+    // Keep the camera, parameters, seed and clear colour in a numbered slot, or go back to them
+    SavePreset(u8),
+    LoadPreset(u8),
+    // Play the test sound, to check audio output works
+    #[cfg(all(feature = "audio", not(target_arch = "wasm32")))]
+    PlayTestSound,
+}
+
+impl Action {
+    // Whether holding the key fires the action again on every key repeat
+    fn repeats(self) -> bool {
+        matches!(self, Self::StepFrame | Self::Screenshot)
+    }
+}
+
+const NONE: ModifiersState = ModifiersState::empty();
+const CTRL: ModifiersState = ModifiersState::CTRL;
+const CTRL_SHIFT: ModifiersState = ModifiersState::CTRL.union(ModifiersState::SHIFT);
+
+// Add new shortcuts here. The modifiers must match exactly, so Ctrl+Space doesn't pause and
+// several actions can share a key with different modifiers.
+const SHORTCUTS: &[(ModifiersState, VirtualKeyCode, Action)] = &[
+    (NONE, VirtualKeyCode::F11, Action::ToggleFullscreen),
+    (NONE, VirtualKeyCode::F12, Action::Screenshot),
+    (NONE, VirtualKeyCode::F5, Action::SaveWorld),
+    (CTRL, VirtualKeyCode::S, Action::SaveWorld),
+    (CTRL, VirtualKeyCode::R, Action::ReloadShader),
+    (NONE, VirtualKeyCode::Space, Action::TogglePause),
+    (NONE, VirtualKeyCode::Period, Action::StepFrame),
+    (NONE, VirtualKeyCode::Tab, Action::ToggleCursorCapture),
+    (NONE, VirtualKeyCode::Escape, Action::ReleaseCursor),
+    (NONE, VirtualKeyCode::R, Action::RerollSeed),
+    (NONE, VirtualKeyCode::F3, Action::ToggleWireframe),
+    (NONE, VirtualKeyCode::G, Action::ToggleGrid),
+    (NONE, VirtualKeyCode::F2, Action::TogglePerfGraph),
+    #[cfg(all(feature = "audio", not(target_arch = "wasm32")))]
+    (NONE, VirtualKeyCode::F8, Action::PlayTestSound),
+];
+
+// The directions the camera or player move in, from the keys where W, A, S and D are on a US
+// layout or the arrows. Going by the key's place rather than its letter keeps them together on
+// AZERTY or Dvorak, where those letters are elsewhere.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Movement {
+    Up,
+    Left,
+    Down,
+    Right,
+}
+
+impl Movement {
+    // The platform's scancode for the key in that place. Winit passes on the platform's own codes,
+    // Linux's evdev codes and Windows' set 1 scancodes agree for these, and macOS has its own.
+    // None where they aren't known, like in the browser.
+    fn scancode(self) -> Option<u32> {
+        let codes: [u32; 4] = if cfg!(target_os = "macos") {
+            [13, 0, 1, 2]
+        } else if cfg!(any(target_os = "linux", target_os = "windows")) {
+            [17, 30, 31, 32]
+        } else {
+            return None;
+        };
+        Some(codes[self as usize])
+    }
+
+    // What it falls back to without a scancode
+    fn letter(self) -> VirtualKeyCode {
+        match self {
+            Self::Up => VirtualKeyCode::W,
+            Self::Left => VirtualKeyCode::A,
+            Self::Down => VirtualKeyCode::S,
+            Self::Right => VirtualKeyCode::D,
+        }
+    }
+
+    // The same on every layout
+    fn arrow(self) -> VirtualKeyCode {
+        match self {
+            Self::Up => VirtualKeyCode::Up,
+            Self::Left => VirtualKeyCode::Left,
+            Self::Down => VirtualKeyCode::Down,
+            Self::Right => VirtualKeyCode::Right,
+        }
+    }
+}
+
+// Which keys are down. The OS repeats KeyboardInput presses while a key is held, `update` tells
+// the first of them apart as the press.
+#[derive(Default)]
+pub struct KeyStates {
+    held: HashSet<VirtualKeyCode>,
+    // Went down with Ctrl, Alt or the logo key held, as part of a shortcut like Ctrl+S
+    chorded: HashSet<VirtualKeyCode>,
+    // Scancodes of the keys held down other than as part of a shortcut, whatever they type
+    held_scancodes: HashSet<u32>,
+}
+
+// What a key event turned out to be
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyEdge {
+    Pressed,
+    // The OS repeating a press while the key stays down
+    Repeat,
+    Released,
+}
+
+impl KeyStates {
+    // Track a key event, `chord` when a command modifier is held with it
+    pub fn update(&mut self, key: VirtualKeyCode, state: ElementState, chord: bool) -> KeyEdge {
+        match state {
+            ElementState::Pressed if self.held.insert(key) => {
+                if chord {
+                    self.chorded.insert(key);
+                }
+                KeyEdge::Pressed
+            }
+            ElementState::Pressed => KeyEdge::Repeat,
+            ElementState::Released => {
+                self.held.remove(&key);
+                self.chorded.remove(&key);
+                KeyEdge::Released
+            }
+        }
+    }
+
+    // Track where the key of every key event is, including ones that type nothing winit knows.
+    // Only what's held matters, presses and releases go by `update`.
+    pub fn update_scancode(&mut self, scancode: u32, state: ElementState, chord: bool) {
+        match state {
+            ElementState::Pressed if !chord => {
+                self.held_scancodes.insert(scancode);
+            }
+            ElementState::Pressed => {}
+            ElementState::Released => {
+                self.held_scancodes.remove(&scancode);
+            }
+        }
+    }
+
+    // Down, and not as part of a shortcut, for things that last as long as the key is held
+    pub fn held(&self, key: VirtualKeyCode) -> bool {
+        self.held.contains(&key) && !self.chorded.contains(&key)
+    }
+
+    // Whether a key moving in `direction` is held, see `Movement`
+    pub fn moving(&self, direction: Movement) -> bool {
+        let placed = match direction.scancode() {
+            Some(scancode) => self.held_scancodes.contains(&scancode),
+            None => self.held(direction.letter()),
+        };
+        placed || self.held(direction.arrow())
+    }
+
+    // Let go of everything, the releases would go to whichever window has focus next
+    pub fn release_all(&mut self) {
+        self.held.clear();
+        self.chorded.clear();
+        self.held_scancodes.clear();
+    }
+}
+
+#[derive(Default)]
+pub struct Shortcuts {
+    modifiers: ModifiersState,
+}
+
+impl Shortcuts {
+    pub fn set_modifiers(&mut self, modifiers: ModifiersState) {
+        self.modifiers = modifiers;
+    }
+
+    // Ctrl, Alt and the logo key turn keys into shortcuts, so they shouldn't also move the camera
+    pub fn command_held(&self) -> bool {
+        self.modifiers
+            .intersects(ModifiersState::CTRL | ModifiersState::ALT | ModifiersState::LOGO)
+    }
+
+    pub fn ctrl_held(&self) -> bool {
+        self.modifiers.ctrl()
+    }
+
+    pub fn shift_held(&self) -> bool {
+        self.modifiers.shift()
+    }
+
+    // The action a key event triggers if any, toggles only fire on the first press
+    pub fn key(&self, key: VirtualKeyCode, edge: KeyEdge) -> Option<Action> {
+        match edge {
+            KeyEdge::Pressed => lookup(self.modifiers, key),
+            KeyEdge::Repeat => lookup(self.modifiers, key).filter(|action| action.repeats()),
+            KeyEdge::Released => None,
+        }
+    }
+}
+
+// The number row, the preset slots are numbered from 1
+const PRESET_KEYS: [VirtualKeyCode; 9] = [
+    VirtualKeyCode::Key1,
+    VirtualKeyCode::Key2,
+    VirtualKeyCode::Key3,
+    VirtualKeyCode::Key4,
+    VirtualKeyCode::Key5,
+    VirtualKeyCode::Key6,
+    VirtualKeyCode::Key7,
+    VirtualKeyCode::Key8,
+    VirtualKeyCode::Key9,
+];
+
+fn lookup(modifiers: ModifiersState, key: VirtualKeyCode) -> Option<Action> {
+    SHORTCUTS
+        .iter()
+        .find(|&&(shortcut_modifiers, shortcut_key, _)| {
+            shortcut_modifiers == modifiers && shortcut_key == key
+        })
+        .map(|&(_, _, action)| action)
+        .or_else(|| preset_shortcut(modifiers, key))
+}
+
+// Ctrl and a number loads that preset slot, with Shift as well it saves to it
+fn preset_shortcut(modifiers: ModifiersState, key: VirtualKeyCode) -> Option<Action> {
+    let slot = PRESET_KEYS
+        .iter()
+        .position(|&preset_key| preset_key == key)? as u8
+        + 1;
+    if modifiers == CTRL {
+        Some(Action::LoadPreset(slot))
+    } else if modifiers == CTRL_SHIFT {
+        Some(Action::SavePreset(slot))
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_held_key_is_pressed_once() {
+        let mut keys = KeyStates::default();
+        let key = VirtualKeyCode::Space;
+        assert_eq!(
+            keys.update(key, ElementState::Pressed, false),
+            KeyEdge::Pressed
+        );
+        assert!(keys.held(key));
+        assert_eq!(
+            keys.update(key, ElementState::Pressed, false),
+            KeyEdge::Repeat
+        );
+        assert!(keys.held(key));
+        assert_eq!(
+            keys.update(key, ElementState::Released, false),
+            KeyEdge::Released
+        );
+        assert!(!keys.held(key));
+        // Pressed again after letting go
+        assert_eq!(
+            keys.update(key, ElementState::Pressed, false),
+            KeyEdge::Pressed
+        );
+    }
+
+    #[test]
+    fn movement_goes_by_where_the_key_is() {
+        let mut keys = KeyStates::default();
+        // Where W is on a US layout types Z on AZERTY, whose W is elsewhere
+        let Some(up) = Movement::Up.scancode() else {
+            return;
+        };
+        keys.update_scancode(up, ElementState::Pressed, false);
+        keys.update(VirtualKeyCode::Z, ElementState::Pressed, false);
+        assert!(keys.moving(Movement::Up));
+        assert!(!keys.moving(Movement::Left));
+        keys.update_scancode(up, ElementState::Released, false);
+        assert!(!keys.moving(Movement::Up));
+        // Not while it's part of a shortcut
+        keys.update_scancode(up, ElementState::Pressed, true);
+        assert!(!keys.moving(Movement::Up));
+        keys.update(VirtualKeyCode::Left, ElementState::Pressed, false);
+        assert!(keys.moving(Movement::Left));
+    }
+
+    #[test]
+    fn toggles_ignore_repeats() {
+        let shortcuts = Shortcuts::default();
+        let key = VirtualKeyCode::Space;
+        assert_eq!(
+            shortcuts.key(key, KeyEdge::Pressed),
+            Some(Action::TogglePause)
+        );
+        assert_eq!(shortcuts.key(key, KeyEdge::Repeat), None);
+        assert_eq!(
+            shortcuts.key(VirtualKeyCode::Period, KeyEdge::Repeat),
+            Some(Action::StepFrame)
+        );
+    }
+
+    #[test]
+    fn number_keys_pick_preset_slots() {
+        let mut shortcuts = Shortcuts::default();
+        let key = VirtualKeyCode::Key3;
+        assert_eq!(shortcuts.key(key, KeyEdge::Pressed), None);
+        shortcuts.set_modifiers(CTRL);
+        assert_eq!(
+            shortcuts.key(key, KeyEdge::Pressed),
+            Some(Action::LoadPreset(3))
+        );
+        shortcuts.set_modifiers(CTRL_SHIFT);
+        assert_eq!(
+            shortcuts.key(key, KeyEdge::Pressed),
+            Some(Action::SavePreset(3))
+        );
+    }
+
+    #[test]
+    fn chorded_keys_are_not_held_for_movement() {
+        let mut keys = KeyStates::default();
+        // Still a press for the shortcut
+        assert_eq!(
+            keys.update(VirtualKeyCode::S, ElementState::Pressed, true),
+            KeyEdge::Pressed
+        );
+        assert!(!keys.held(VirtualKeyCode::S));
+    }
+}
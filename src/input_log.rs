@@ -0,0 +1,326 @@
+// Input saved with --record-input and played back with --replay-input, so a bug report can come
+// with the exact input that led to it. Each line of the log is one RON entry, written as it
+// happens so a crash keeps everything up to it. Entries are stamped with the clock the frames
+// step through rather than the wall clock, so a replay running at another frame rate or stalling
+// on a slow shader compile still lands each event between the same frames.
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{LineWriter, Write};
+use std::path::Path;
+use winit::{
+    dpi::PhysicalPosition,
+    event::{
+        DeviceId, ElementState, KeyboardInput, ModifiersState, MouseButton, MouseScrollDelta,
+        Touch, TouchPhase, WindowEvent,
+    },
+    window::WindowId,
+};
+
+use crate::options::Options;
+
+// The parts of a window event the app reacts to, winit's own events can't be saved
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum InputEvent {
+    CursorMoved(PhysicalPosition<f64>),
+    CursorEntered,
+    CursorLeft,
+    MouseInput(ElementState, MouseButton),
+    MouseWheel(MouseScrollDelta, TouchPhase),
+    Keyboard(KeyboardInput),
+    ReceivedCharacter(char),
+    ModifiersChanged(ModifiersState),
+    Touch {
+        id: u64,
+        phase: TouchPhase,
+        location: PhysicalPosition<f64>,
+    },
+}
+
+impl InputEvent {
+    // None for events that aren't input, like resizes, which the replay leaves to the OS
+    pub fn from_window_event(event: &WindowEvent) -> Option<Self> {
+        Some(match *event {
+            WindowEvent::CursorMoved { position, .. } => Self::CursorMoved(position),
+            WindowEvent::CursorEntered { .. } => Self::CursorEntered,
+            WindowEvent::CursorLeft { .. } => Self::CursorLeft,
+            WindowEvent::MouseInput { state, button, .. } => Self::MouseInput(state, button),
+            WindowEvent::MouseWheel { delta, phase, .. } => Self::MouseWheel(delta, phase),
+            WindowEvent::KeyboardInput { input, .. } => Self::Keyboard(input),
+            WindowEvent::ReceivedCharacter(character) => Self::ReceivedCharacter(character),
+            WindowEvent::ModifiersChanged(modifiers) => Self::ModifiersChanged(modifiers),
+            WindowEvent::Touch(touch) => Self::Touch {
+                id: touch.id,
+                phase: touch.phase,
+                location: touch.location,
+            },
+            _ => return None,
+        })
+    }
+
+    #[allow(deprecated)] // The modifiers fields, ModifiersChanged is replayed instead
+    pub fn to_window_event(self) -> WindowEvent<'static> {
+        // Safety: the id only reaches the app's handlers and egui, never a winit function
+        let device_id = unsafe { DeviceId::dummy() };
+        let modifiers = ModifiersState::empty();
+        match self {
+            Self::CursorMoved(position) => WindowEvent::CursorMoved {
+                device_id,
+                position,
+                modifiers,
+            },
+            Self::CursorEntered => WindowEvent::CursorEntered { device_id },
+            Self::CursorLeft => WindowEvent::CursorLeft { device_id },
+            Self::MouseInput(state, button) => WindowEvent::MouseInput {
+                device_id,
+                state,
+                button,
+                modifiers,
+            },
+            Self::MouseWheel(delta, phase) => WindowEvent::MouseWheel {
+                device_id,
+                delta,
+                phase,
+                modifiers,
+            },
+            Self::Keyboard(input) => WindowEvent::KeyboardInput {
+                device_id,
+                input,
+                is_synthetic: false,
+            },
+            Self::ReceivedCharacter(character) => WindowEvent::ReceivedCharacter(character),
+            Self::ModifiersChanged(modifiers) => WindowEvent::ModifiersChanged(modifiers),
+            Self::Touch {
+                id,
+                phase,
+                location,
+            } => WindowEvent::Touch(Touch {
+                device_id,
+                phase,
+                location,
+                force: None,
+                id,
+            }),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+enum LoggedEvent {
+    // To the window at this index in the order they were opened
+    Window(usize, InputEvent),
+    // Raw mouse movement, which turns the camera while the cursor is captured
+    MouseMotion(f64, f64),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+struct Entry {
+    // Seconds of frames stepped through before the event arrived
+    time: f64,
+    event: LoggedEvent,
+}
+
+// An event from the log that's due, for the window it was recorded on
+pub enum Replayed {
+    Window(WindowId, WindowEvent<'static>),
+    MouseMotion((f64, f64)),
+}
+
+pub struct InputLog {
+    recording: Option<LineWriter<File>>,
+    // Entries still to be played, None when not replaying or once they've all been
+    replay: Option<VecDeque<Entry>>,
+    clock: f64,
+    // Windows in the order they were opened, the log refers to them by index
+    windows: Vec<WindowId>,
+}
+
+impl InputLog {
+    pub fn new(options: &Options, windows: Vec<WindowId>) -> anyhow::Result<Self> {
+        // Read before creating the recording, in case they're the same file
+        let replay = options.replay_input.as_deref().map(load).transpose()?;
+        let recording = options
+            .record_input
+            .as_deref()
+            .map(|path| {
+                File::create(path)
+                    .map(LineWriter::new)
+                    .with_context(|| format!("Failed to create input log {}", path.display()))
+            })
+            .transpose()?;
+        if let Some(entries) = &replay {
+            log::info!(
+                "Replaying {} input events, ignoring real input",
+                entries.len()
+            );
+        }
+        Ok(Self {
+            recording,
+            replay,
+            clock: 0.0,
+            windows,
+        })
+    }
+
+    // Real input is dropped while the log is being played
+    pub fn replaying(&self) -> bool {
+        self.replay.is_some()
+    }
+
+    // Step the clock by a frame's `dt`, once per frame like `State::update`
+    pub fn advance(&mut self, dt: f32) {
+        self.clock += f64::from(dt);
+    }
+
+    pub fn record_window_event(&mut self, id: WindowId, event: &WindowEvent) {
+        let (Some(index), Some(event)) = (
+            self.windows.iter().position(|&window| window == id),
+            InputEvent::from_window_event(event),
+        ) else {
+            return;
+        };
+        self.record(LoggedEvent::Window(index, event));
+    }
+
+    pub fn record_mouse_motion(&mut self, delta: (f64, f64)) {
+        self.record(LoggedEvent::MouseMotion(delta.0, delta.1));
+    }
+
+    fn record(&mut self, event: LoggedEvent) {
+        let Some(writer) = &mut self.recording else {
+            return;
+        };
+        let entry = Entry {
+            time: self.clock,
+            event,
+        };
+        let result = ron::to_string(&entry)
+            .map_err(anyhow::Error::from)
+            .and_then(|line| Ok(writeln!(writer, "{line}")?));
+        if let Err(error) = result {
+            log::warn!("Failed to record input, stopping the recording: {error:#}");
+            self.recording = None;
+        }
+    }
+
+    // The events recorded by the current time, in the order they arrived. Real input comes back
+    // once the last one has been played.
+    pub fn due(&mut self) -> Vec<Replayed> {
+        let Some(entries) = &mut self.replay else {
+            return Vec::new();
+        };
+        let mut due = Vec::new();
+        while let Some(entry) = entries.front().filter(|entry| entry.time <= self.clock) {
+            due.push(match entry.event {
+                // A window that failed to open can't get its events
+                LoggedEvent::Window(index, event) => match self.windows.get(index) {
+                    Some(&id) => Replayed::Window(id, event.to_window_event()),
+                    None => {
+                        entries.pop_front();
+                        continue;
+                    }
+                },
+                LoggedEvent::MouseMotion(x, y) => Replayed::MouseMotion((x, y)),
+            });
+            entries.pop_front();
+        }
+        if entries.is_empty() {
+            log::info!("Finished replaying input");
+            self.replay = None;
+        }
+        due
+    }
+}
+
+fn load(path: &Path) -> anyhow::Result<VecDeque<Entry>> {
+    let text = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read input log {}", path.display()))?;
+    parse(&text).with_context(|| format!("Failed to parse input log {}", path.display()))
+}
+
+fn parse(text: &str) -> anyhow::Result<VecDeque<Entry>> {
+    text.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(index, line)| {
+            ron::from_str(line).with_context(|| format!("Invalid entry on line {}", index + 1))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use winit::event::VirtualKeyCode;
+
+    #[test]
+    #[allow(deprecated)]
+    fn entries_survive_a_round_trip() {
+        let entries = [
+            Entry {
+                time: 0.0,
+                event: LoggedEvent::Window(
+                    0,
+                    InputEvent::Keyboard(KeyboardInput {
+                        scancode: 17,
+                        state: ElementState::Pressed,
+                        virtual_keycode: Some(VirtualKeyCode::W),
+                        modifiers: ModifiersState::empty(),
+                    }),
+                ),
+            },
+            Entry {
+                time: 0.25,
+                event: LoggedEvent::Window(
+                    1,
+                    InputEvent::MouseWheel(
+                        MouseScrollDelta::LineDelta(0.0, -1.0),
+                        TouchPhase::Moved,
+                    ),
+                ),
+            },
+            Entry {
+                time: 0.5,
+                event: LoggedEvent::MouseMotion(3.0, -2.5),
+            },
+        ];
+        let text: String = entries
+            .iter()
+            .map(|entry| ron::to_string(entry).unwrap() + "\n")
+            .collect();
+        assert_eq!(parse(&text).unwrap(), entries);
+    }
+
+    #[test]
+    fn events_play_on_the_logical_clock() {
+        let entry = |time| Entry {
+            time,
+            event: LoggedEvent::MouseMotion(time, 0.0),
+        };
+        let mut log = InputLog {
+            recording: None,
+            replay: Some([entry(0.0), entry(0.1), entry(0.1), entry(0.3)].into()),
+            clock: 0.0,
+            windows: Vec::new(),
+        };
+        let times = |due: Vec<Replayed>| -> Vec<f64> {
+            due.into_iter()
+                .map(|replayed| match replayed {
+                    Replayed::MouseMotion((time, _)) => time,
+                    Replayed::Window(..) => unreachable!(),
+                })
+                .collect()
+        };
+        assert_eq!(times(log.due()), [0.0]);
+        // However long a frame really took, only the time it stepped counts
+        log.advance(0.05);
+        assert!(log.due().is_empty());
+        log.advance(0.05);
+        assert_eq!(times(log.due()), [0.1, 0.1]);
+        assert!(log.replaying());
+        log.advance(0.5);
+        assert_eq!(times(log.due()), [0.3]);
+        assert!(!log.replaying());
+    }
+}
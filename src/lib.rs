@@ -0,0 +1,423 @@
+mod atlas;
+#[cfg(all(feature = "audio", not(target_arch = "wasm32")))]
+mod audio;
+#[cfg(not(target_arch = "wasm32"))]
+mod bench;
+mod camera;
+#[cfg(not(target_arch = "wasm32"))]
+mod capture;
+mod color;
+mod compute;
+mod device_loss;
+mod dynamic_res;
+mod ecs;
+mod frame_limiter;
+mod gamepad;
+mod gpu_timer;
+mod graph;
+mod grid;
+#[cfg(not(target_arch = "wasm32"))]
+mod headless;
+#[cfg(not(target_arch = "wasm32"))]
+mod hot_reload;
+mod input;
+#[cfg(not(target_arch = "wasm32"))]
+mod input_log;
+mod limits;
+mod minimap;
+#[cfg(not(target_arch = "wasm32"))]
+mod monitors;
+#[cfg(all(feature = "net", not(target_arch = "wasm32")))]
+mod net;
+mod options;
+mod particles;
+mod perf_graph;
+mod picking;
+mod post;
+mod power;
+mod preprocess;
+#[cfg(not(target_arch = "wasm32"))]
+mod preset;
+mod quad;
+#[cfg(not(target_arch = "wasm32"))]
+mod record;
+mod save;
+mod shader_check;
+mod sized;
+mod sprite;
+mod startup;
+mod state;
+mod stats;
+mod text;
+mod texture;
+mod tilemap;
+mod timestep;
+mod touch;
+mod ui;
+mod uniform_slots;
+#[cfg(not(target_arch = "wasm32"))]
+mod window_config;
+mod world;
+
+use anyhow::Context;
+use device_loss::DeviceLost;
+use frame_limiter::FrameLimiter;
+#[cfg(not(target_arch = "wasm32"))]
+use input_log::{InputEvent, InputLog, Replayed};
+use options::Options;
+use startup::StartupProfile;
+use state::State;
+use stats::FpsCounter;
+use winit::{
+    event::{DeviceEvent, Event, WindowEvent},
+    event_loop::{ControlFlow, EventLoop},
+    window::{Window, WindowId},
+};
+
+// Browser entry point, WebGPU setup is async so it runs on the browser's event loop
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen::prelude::wasm_bindgen(start)]
+pub fn start() {
+    wasm_bindgen_futures::spawn_local(async {
+        if let Err(error) = run().await {
+            log::error!("{error:#}");
+        }
+    });
+}
+
+pub async fn run() -> anyhow::Result<()> {
+    init_logging();
+    let options = Options::parse();
+    let mut startup = StartupProfile::new(options.profile_startup);
+    #[cfg(not(target_arch = "wasm32"))]
+    if let Some(frames) = options.bench {
+        return bench::run(&options, frames)
+            .await
+            .context("Benchmark failed");
+    }
+    #[cfg(not(target_arch = "wasm32"))]
+    if let Some(pattern) = &options.record {
+        return record::run(&options, pattern)
+            .await
+            .context("Recording failed");
+    }
+    #[cfg(not(target_arch = "wasm32"))]
+    if options.headless {
+        return headless::run(&options)
+            .await
+            .context("Headless render failed");
+    }
+    let event_loop = EventLoop::new();
+    #[cfg(not(target_arch = "wasm32"))]
+    if options.list_monitors {
+        monitors::print(&event_loop);
+        return Ok(());
+    }
+    let mut builder = winit::window::WindowBuilder::new()
+        .with_title("danrpg")
+        .with_transparent(options.transparent);
+    if options.fullscreen {
+        builder = builder.with_fullscreen(Some(winit::window::Fullscreen::Borderless(None)));
+    }
+    // A chosen monitor takes every window there, otherwise only the first goes back to where
+    // the last one was closed
+    #[cfg(not(target_arch = "wasm32"))]
+    let first_builder = match (options.monitor, window_config::load()) {
+        (Some(index), _) => {
+            builder = monitors::place(builder, &event_loop, index, options.fullscreen);
+            builder.clone()
+        }
+        (None, Some(config)) => window_config::restore(builder.clone(), &config, &event_loop),
+        (None, None) => builder.clone(),
+    };
+    // One window takes the monitor over, a mode it doesn't have falls back to borderless
+    #[cfg(not(target_arch = "wasm32"))]
+    let first_builder = match monitors::choose(&event_loop, options.monitor) {
+        Some(monitor) if options.fullscreen_exclusive => {
+            let fullscreen =
+                monitors::exclusive_fullscreen(&monitor, options.resolution, options.refresh_rate)
+                    .unwrap_or(winit::window::Fullscreen::Borderless(Some(monitor)));
+            first_builder.with_fullscreen(Some(fullscreen))
+        }
+        _ => first_builder,
+    };
+    #[cfg(target_arch = "wasm32")]
+    let first_builder = builder.clone();
+    let mut windows = vec![first_builder
+        .build(&event_loop)
+        .context("Failed to create window")?];
+    for _ in 1..options.windows {
+        windows.push(
+            builder
+                .clone()
+                .build(&event_loop)
+                .context("Failed to create window")?,
+        );
+    }
+    #[cfg(target_arch = "wasm32")]
+    for window in &windows {
+        attach_canvas(window);
+    }
+    startup.step("create_windows");
+    run_event_loop(event_loop, windows, options, startup).await
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn init_logging() {
+    env_logger::Builder::from_env(
+        env_logger::Env::default().default_filter_or("warn,rust_raymarching=info"),
+    )
+    .init();
+}
+
+#[cfg(target_arch = "wasm32")]
+fn init_logging() {
+    std::panic::set_hook(Box::new(console_error_panic_hook::hook));
+    console_log::init_with_level(log::Level::Info).expect("Failed to initialise logger");
+}
+
+// Winit creates the canvas but leaves sizing it and adding it to the page to us
+#[cfg(target_arch = "wasm32")]
+fn attach_canvas(window: &Window) {
+    use winit::platform::web::WindowExtWebSys;
+
+    window.set_inner_size(winit::dpi::PhysicalSize::new(1280, 720));
+    web_sys::window()
+        .and_then(|win| win.document())
+        .and_then(|doc| doc.body())
+        .and_then(|body| {
+            body.append_child(&web_sys::Element::from(window.canvas()))
+                .ok()
+        })
+        .expect("Failed to append canvas to the document body");
+}
+
+async fn run_event_loop(
+    event_loop: EventLoop<()>,
+    windows: Vec<Window>,
+    options: Options,
+    mut startup: StartupProfile,
+) -> anyhow::Result<()> {
+    #[cfg(not(target_arch = "wasm32"))]
+    let mut input_log = InputLog::new(&options, windows.iter().map(Window::id).collect())?;
+    let mut windows = windows.into_iter();
+    let first = windows.next().context("No window to render into")?;
+    let mut state = State::new(first, &options, &mut startup).await?;
+    for window in windows {
+        state.add_window(window)?;
+    }
+    startup.step("add_window");
+
+    // Poll keeps the loop spinning and redraws every frame, which animated shaders need but
+    // costs a full CPU core and constant GPU work. Wait sleeps until the OS delivers an event,
+    // so a static scene is only redrawn on resize or expose and the machine stays idle.
+    let control_flow_mode = if options.static_scene {
+        ControlFlow::Wait
+    } else {
+        ControlFlow::Poll
+    };
+    // With a frame rate cap the loop sleeps until the next frame is due instead of polling. It
+    // defaults to one on battery.
+    let mut frame_limiter = (!options.static_scene)
+        .then(|| power::max_fps(&options))
+        .flatten()
+        .map(|max_fps| FrameLimiter::new(max_fps, instant::Instant::now()));
+    // The title shows the frame rate, only updated once a second as setting it isn't free
+    let mut fps_counter = FpsCounter::default();
+    let mut last_redraw = None;
+    // Every window redraws each frame but the clocks only advance for the first of them
+    let mut updated = false;
+
+    event_loop.run(move |event, _, control_flow| {
+        // Nothing is drawn while every window is hidden, so sleep until one of them comes back
+        *control_flow = match &frame_limiter {
+            _ if !state.any_drawable() => ControlFlow::Wait,
+            Some(limiter) => ControlFlow::WaitUntil(limiter.deadline()),
+            None => control_flow_mode,
+        };
+        match event {
+            // While a log is replayed the real input is dropped, the log plays its own instead
+            #[cfg(not(target_arch = "wasm32"))]
+            Event::WindowEvent { event, .. }
+                if input_log.replaying() && InputEvent::from_window_event(&event).is_some() => {}
+            #[cfg(not(target_arch = "wasm32"))]
+            Event::DeviceEvent {
+                event: DeviceEvent::MouseMotion { .. },
+                ..
+            } if input_log.replaying() => {}
+            // Input is logged on its way in
+            #[cfg(not(target_arch = "wasm32"))]
+            Event::WindowEvent { window_id, event }
+                if InputEvent::from_window_event(&event).is_some() =>
+            {
+                input_log.record_window_event(window_id, &event);
+                window_input(&mut state, window_id, &event);
+            }
+            // The debug panel sees events first and keeps the ones it captures, like clicks on it
+            Event::WindowEvent { window_id, event } if state.ui_input(window_id, &event) => {}
+            Event::WindowEvent { window_id, event } => match event {
+                // The app keeps running until its last window is closed
+                WindowEvent::CloseRequested => {
+                    let last = state.windows().count() == 1;
+                    #[cfg(not(target_arch = "wasm32"))]
+                    if let Some(window) = state.window(window_id).filter(|_| last) {
+                        window_config::save(window);
+                    }
+                    state.remove_window(window_id);
+                    if last {
+                        *control_flow = ControlFlow::Exit;
+                    }
+                }
+                // Dragging an edge can send dozens of these a second, only the last one before a
+                // frame gets applied, and the redraw makes sure it is
+                WindowEvent::Resized(new_size) => {
+                    state.queue_resize(window_id, new_size);
+                    state.request_redraw(window_id);
+                }
+                // Moving to a monitor with a different scale changes the physical size too
+                WindowEvent::ScaleFactorChanged { new_inner_size, .. } => {
+                    state.queue_resize(window_id, *new_inner_size);
+                    state.request_redraw(window_id);
+                }
+                // A shader or image to load in place of the current one
+                #[cfg(not(target_arch = "wasm32"))]
+                WindowEvent::DroppedFile(path) => state.drop_file(&path),
+                event => {
+                    state.input(window_id, &event);
+                }
+            },
+            Event::DeviceEvent {
+                event: DeviceEvent::MouseMotion { delta },
+                ..
+            } => {
+                #[cfg(not(target_arch = "wasm32"))]
+                input_log.record_mouse_motion(delta);
+                state.mouse_motion(delta);
+            }
+            Event::MainEventsCleared => {
+                // Recorded input arrives here, after the real events and before the next update
+                #[cfg(not(target_arch = "wasm32"))]
+                for replayed in input_log.due() {
+                    match replayed {
+                        Replayed::Window(window_id, event) => {
+                            input_log.record_window_event(window_id, &event);
+                            window_input(&mut state, window_id, &event);
+                        }
+                        Replayed::MouseMotion(delta) => {
+                            input_log.record_mouse_motion(delta);
+                            state.mouse_motion(delta);
+                        }
+                    }
+                }
+                // The frame rate shouldn't count the time spent hidden
+                if !state.any_drawable() {
+                    last_redraw = None;
+                }
+                state.poll_gamepads();
+                #[cfg(not(target_arch = "wasm32"))]
+                if state.reload_shader_if_changed() {
+                    state.request_redraws();
+                }
+                // Redraw request after all other events are finished processing
+                let frame_due = match &mut frame_limiter {
+                    Some(limiter) => limiter.ready(instant::Instant::now()),
+                    None => true,
+                };
+                if !options.static_scene && frame_due {
+                    state.request_redraws();
+                }
+            }
+            Event::RedrawRequested(window_id) if state.drawable(window_id) => {
+                if !std::mem::replace(&mut updated, true) {
+                    let now = instant::Instant::now();
+                    if let Some(last) = last_redraw.replace(now) {
+                        if let Some(fps) = fps_counter.tick((now - last).as_secs_f32()) {
+                            for window in state.windows() {
+                                let size = window.inner_size();
+                                window.set_title(&format!(
+                                    "danrpg — {}x{} @ {fps:.0} fps",
+                                    size.width, size.height
+                                ));
+                            }
+                        }
+                    }
+                    let dt = state.frame_dt();
+                    state.update(dt);
+                    #[cfg(not(target_arch = "wasm32"))]
+                    input_log.advance(dt);
+                }
+                match render(&mut state, window_id) {
+                    Ok(Ok(())) => {
+                        startup.step("first_frame");
+                        startup.report();
+                    }
+                    #[cfg(not(target_arch = "wasm32"))]
+                    Err(DeviceLost) => {
+                        log::error!("The GPU device was lost, recreating it");
+                        if let Err(error) = pollster::block_on(state.recreate(&options)) {
+                            log::error!("Failed to recover from losing the GPU device: {error:#}");
+                            *control_flow = ControlFlow::Exit;
+                        }
+                    }
+                    // Recreating needs to wait on the browser, so stop with a reason instead
+                    #[cfg(target_arch = "wasm32")]
+                    Err(DeviceLost) => {
+                        log::error!("The GPU device was lost, reload the page to start again");
+                        *control_flow = ControlFlow::Exit;
+                    }
+                    // The swapchain no longer matches the surface, like right after switching to
+                    // fullscreen, so pick up the window's current size and draw again straight
+                    // away. One that stays outdated gets a new surface, see
+                    // `State::recover_surface`.
+                    Ok(Err(wgpu::SurfaceError::Lost | wgpu::SurfaceError::Outdated)) => {
+                        if let Err(error) = state.recover_surface(window_id) {
+                            #[cfg(not(target_arch = "wasm32"))]
+                            {
+                                log::error!("{error:#}, recreating the GPU device");
+                                if let Err(error) = pollster::block_on(state.recreate(&options)) {
+                                    log::error!("Failed to recreate the GPU device: {error:#}");
+                                    *control_flow = ControlFlow::Exit;
+                                }
+                            }
+                            #[cfg(target_arch = "wasm32")]
+                            {
+                                log::error!("{error:#}, reload the page to start again");
+                                *control_flow = ControlFlow::Exit;
+                            }
+                        }
+                        state.request_redraw(window_id);
+                    }
+                    Ok(Err(wgpu::SurfaceError::OutOfMemory)) => {
+                        log::error!("Out of memory while acquiring the next frame, exiting");
+                        *control_flow = ControlFlow::Exit;
+                    }
+                    // Presenting took too long, drop this frame and try again next time
+                    Ok(Err(wgpu::SurfaceError::Timeout)) => {}
+                }
+            }
+            Event::RedrawEventsCleared => updated = false,
+            _ => {}
+        }
+    });
+}
+
+// Input to a window goes to the debug panel first, then the app if the panel doesn't keep it
+#[cfg(not(target_arch = "wasm32"))]
+fn window_input(state: &mut State, id: WindowId, event: &WindowEvent) {
+    if !state.ui_input(id, event) {
+        state.input(id, event);
+    }
+}
+
+// Draw a window, reporting a lost device rather than panicking on it
+fn render(state: &mut State, id: WindowId) -> Result<Result<(), wgpu::SurfaceError>, DeviceLost> {
+    let alpha = state.alpha();
+    #[cfg(not(target_arch = "wasm32"))]
+    let result = device_loss::catch(|| state.render(id, alpha))?;
+    #[cfg(target_arch = "wasm32")]
+    let result = state.render(id, alpha);
+    if state.device_lost() {
+        return Err(DeviceLost);
+    }
+    Ok(result)
+}
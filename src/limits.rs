@@ -0,0 +1,61 @@
+// Printing device limits for --limits, to see which cap a large texture or buffer ran into
+
+// One row per limit in the order wgpu declares them
+macro_rules! limit_rows {
+    ($($field:ident),* $(,)?) => {
+        fn limit_rows(limits: [&wgpu::Limits; 3]) -> Vec<(&'static str, [u64; 3])> {
+            vec![$((stringify!($field), limits.map(|limits| limits.$field as u64))),*]
+        }
+    };
+}
+
+limit_rows!(
+    max_texture_dimension_1d,
+    max_texture_dimension_2d,
+    max_texture_dimension_3d,
+    max_texture_array_layers,
+    max_bind_groups,
+    max_bindings_per_bind_group,
+    max_dynamic_uniform_buffers_per_pipeline_layout,
+    max_dynamic_storage_buffers_per_pipeline_layout,
+    max_sampled_textures_per_shader_stage,
+    max_samplers_per_shader_stage,
+    max_storage_buffers_per_shader_stage,
+    max_storage_textures_per_shader_stage,
+    max_uniform_buffers_per_shader_stage,
+    max_uniform_buffer_binding_size,
+    max_storage_buffer_binding_size,
+    max_vertex_buffers,
+    max_buffer_size,
+    max_vertex_attributes,
+    max_vertex_buffer_array_stride,
+    min_uniform_buffer_offset_alignment,
+    min_storage_buffer_offset_alignment,
+    max_inter_stage_shader_components,
+    max_compute_workgroup_storage_size,
+    max_compute_invocations_per_workgroup,
+    max_compute_workgroup_size_x,
+    max_compute_workgroup_size_y,
+    max_compute_workgroup_size_z,
+    max_compute_workgroups_per_dimension,
+    max_push_constant_size,
+);
+
+// What the adapter offers, the defaults the request starts from and what was asked for, side by
+// side. Rows where the request is lower than the adapter are marked, the alignments are minimums
+// so lower is more there and they're left unmarked.
+pub fn print_limits(adapter: &wgpu::Limits, baseline: &wgpu::Limits, requested: &wgpu::Limits) {
+    let rows = limit_rows([adapter, baseline, requested]);
+    let width = rows.iter().map(|(name, _)| name.len()).max().unwrap_or(0);
+    println!(
+        "{:width$} {:>12} {:>12} {:>12}",
+        "Limit", "Adapter", "Baseline", "Requested"
+    );
+    for (name, [adapter, baseline, requested]) in rows {
+        let capped = requested < adapter && !name.starts_with("min_");
+        println!(
+            "{name:width$} {adapter:>12} {baseline:>12} {requested:>12}{}",
+            if capped { " capped" } else { "" }
+        );
+    }
+}
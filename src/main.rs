@@ -1,258 +1,6 @@
-use bytemuck::{Pod, Zeroable};
-use std::borrow::Cow;
-use wgpu::util::DeviceExt;
-use winit::{
-    event::{Event, WindowEvent},
-    event_loop::{ControlFlow, EventLoop},
-    window::Window,
-};
-
-#[repr(C)]
-#[derive(Debug, Copy, Clone, Pod, Zeroable)]
-struct Uniforms {
-    resolution: [f32; 2],
-    time: f32,
-    padding: f32,
-}
-
-fn main() {
-    let event_loop = EventLoop::new();
-    let window = winit::window::Window::new(&event_loop).unwrap();
-    let start_time = std::time::Instant::now();
-    pollster::block_on(run(event_loop, window, start_time));
-}
-
-async fn run(event_loop: EventLoop<()>, window: Window, start_time: std::time::Instant) {
-    let instance = create_instance();
-    let surface = unsafe { create_surface(&instance, &window) };
-    let (adapter, device, queue) = create_device_queue(&instance, &surface).await;
-    let shader = create_shader(&device);
-
-    let size = window.inner_size();
-    let uniforms = create_uniforms(&window, start_time);
-    let uniform_buffer = create_uniform_buffer(&device, uniforms);
-
-    let (bind_group_layout, bind_group) = create_bind_group(&device, &uniform_buffer);
-    let pipeline_layout = create_pipeline_layout(&device, &bind_group_layout);
-
-    let (swapchain_capabilities, swapchain_format) =
-        get_swapchain_caps_and_format(&surface, &adapter);
-    let render_pipeline =
-        create_render_pipeline(&device, &shader, &pipeline_layout, swapchain_format);
-
-    let mut config = create_surface_config(&swapchain_capabilities, swapchain_format, size);
-    surface.configure(&device, &config);
-
-    event_loop.run(move |event, _, control_flow| {
-        *control_flow = ControlFlow::Poll;
-        match event {
-            Event::WindowEvent {
-                event: WindowEvent::Resized(new_size),
-                ..
-            } => {
-                config.width = new_size.width;
-                config.height = new_size.height;
-                surface.configure(&device, &config);
-            }
-            Event::MainEventsCleared => {
-                // Redraw request after all other events are finished processing
-                window.request_redraw();
-            }
-            Event::RedrawRequested(_) => {
-                let frame = surface
-                    .get_current_texture()
-                    .expect("Failed to acquire next swap chain texture");
-                let view = frame
-                    .texture
-                    .create_view(&wgpu::TextureViewDescriptor::default());
-
-                let uniforms = create_uniforms(&window, start_time);
-                queue.write_buffer(&uniform_buffer, 0, bytemuck::cast_slice(&[uniforms]));
-
-                let mut encoder =
-                    device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
-                {
-                    let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-                        label: None,
-                        color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                            view: &view,
-                            resolve_target: None,
-                            ops: wgpu::Operations {
-                                load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
-                                store: true,
-                            },
-                        })],
-                        depth_stencil_attachment: None,
-                    });
-                    rpass.set_pipeline(&render_pipeline);
-                    rpass.set_bind_group(0, &bind_group, &[]);
-                    rpass.draw(0..6, 0..1);
-                }
-                queue.submit(Some(encoder.finish()));
-                frame.present();
-            }
-            Event::WindowEvent {
-                event: WindowEvent::CloseRequested,
-                ..
-            } => *control_flow = ControlFlow::Exit,
-            _ => {}
-        }
-    });
-}
-
-// The helper functions used to create and setup the components
-
-fn create_instance() -> wgpu::Instance {
-    wgpu::Instance::default()
-}
-
-unsafe fn create_surface(instance: &wgpu::Instance, window: &Window) -> wgpu::Surface {
-    instance.create_surface(window).unwrap()
-}
-
-async fn create_device_queue(
-    instance: &wgpu::Instance,
-    surface: &wgpu::Surface,
-) -> (wgpu::Adapter, wgpu::Device, wgpu::Queue) {
-    let adapter = instance
-        .request_adapter(&wgpu::RequestAdapterOptions {
-            power_preference: wgpu::PowerPreference::HighPerformance,
-            compatible_surface: Some(surface),
-            force_fallback_adapter: false,
-        })
-        .await
-        .expect("Failed to find an appropriate adapter");
-
-    // Create the logical device and command queue
-    let (device, queue) = adapter
-        .request_device(
-            &wgpu::DeviceDescriptor {
-                label: None,
-                features: wgpu::Features::empty(),
-                limits: wgpu::Limits::downlevel_webgl2_defaults()
-                    .using_resolution(adapter.limits()),
-            },
-            None,
-        )
-        .await
-        .expect("Failed to create device");
-
-    (adapter, device, queue)
-}
-
-fn create_shader(device: &wgpu::Device) -> wgpu::ShaderModule {
-    device.create_shader_module(wgpu::ShaderModuleDescriptor {
-        label: None,
-        source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(include_str!("shader.wgsl"))),
-    })
-}
-
-fn create_uniforms(window: &Window, start_time: std::time::Instant) -> Uniforms {
-    let size = window.inner_size();
-    let elapsed = start_time.elapsed().as_secs_f32();
-    Uniforms {
-        resolution: [size.width as f32, size.height as f32],
-        time: elapsed,
-        padding: 0.0,
-    }
-}
-
-fn create_uniform_buffer(device: &wgpu::Device, uniforms: Uniforms) -> wgpu::Buffer {
-    device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-        label: Some("Uniform Buffer"),
-        contents: bytemuck::cast_slice(&[uniforms]),
-        usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
-    })
-}
-
-fn create_bind_group(
-    device: &wgpu::Device,
-    uniform_buffer: &wgpu::Buffer,
-) -> (wgpu::BindGroupLayout, wgpu::BindGroup) {
-    let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-        label: Some("uniform_bind_group_layout"),
-        entries: &[wgpu::BindGroupLayoutEntry {
-            binding: 0,
-            visibility: wgpu::ShaderStages::FRAGMENT,
-            ty: wgpu::BindingType::Buffer {
-                ty: wgpu::BufferBindingType::Uniform,
-                has_dynamic_offset: false,
-                min_binding_size: None,
-            },
-            count: None,
-        }],
-    });
-
-    let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-        layout: &bind_group_layout,
-        entries: &[wgpu::BindGroupEntry {
-            binding: 0,
-            resource: uniform_buffer.as_entire_binding(),
-        }],
-        label: Some("uniform_bind_group"),
-    });
-
-    (bind_group_layout, bind_group)
-}
-
-fn create_pipeline_layout(
-    device: &wgpu::Device,
-    bind_group_layout: &wgpu::BindGroupLayout,
-) -> wgpu::PipelineLayout {
-    device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
-        label: None,
-        bind_group_layouts: &[bind_group_layout],
-        push_constant_ranges: &[],
-    })
-}
-
-fn get_swapchain_caps_and_format(
-    surface: &wgpu::Surface,
-    adapter: &wgpu::Adapter,
-) -> (wgpu::SurfaceCapabilities, wgpu::TextureFormat) {
-    let swapchain_capabilities = surface.get_capabilities(adapter);
-    let swapchain_format = swapchain_capabilities.formats[0];
-    (swapchain_capabilities, swapchain_format)
-}
-
-fn create_render_pipeline(
-    device: &wgpu::Device,
-    shader: &wgpu::ShaderModule,
-    pipeline_layout: &wgpu::PipelineLayout,
-    swapchain_format: wgpu::TextureFormat,
-) -> wgpu::RenderPipeline {
-    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-        label: None,
-        layout: Some(pipeline_layout),
-        vertex: wgpu::VertexState {
-            module: shader,
-            entry_point: "vs_main",
-            buffers: &[],
-        },
-        fragment: Some(wgpu::FragmentState {
-            module: shader,
-            entry_point: "fs_main",
-            targets: &[Some(swapchain_format.into())],
-        }),
-        primitive: wgpu::PrimitiveState::default(),
-        depth_stencil: None,
-        multisample: wgpu::MultisampleState::default(),
-        multiview: None,
-    })
-}
-
-fn create_surface_config(
-    swapchain_capabilities: &wgpu::SurfaceCapabilities,
-    swapchain_format: wgpu::TextureFormat,
-    size: winit::dpi::PhysicalSize<u32>,
-) -> wgpu::SurfaceConfiguration {
-    wgpu::SurfaceConfiguration {
-        usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
-        format: swapchain_format,
-        width: size.width,
-        height: size.height,
-        present_mode: wgpu::PresentMode::Fifo,
-        alpha_mode: swapchain_capabilities.alpha_modes[0],
-        view_formats: vec![],
-    }
+fn main() -> anyhow::Result<()> {
+    // The browser build starts from `start` in lib.rs instead
+    #[cfg(not(target_arch = "wasm32"))]
+    pollster::block_on(rust_raymarching::run())?;
+    Ok(())
 }
@@ -0,0 +1,249 @@
+// A zoomed out view of the world with --minimap, the tiles and a dot for each entity seen from far
+// above the player. Every few frames it's drawn into a small texture through its own camera, and
+// every frame that texture is drawn into the top right corner of the window.
+use crate::camera::Camera;
+use crate::post::Viewport;
+use crate::state::{self, PipelineSettings, SceneTargets};
+
+// How far out the minimap's camera is, it shows 1 / ZOOM world units across
+const ZOOM: f32 = 0.5;
+// Pixels across an entity's dot in the minimap's texture
+const DOT_PIXELS: f32 = 4.0;
+// Pixels between the minimap and the window's edges
+const MARGIN: f32 = 16.0;
+
+pub struct Minimap {
+    // Pixels along each side of the texture
+    size: u32,
+    // Drawn once every this many frames
+    every: u32,
+    // Frames since it was last drawn, it's drawn on the first
+    since_drawn: Option<u32>,
+    // The uniform slot its camera is written to, one no window has
+    slot: u32,
+    view: wgpu::TextureView,
+    // The depth and MSAA targets the world's pipelines expect to draw with
+    targets: SceneTargets,
+    pipeline: wgpu::RenderPipeline,
+    bind_group: wgpu::BindGroup,
+}
+
+impl Minimap {
+    // The texture has the window's format so the world's pipelines can draw into it
+    pub fn new(
+        device: &wgpu::Device,
+        settings: &PipelineSettings,
+        size: u32,
+        every: u32,
+        slot: u32,
+    ) -> Self {
+        let view = device
+            .create_texture(&wgpu::TextureDescriptor {
+                label: Some("minimap_texture"),
+                size: wgpu::Extent3d {
+                    width: size,
+                    height: size,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format: settings.format,
+                usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                    | wgpu::TextureUsages::TEXTURE_BINDING,
+                view_formats: &[],
+            })
+            .create_view(&wgpu::TextureViewDescriptor::default());
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("minimap_bind_group_layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+        });
+        // Shown at its own size, so nearest keeps the tiles crisp
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("minimap_sampler"),
+            ..Default::default()
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("minimap_bind_group"),
+            layout: &bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&sampler),
+                },
+            ],
+        });
+        Self {
+            size,
+            every: every.max(1),
+            since_drawn: None,
+            slot,
+            view,
+            targets: state::create_scene_targets(device, settings, (size, size)),
+            pipeline: create_composite_pipeline(device, &bind_group_layout, settings.format),
+            bind_group,
+        }
+    }
+
+    pub fn size(&self) -> u32 {
+        self.size
+    }
+
+    pub fn slot(&self) -> u32 {
+        self.slot
+    }
+
+    // What the minimap looks through, centred on the player
+    pub fn camera(&self, player: [f32; 2]) -> Camera {
+        Camera {
+            zoom: ZOOM,
+            ..Camera::centred_on(player)
+        }
+    }
+
+    // World units across a dot, so it's the same few pixels in the texture at the minimap's zoom
+    pub fn dot_size(&self) -> f32 {
+        DOT_PIXELS / (ZOOM * self.size as f32)
+    }
+
+    // Once per frame, whether it's to be drawn again this one
+    pub fn due(&mut self) -> bool {
+        let due = self
+            .since_drawn
+            .is_none_or(|since_drawn| since_drawn + 1 >= self.every);
+        self.since_drawn = Some(if due {
+            0
+        } else {
+            self.since_drawn.unwrap_or(0) + 1
+        });
+        due
+    }
+
+    // Start a pass drawing into the minimap's texture, set up like the scene's
+    pub fn begin_pass<'a>(
+        &'a self,
+        encoder: &'a mut wgpu::CommandEncoder,
+        clear_color: wgpu::Color,
+        depth_clear: f32,
+    ) -> wgpu::RenderPass<'a> {
+        state::begin_render_pass(
+            encoder,
+            &self.view,
+            self.targets.msaa_view.as_ref(),
+            &self.targets.depth_view,
+            self.targets.id_texture.as_ref().map(|(_, view)| view),
+            clear_color,
+            depth_clear,
+        )
+    }
+
+    // Draw the last minimap drawn into the top right of `view`, which is `output_size` pixels.
+    // Skipped when the window is too small to fit it.
+    pub fn composite(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        view: &wgpu::TextureView,
+        output_size: (u32, u32),
+    ) {
+        let Some(viewport) = corner(self.size, output_size) else {
+            return;
+        };
+        let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("minimap_pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: true,
+                },
+            })],
+            depth_stencil_attachment: None,
+        });
+        viewport.apply(&mut rpass);
+        rpass.set_pipeline(&self.pipeline);
+        rpass.set_bind_group(0, &self.bind_group, &[]);
+        rpass.draw(0..3, 0..1);
+    }
+}
+
+// Where a minimap `size` pixels across goes in the output, None if it wouldn't fit with margins
+fn corner(size: u32, (width, height): (u32, u32)) -> Option<Viewport> {
+    let size = size as f32;
+    let x = width as f32 - MARGIN - size;
+    (x >= MARGIN && height as f32 >= 2.0 * MARGIN + size).then_some(Viewport {
+        x,
+        y: MARGIN,
+        width: size,
+        height: size,
+    })
+}
+
+fn create_composite_pipeline(
+    device: &wgpu::Device,
+    bind_group_layout: &wgpu::BindGroupLayout,
+    format: wgpu::TextureFormat,
+) -> wgpu::RenderPipeline {
+    let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some("minimap_shader"),
+        source: wgpu::ShaderSource::Wgsl(include_str!("minimap.wgsl").into()),
+    });
+    let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+        label: Some("minimap_pipeline_layout"),
+        bind_group_layouts: &[bind_group_layout],
+        push_constant_ranges: &[],
+    });
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("minimap_pipeline"),
+        layout: Some(&pipeline_layout),
+        vertex: wgpu::VertexState {
+            module: &shader,
+            entry_point: "vs_main",
+            buffers: &[],
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: &shader,
+            entry_point: "fs_main",
+            targets: &[Some(format.into())],
+        }),
+        primitive: wgpu::PrimitiveState::default(),
+        depth_stencil: None,
+        multisample: wgpu::MultisampleState::default(),
+        multiview: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sits_in_the_top_right_when_it_fits() {
+        let viewport = corner(160, (800, 600)).unwrap();
+        assert_eq!([viewport.x, viewport.y], [624.0, 16.0]);
+        assert_eq!([viewport.width, viewport.height], [160.0, 160.0]);
+        assert!(corner(160, (180, 600)).is_none());
+        assert!(corner(160, (800, 180)).is_none());
+    }
+}
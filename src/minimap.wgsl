@@ -0,0 +1,37 @@
+// The minimap's texture drawn into the corner of the frame, the viewport places it
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) tex_coords: vec2<f32>,
+};
+
+// One triangle covering the viewport, the parts outside get clipped
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> VertexOutput {
+    let position = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+    var out: VertexOutput;
+    out.position = vec4<f32>(position * 2.0 - 1.0, 0.0, 1.0);
+    // Texture rows go down while clip space goes up
+    out.tex_coords = vec2<f32>(position.x, 1.0 - position.y);
+    return out;
+}
+
+@group(0) @binding(0)
+var minimap_texture: texture_2d<f32>;
+@group(0) @binding(1)
+var minimap_sampler: sampler;
+
+// Texels of dark border around the edge, so the map stands out from the scene behind it
+const BORDER: f32 = 2.0;
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let colour = textureSample(minimap_texture, minimap_sampler, in.tex_coords);
+    let size = vec2<f32>(textureDimensions(minimap_texture));
+    let texel = in.tex_coords * size;
+    let edge = min(min(texel.x, texel.y), min(size.x - texel.x, size.y - texel.y));
+    if edge < BORDER {
+        return vec4<f32>(0.0, 0.0, 0.0, 1.0);
+    }
+    return vec4<f32>(colour.rgb, 1.0);
+}
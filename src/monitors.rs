@@ -0,0 +1,176 @@
+// Listing the connected monitors for --list-monitors, opening on one of them with --monitor and
+// picking its video mode for --fullscreen-exclusive
+use winit::{
+    dpi::{PhysicalPosition, PhysicalSize},
+    event_loop::EventLoop,
+    monitor::{MonitorHandle, VideoMode},
+    window::{Fullscreen, WindowBuilder},
+};
+
+// Size of a window opened on a chosen monitor, shrunk to fit smaller ones
+const WINDOW_SIZE: PhysicalSize<u32> = PhysicalSize::new(1280, 720);
+
+// One line per monitor, numbered the way --monitor takes them
+pub fn print(event_loop: &EventLoop<()>) {
+    let monitors: Vec<MonitorHandle> = event_loop.available_monitors().collect();
+    if monitors.is_empty() {
+        println!("No monitors found");
+    }
+    let primary = event_loop.primary_monitor();
+    for (index, monitor) in monitors.iter().enumerate() {
+        let size = monitor.size();
+        let position = monitor.position();
+        let refresh_rate = match monitor.refresh_rate_millihertz() {
+            Some(millihertz) => format!("{:.2} Hz", f64::from(millihertz) / 1000.0),
+            None => "unknown refresh rate".to_string(),
+        };
+        println!(
+            "{index}: {} {}x{} at {},{} scale {} {refresh_rate}{}",
+            monitor.name().as_deref().unwrap_or("Unnamed monitor"),
+            size.width,
+            size.height,
+            position.x,
+            position.y,
+            monitor.scale_factor(),
+            if primary.as_ref() == Some(monitor) {
+                " primary"
+            } else {
+                ""
+            }
+        );
+    }
+}
+
+// Centre the window on monitor `index`, and with `fullscreen` cover it instead. Unknown indices
+// leave the builder as it is.
+pub fn place(
+    builder: WindowBuilder,
+    event_loop: &EventLoop<()>,
+    index: usize,
+    fullscreen: bool,
+) -> WindowBuilder {
+    let Some(monitor) = event_loop.available_monitors().nth(index) else {
+        log::warn!(
+            "No monitor {index}, there are {}, see --list-monitors",
+            event_loop.available_monitors().count()
+        );
+        return builder;
+    };
+    let origin = monitor.position();
+    let size = monitor.size();
+    let width = WINDOW_SIZE.width.min(size.width);
+    let height = WINDOW_SIZE.height.min(size.height);
+    let builder = builder
+        .with_inner_size(PhysicalSize::new(width, height))
+        .with_position(PhysicalPosition::new(
+            origin.x + ((size.width - width) / 2) as i32,
+            origin.y + ((size.height - height) / 2) as i32,
+        ));
+    if fullscreen {
+        builder.with_fullscreen(Some(Fullscreen::Borderless(Some(monitor))))
+    } else {
+        builder
+    }
+}
+
+// Monitor `index` if given and connected, otherwise the primary one or failing that the first
+pub fn choose(event_loop: &EventLoop<()>, index: Option<usize>) -> Option<MonitorHandle> {
+    index
+        .and_then(|index| event_loop.available_monitors().nth(index))
+        .or_else(|| event_loop.primary_monitor())
+        .or_else(|| event_loop.available_monitors().next())
+}
+
+// Exclusive fullscreen on `monitor` in the video mode closest to the hints, None if it lists no
+// modes. Refresh rates are in hertz.
+pub fn exclusive_fullscreen(
+    monitor: &MonitorHandle,
+    resolution: Option<(u32, u32)>,
+    refresh_rate: Option<u32>,
+) -> Option<Fullscreen> {
+    let modes: Vec<VideoMode> = monitor.video_modes().collect();
+    let summaries: Vec<ModeSummary> = modes
+        .iter()
+        .map(|mode| ModeSummary {
+            size: (mode.size().width, mode.size().height),
+            millihertz: mode.refresh_rate_millihertz(),
+            bit_depth: mode.bit_depth(),
+        })
+        .collect();
+    let Some(index) = closest_mode(&summaries, resolution, refresh_rate) else {
+        log::warn!("The monitor has no video modes for exclusive fullscreen");
+        return None;
+    };
+    let ModeSummary {
+        size: (width, height),
+        millihertz,
+        bit_depth,
+    } = summaries[index];
+    log::info!(
+        "Exclusive fullscreen at {width}x{height} {:.2} Hz {bit_depth} bit",
+        f64::from(millihertz) / 1000.0
+    );
+    Some(Fullscreen::Exclusive(modes[index].clone()))
+}
+
+// What matters for picking a video mode
+#[derive(Debug, Clone, Copy)]
+struct ModeSummary {
+    size: (u32, u32),
+    millihertz: u32,
+    bit_depth: u16,
+}
+
+// The mode nearest the requested resolution, then refresh rate, then with the most bits. Without
+// a hint the largest or fastest mode wins.
+fn closest_mode(
+    modes: &[ModeSummary],
+    resolution: Option<(u32, u32)>,
+    refresh_rate: Option<u32>,
+) -> Option<usize> {
+    let size_cost = |mode: &ModeSummary| match resolution {
+        Some((width, height)) => {
+            i64::from(mode.size.0.abs_diff(width)) + i64::from(mode.size.1.abs_diff(height))
+        }
+        None => -(i64::from(mode.size.0) * i64::from(mode.size.1)),
+    };
+    let refresh_cost = |mode: &ModeSummary| match refresh_rate {
+        Some(hertz) => i64::from(mode.millihertz.abs_diff(hertz * 1000)),
+        None => -i64::from(mode.millihertz),
+    };
+    (0..modes.len()).min_by_key(|&index| {
+        let mode = &modes[index];
+        (
+            size_cost(mode),
+            refresh_cost(mode),
+            -i64::from(mode.bit_depth),
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mode(width: u32, height: u32, hertz: u32, bit_depth: u16) -> ModeSummary {
+        ModeSummary {
+            size: (width, height),
+            millihertz: hertz * 1000,
+            bit_depth,
+        }
+    }
+
+    #[test]
+    fn closest_mode_prefers_size_then_refresh_rate() {
+        let modes = [
+            mode(1920, 1080, 60, 32),
+            mode(1920, 1080, 144, 32),
+            mode(2560, 1440, 144, 32),
+            mode(1280, 720, 60, 32),
+        ];
+        assert_eq!(closest_mode(&modes, Some((1920, 1080)), Some(120)), Some(1));
+        assert_eq!(closest_mode(&modes, Some((1300, 700)), None), Some(3));
+        assert_eq!(closest_mode(&modes, None, None), Some(2));
+        assert_eq!(closest_mode(&[], None, None), None);
+    }
+}
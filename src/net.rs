@@ -0,0 +1,358 @@
+// Multiplayer behind the `net` feature. One instance hosts with --serve and the others join with
+// --connect. Every client sends its player's position each logic step and the server passes them
+// on to every client, which draws the other players as sprites. The server and each connection
+// run on their own threads with a small tokio runtime. They talk to the game through channels, so
+// the render thread never waits on the network.
+//
+// Each message is RON text sent after its length, as a big endian u32.
+use crate::atlas;
+use crate::ecs::{self, Sprite, Transform};
+use crate::options::Options;
+use anyhow::Context;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio::sync::{broadcast, mpsc};
+
+// Anything longer is a broken or hostile peer, positions take a few dozen bytes
+const MAX_MESSAGE_LEN: u32 = 1024;
+// Updates the server holds for a client that's slow to read them. Past this it skips the
+// oldest, a player that left in the gap stays behind on that client until it reconnects.
+const BROADCAST_CAPACITY: usize = 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum ToServer {
+    // Where this client's player is, the server knows which player it is from the connection
+    Position([f32; 2]),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum ToClient {
+    // Sent first, the id this client's own positions come back with
+    Welcome(u32),
+    Position { id: u32, position: [f32; 2] },
+    Left(u32),
+}
+
+async fn write_message<T: Serialize>(
+    stream: &mut (impl AsyncWrite + Unpin),
+    message: &T,
+) -> anyhow::Result<()> {
+    let text = ron::to_string(message)?;
+    stream.write_u32(text.len() as u32).await?;
+    stream.write_all(text.as_bytes()).await?;
+    Ok(())
+}
+
+// None once the peer has closed the connection
+async fn read_message<T: DeserializeOwned>(
+    stream: &mut (impl AsyncRead + Unpin),
+) -> anyhow::Result<Option<T>> {
+    let len = match stream.read_u32().await {
+        Ok(len) => len,
+        Err(error) if error.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(error) => return Err(error.into()),
+    };
+    anyhow::ensure!(len <= MAX_MESSAGE_LEN, "Message of {len} bytes is too long");
+    let mut bytes = vec![0; len as usize];
+    stream.read_exact(&mut bytes).await?;
+    Ok(Some(ron::from_str(std::str::from_utf8(&bytes)?)?))
+}
+
+fn create_runtime() -> anyhow::Result<tokio::runtime::Runtime> {
+    tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .context("Failed to start the network runtime")
+}
+
+// Accept clients on a background thread for as long as the game runs, returns the address it
+// listens on
+pub fn serve(address: &str) -> anyhow::Result<SocketAddr> {
+    let listener = std::net::TcpListener::bind(address)
+        .with_context(|| format!("Failed to listen on {address}"))?;
+    listener.set_nonblocking(true)?;
+    let local_address = listener.local_addr()?;
+    let runtime = create_runtime()?;
+    std::thread::Builder::new()
+        .name("net server".into())
+        .spawn(move || {
+            runtime.block_on(async {
+                match tokio::net::TcpListener::from_std(listener) {
+                    Ok(listener) => run_server(listener).await,
+                    Err(error) => log::error!("Failed to start the server: {error}"),
+                }
+            })
+        })?;
+    log::info!("Serving on {local_address}");
+    Ok(local_address)
+}
+
+async fn run_server(listener: tokio::net::TcpListener) {
+    let (updates, _) = broadcast::channel(BROADCAST_CAPACITY);
+    let mut next_id = 0;
+    loop {
+        match listener.accept().await {
+            Ok((stream, peer)) => {
+                let id = next_id;
+                next_id += 1;
+                log::info!("Player {id} joined from {peer}");
+                tokio::spawn(serve_client(stream, id, updates.clone()));
+            }
+            Err(error) => log::warn!("Failed to accept a connection: {error}"),
+        }
+    }
+}
+
+// Pass this client's positions on to everyone, itself included, and everyone's to it
+async fn serve_client(stream: TcpStream, id: u32, updates: broadcast::Sender<ToClient>) {
+    let _ = stream.set_nodelay(true);
+    let (mut reader, mut writer) = stream.into_split();
+    let mut received = updates.subscribe();
+    let reading = async {
+        while let Some(ToServer::Position(position)) = read_message(&mut reader).await? {
+            // Only fails with no clients to send to
+            let _ = updates.send(ToClient::Position { id, position });
+        }
+        anyhow::Ok(())
+    };
+    let writing = async {
+        write_message(&mut writer, &ToClient::Welcome(id)).await?;
+        loop {
+            match received.recv().await {
+                Ok(message) => write_message(&mut writer, &message).await?,
+                Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                    log::warn!("Player {id} fell {skipped} updates behind");
+                }
+                Err(broadcast::error::RecvError::Closed) => return anyhow::Ok(()),
+            }
+        }
+    };
+    let result = tokio::select! {
+        result = reading => result,
+        result = writing => result,
+    };
+    match result {
+        Ok(()) => log::info!("Player {id} left"),
+        Err(error) => log::warn!("Dropped player {id}: {error:#}"),
+    }
+    let _ = updates.send(ToClient::Left(id));
+}
+
+// The game's end of a connection to a server
+pub struct Client {
+    outgoing: mpsc::UnboundedSender<ToServer>,
+    incoming: std::sync::mpsc::Receiver<ToClient>,
+}
+
+impl Client {
+    // Connects before returning so a wrong address fails straight away, the connection then runs
+    // on its own thread until the client is dropped
+    pub fn connect(address: &str) -> anyhow::Result<Self> {
+        let stream = std::net::TcpStream::connect(address)
+            .with_context(|| format!("Failed to connect to {address}"))?;
+        stream.set_nonblocking(true)?;
+        stream.set_nodelay(true)?;
+        let runtime = create_runtime()?;
+        let (outgoing, outgoing_receiver) = mpsc::unbounded_channel();
+        let (incoming_sender, incoming) = std::sync::mpsc::channel();
+        std::thread::Builder::new()
+            .name("net client".into())
+            .spawn(move || {
+                let result = runtime.block_on(async {
+                    let stream = TcpStream::from_std(stream)?;
+                    run_client(stream, outgoing_receiver, incoming_sender).await
+                });
+                match result {
+                    Ok(()) => log::info!("Disconnected from the server"),
+                    Err(error) => log::warn!("Lost the connection to the server: {error:#}"),
+                }
+            })?;
+        log::info!("Connected to {address}");
+        Ok(Self { outgoing, incoming })
+    }
+
+    // Dropped if the connection has gone
+    pub fn send(&self, message: ToServer) {
+        let _ = self.outgoing.send(message);
+    }
+
+    // Everything the server sent since the last call, without waiting for more
+    pub fn receive(&self) -> impl Iterator<Item = ToClient> + '_ {
+        self.incoming.try_iter()
+    }
+}
+
+// Until either side closes the connection or the game drops the client
+async fn run_client(
+    stream: TcpStream,
+    mut outgoing: mpsc::UnboundedReceiver<ToServer>,
+    incoming: std::sync::mpsc::Sender<ToClient>,
+) -> anyhow::Result<()> {
+    let (mut reader, mut writer) = stream.into_split();
+    let reading = async {
+        while let Some(message) = read_message(&mut reader).await? {
+            if incoming.send(message).is_err() {
+                break;
+            }
+        }
+        anyhow::Ok(())
+    };
+    let writing = async {
+        while let Some(message) = outgoing.recv().await {
+            write_message(&mut writer, &message).await?;
+        }
+        anyhow::Ok(())
+    };
+    tokio::select! {
+        result = reading => result,
+        result = writing => result,
+    }
+}
+
+// A connection and the other players it has heard about, as entities in the game's world
+pub struct Network {
+    client: Client,
+    // None until the server's welcome arrives
+    own_id: Option<u32>,
+    players: HashMap<u32, ecs::Entity>,
+}
+
+impl Network {
+    // Host with --serve, joining the server too unless --connect goes elsewhere, or join with
+    // --connect. None without either or after a warning.
+    pub fn start(options: &Options) -> Option<Self> {
+        let hosted = options
+            .serve
+            .as_deref()
+            .and_then(|address| match serve(address) {
+                Ok(mut local_address) => {
+                    // Listening on every interface, this game joins over loopback
+                    if local_address.ip().is_unspecified() {
+                        local_address.set_ip(match local_address.ip() {
+                            IpAddr::V4(_) => Ipv4Addr::LOCALHOST.into(),
+                            IpAddr::V6(_) => Ipv6Addr::LOCALHOST.into(),
+                        });
+                    }
+                    Some(local_address)
+                }
+                Err(error) => {
+                    log::warn!("{error:#}, not hosting");
+                    None
+                }
+            });
+        let address = match (&options.connect, hosted) {
+            (Some(address), _) => address.clone(),
+            (None, Some(local_address)) => local_address.to_string(),
+            (None, None) => return None,
+        };
+        match Client::connect(&address) {
+            Ok(client) => Some(Self {
+                client,
+                own_id: None,
+                players: HashMap::new(),
+            }),
+            Err(error) => {
+                log::warn!("{error:#}, playing offline");
+                None
+            }
+        }
+    }
+
+    // Once per logic step, after `begin_step` so the other players' sprites blend between the
+    // positions they were sent at
+    pub fn step(&mut self, world: &mut ecs::World, position: [f32; 2]) {
+        self.client.send(ToServer::Position(position));
+        for message in self.client.receive() {
+            match message {
+                ToClient::Welcome(id) => self.own_id = Some(id),
+                // The player this game draws itself
+                ToClient::Position { id, .. } if Some(id) == self.own_id => {}
+                ToClient::Position { id, position } => {
+                    let entity = *self.players.entry(id).or_insert_with(|| {
+                        let entity = world.spawn();
+                        world.insert(
+                            entity,
+                            Sprite {
+                                tile: atlas::CHARACTER,
+                            },
+                        );
+                        entity
+                    });
+                    world.insert(entity, Transform::at(position));
+                }
+                ToClient::Left(id) => {
+                    if let Some(entity) = self.players.remove(&id) {
+                        world.despawn(entity);
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, Instant};
+
+    // Everything the client is sent until `done` is happy with it, failing after a few seconds
+    fn receive_until(client: &Client, done: impl Fn(&[ToClient]) -> bool) -> Vec<ToClient> {
+        let start = Instant::now();
+        let mut received = Vec::new();
+        while !done(&received) {
+            assert!(
+                start.elapsed() < Duration::from_secs(5),
+                "Timed out with {received:?}"
+            );
+            received.extend(client.receive());
+            std::thread::sleep(Duration::from_millis(5));
+        }
+        received
+    }
+
+    #[test]
+    fn clients_see_each_others_positions() {
+        let address = serve("127.0.0.1:0").unwrap().to_string();
+        let first = Client::connect(&address).unwrap();
+        let second = Client::connect(&address).unwrap();
+        let welcome = |client: &Client| {
+            let received = receive_until(client, |received| !received.is_empty());
+            let ToClient::Welcome(id) = received[0] else {
+                panic!("Expected a welcome, got {received:?}");
+            };
+            id
+        };
+        let (first_id, second_id) = (welcome(&first), welcome(&second));
+        assert_ne!(first_id, second_id);
+
+        first.send(ToServer::Position([1.5, -2.0]));
+        let expected = ToClient::Position {
+            id: first_id,
+            position: [1.5, -2.0],
+        };
+        receive_until(&second, |received| received.contains(&expected));
+        // Echoed back to the sender as well
+        receive_until(&first, |received| received.contains(&expected));
+
+        drop(first);
+        receive_until(&second, |received| {
+            received.contains(&ToClient::Left(first_id))
+        });
+    }
+
+    #[test]
+    fn oversized_messages_are_refused() {
+        let runtime = create_runtime().unwrap();
+        let mut bytes = (MAX_MESSAGE_LEN + 1).to_be_bytes().to_vec();
+        bytes.resize(bytes.len() + MAX_MESSAGE_LEN as usize + 1, b' ');
+        let result = runtime.block_on(read_message::<ToServer>(&mut bytes.as_slice()));
+        assert!(result.is_err());
+        // A closed connection before the length is a clean end
+        let result = runtime.block_on(read_message::<ToServer>(&mut [].as_slice()));
+        assert!(result.unwrap().is_none());
+    }
+}
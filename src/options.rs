@@ -0,0 +1,685 @@
+use crate::color::Color;
+
+// How the surface stores colour, sRGB surfaces encode the shader's linear output on write
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorSpace {
+    Srgb,
+    Linear,
+}
+
+// How quickly --follow catches up and how much of the screen the player can move around without
+// it, see `camera::Follow`
+const FOLLOW_DAMPING: f32 = 8.0;
+const DEAD_ZONE: [f32; 2] = [0.2, 0.2];
+
+// Pixels along each side of the --minimap and how many frames apart it's redrawn
+const MINIMAP_SIZE: u32 = 160;
+const MINIMAP_EVERY: u32 = 4;
+
+// Past this the scene's textures get huge and the last pass takes dozens of samples a pixel
+const MAX_SSAA: u32 = 4;
+
+// Startup options parsed from the command line
+#[derive(Debug)]
+pub struct Options {
+    // Only redraw when the window asks for it instead of rendering continuously
+    pub static_scene: bool,
+    // Let the clock run on while no window is visible, so the scene has moved on when one comes
+    // back instead of carrying on from where it stopped
+    pub background_animation: bool,
+    // Load the shader from disk and rebuild the pipeline whenever it changes
+    pub hot_reload: bool,
+    // Edit the shader in a panel in the window and compile it from there
+    pub editor: bool,
+    // Log the frame rate, frame times and CPU and GPU time once a second
+    pub stats: bool,
+    // Add the GPU time of each pass to that, implies --stats
+    pub profile: bool,
+    // Log how long each step of starting up took once the first frame is drawn, see startup.rs
+    pub profile_startup: bool,
+    // Upper limit on the frame rate when rendering continuously, applied on top of vsync
+    pub max_fps: Option<u32>,
+    // Don't fall back to a cap on battery either, see power.rs
+    pub uncapped: bool,
+    // Graphics APIs wgpu may pick an adapter from
+    pub backends: wgpu::Backends,
+    // Index or name of the GPU adapter to use instead of the high performance default
+    pub adapter: Option<String>,
+    // Print the adapter's limits next to the requested ones, and with `--limits max` request
+    // everything the adapter supports instead of the portable baseline
+    pub print_limits: bool,
+    pub max_limits: bool,
+    // Requested swapchain present mode, falls back to Fifo when unsupported
+    pub present_mode: Option<wgpu::PresentMode>,
+    // Surface format to pick, an sRGB one when it's available if not given
+    pub color_space: Option<ColorSpace>,
+    // Pick an HDR surface format when the surface has one, falling back to SDR
+    pub hdr: bool,
+    // What the scene's pass clears to before drawing, as written in the hex colour
+    pub clear_color: Color,
+    // Multiplies the scene shader's colour, as written too
+    pub tint: Color,
+    // Let the shader's alpha show the desktop behind the window, where the compositor allows it
+    pub transparent: bool,
+    // Samples per pixel for multisample anti-aliasing, 1 disables it
+    pub msaa: u32,
+    // Render the scene at this many times the window's size along each axis and average it down,
+    // 1 disables it
+    pub ssaa: u32,
+    // WGSL file to run instead of the embedded shader
+    pub shader: Option<std::path::PathBuf>,
+    // Where else the shader's #include directives look, after the including file's directory
+    pub include_dirs: Vec<std::path::PathBuf>,
+    // Images bound for the shader to sample, one per --texture up to MAX_TEXTURES, and whether
+    // they hold linear data rather than colour
+    pub textures: Vec<std::path::PathBuf>,
+    pub linear_texture: bool,
+    // Draw the scene straight to the window without the post-processing passes
+    pub single_pass: bool,
+    // Draw the scene at this size and scale it up by whole pixels, for crisp pixel art
+    pub internal_res: Option<(u32, u32)>,
+    // Draw the scene at down to half the window's resolution when frames take longer than this
+    // many milliseconds, and scale it up to fit
+    pub target_ms: Option<f32>,
+    // Keep each window's previous frame for the shader to sample, needs the post-processing pass
+    pub feedback: bool,
+    // Seed for the shaders' noise, the same one gives the same noise
+    pub seed: u32,
+    // Draw the world's tile map and sprites over the scene, implied by --load
+    pub tilemap: bool,
+    // Draw the tile map and sprites as the edges of their triangles, where the device can
+    pub wireframe: bool,
+    // Clear depth to 0 and keep what's nearer by its greater depth, see
+    // `PipelineSettings::reverse_z` for why
+    pub reverse_z: bool,
+    // Log which entity is under the cursor on a left click, see picking.rs
+    pub picking: bool,
+    // Move the player with the keys and ease the camera after it, instead of moving the camera
+    // with the player in the middle
+    pub follow: bool,
+    pub follow_damping: f32,
+    pub dead_zone: [f32; 2],
+    // Start with the world space grid shown, G toggles it
+    pub grid: bool,
+    // Start with the frame time graph shown, F2 toggles it
+    pub perf_graph: bool,
+    // Show a zoomed out view of the world in the corner, see minimap.rs
+    pub minimap: bool,
+    pub minimap_size: u32,
+    pub minimap_every: u32,
+    // Saved world to start from, also where F5 saves to
+    pub load: Option<std::path::PathBuf>,
+    // Run the cellular automaton in a compute pass and draw it over the scene
+    pub compute: bool,
+    // Print the connected monitors and exit
+    pub list_monitors: bool,
+    // Monitor to open on, numbered as --list-monitors shows them
+    pub monitor: Option<usize>,
+    // Start in borderless fullscreen, on --monitor if given
+    pub fullscreen: bool,
+    // Start in exclusive fullscreen instead, in the video mode closest to the resolution and
+    // refresh rate in hertz, the largest and fastest if they aren't given
+    pub fullscreen_exclusive: bool,
+    pub resolution: Option<(u32, u32)>,
+    pub refresh_rate: Option<u32>,
+    // Spray particles from the cursor, moved by a compute pass
+    pub particles: bool,
+    // Windows to open, they share the device and pipelines but each has its own surface
+    pub windows: usize,
+    // Render this many frames offscreen as fast as possible, print their timings and exit
+    pub bench: Option<u32>,
+    // Render `frames` frames offscreen at a fixed step and save each to a numbered image, the
+    // pattern's %05d or %d is replaced with the frame number
+    pub record: Option<std::path::PathBuf>,
+    pub frames: Option<u32>,
+    // Save every input event to this file, or play one back in place of real input
+    pub record_input: Option<std::path::PathBuf>,
+    pub replay_input: Option<std::path::PathBuf>,
+    // Host a multiplayer server on this address, and join another with --connect. Builds
+    // without the `net` feature warn and play alone.
+    pub serve: Option<String>,
+    pub connect: Option<String>,
+    // Render a single frame to `output` without opening a window
+    pub headless: bool,
+    pub output: Option<std::path::PathBuf>,
+    // Resolution of the headless frame
+    pub size: Option<(u32, u32)>,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            static_scene: false,
+            background_animation: false,
+            hot_reload: false,
+            editor: false,
+            stats: false,
+            profile: false,
+            profile_startup: false,
+            max_fps: None,
+            uncapped: false,
+            backends: wgpu::Backends::all(),
+            adapter: None,
+            print_limits: false,
+            max_limits: false,
+            present_mode: None,
+            color_space: None,
+            hdr: false,
+            clear_color: Color::BLACK,
+            tint: Color::WHITE,
+            transparent: false,
+            msaa: 1,
+            ssaa: 1,
+            shader: None,
+            include_dirs: Vec::new(),
+            textures: Vec::new(),
+            linear_texture: false,
+            single_pass: false,
+            feedback: false,
+            internal_res: None,
+            target_ms: None,
+            seed: 0,
+            tilemap: false,
+            wireframe: false,
+            reverse_z: false,
+            picking: false,
+            follow: false,
+            follow_damping: FOLLOW_DAMPING,
+            dead_zone: DEAD_ZONE,
+            minimap: false,
+            minimap_size: MINIMAP_SIZE,
+            minimap_every: MINIMAP_EVERY,
+            grid: false,
+            perf_graph: false,
+            load: None,
+            compute: false,
+            particles: false,
+            list_monitors: false,
+            monitor: None,
+            fullscreen: false,
+            fullscreen_exclusive: false,
+            resolution: None,
+            refresh_rate: None,
+            windows: 1,
+            bench: None,
+            record: None,
+            record_input: None,
+            replay_input: None,
+            serve: None,
+            connect: None,
+            frames: None,
+            headless: false,
+            output: None,
+            size: None,
+        }
+    }
+}
+
+// Length of the input texture array, see `input_textures` in shader.wgsl
+pub const MAX_TEXTURES: usize = 4;
+
+// Read from the working directory before the command line, which overrides it
+#[cfg(not(target_arch = "wasm32"))]
+const CONFIG_PATH: &str = "danrpg.toml";
+
+impl Options {
+    pub fn parse() -> Self {
+        let mut options = Self::default();
+        #[cfg(not(target_arch = "wasm32"))]
+        options.load_config_file(std::path::Path::new(CONFIG_PATH));
+        options.apply_args(std::env::args().skip(1));
+        options
+    }
+
+    fn apply_args(&mut self, args: impl Iterator<Item = String>) {
+        let mut args = args.peekable();
+        // Lists the command line has started, the config file's entries are dropped from each
+        // rather than added to
+        let mut replaced = Vec::new();
+        while let Some(arg) = args.next() {
+            let known = arg.strip_prefix("--").is_some_and(|name| {
+                if let Some(list) = self.list(name) {
+                    if !replaced.iter().any(|list| list == name) {
+                        list.clear();
+                        replaced.push(name.to_owned());
+                    }
+                }
+                // A missing value is empty rather than taking the next option, so `--limits`
+                // works without one
+                self.set_flag(name)
+                    || self.set_value(name, || {
+                        args.next_if(|next| !next.starts_with("--"))
+                            .unwrap_or_default()
+                    })
+            });
+            if !known {
+                log::warn!("Ignoring unknown argument {arg}");
+            }
+        }
+    }
+
+    // The options that add to a list each time they're given
+    fn list(&mut self, name: &str) -> Option<&mut Vec<std::path::PathBuf>> {
+        match name {
+            "include-dir" => Some(&mut self.include_dirs),
+            "texture" => Some(&mut self.textures),
+            _ => None,
+        }
+    }
+
+    // Each key is a flag without the dashes, like `msaa = 4` or `single-pass = true`. A missing
+    // file leaves the defaults.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn load_config_file(&mut self, path: &std::path::Path) {
+        let text = match std::fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => return,
+            Err(error) => {
+                log::error!("Failed to read {}: {error}", path.display());
+                return;
+            }
+        };
+        let table = match toml::from_str::<toml::Table>(&text) {
+            Ok(table) => table,
+            Err(error) => {
+                log::error!("Failed to parse {}, ignoring it: {error}", path.display());
+                return;
+            }
+        };
+        self.apply_config(table, path);
+    }
+
+    // The settings in a config file's table, `path` is only for the warnings
+    #[cfg(not(target_arch = "wasm32"))]
+    fn apply_config(&mut self, table: toml::Table, path: &std::path::Path) {
+        for (key, value) in table {
+            if !self.set_config(&key, value) {
+                log::warn!("Ignoring unknown setting {key} in {}", path.display());
+            }
+        }
+    }
+
+    // One setting from a config file, returns false if there's no such option. An array gives a
+    // list option each of its values, like `texture = ["a.png", "b.png"]`.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn set_config(&mut self, key: &str, value: toml::Value) -> bool {
+        match value {
+            // False is already the default for every flag, so it's only checked to be one
+            toml::Value::Boolean(false) => Self::default().set_flag(key),
+            toml::Value::Boolean(true) => self.set_flag(key),
+            toml::Value::String(value) => self.set_value(key, || value),
+            toml::Value::Integer(value) => self.set_value(key, || value.to_string()),
+            toml::Value::Float(value) => self.set_value(key, || value.to_string()),
+            toml::Value::Array(values) => {
+                values.into_iter().all(|value| self.set_config(key, value))
+            }
+            toml::Value::Datetime(_) | toml::Value::Table(_) => false,
+        }
+    }
+
+    // Switch on a flag that takes no value, returns false if there's no such flag
+    fn set_flag(&mut self, name: &str) -> bool {
+        match name {
+            "static" => self.static_scene = true,
+            "uncapped" => self.uncapped = true,
+            "background-animation" => self.background_animation = true,
+            "hot-reload" => self.hot_reload = true,
+            "editor" => self.editor = true,
+            "stats" => self.stats = true,
+            "profile-startup" => self.profile_startup = true,
+            "profile" => {
+                self.profile = true;
+                self.stats = true;
+            }
+            "transparent" => self.transparent = true,
+            "hdr" => self.hdr = true,
+            "linear-texture" => self.linear_texture = true,
+            "single-pass" => self.single_pass = true,
+            "feedback" => self.feedback = true,
+            "tilemap" => self.tilemap = true,
+            "wireframe" => self.wireframe = true,
+            "reverse-z" => self.reverse_z = true,
+            "picking" => self.picking = true,
+            "follow" => self.follow = true,
+            "grid" => self.grid = true,
+            "perf-graph" => self.perf_graph = true,
+            "minimap" => self.minimap = true,
+            "compute" => self.compute = true,
+            "particles" => self.particles = true,
+            "headless" => self.headless = true,
+            "list-monitors" => self.list_monitors = true,
+            "fullscreen" => self.fullscreen = true,
+            "fullscreen-exclusive" => self.fullscreen_exclusive = true,
+            _ => return false,
+        }
+        true
+    }
+
+    // Set an option that takes a value, only calling `value` for a known name so the command
+    // line doesn't consume the next argument otherwise. Returns false if there's no such option.
+    fn set_value(&mut self, name: &str, value: impl FnOnce() -> String) -> bool {
+        match name {
+            "max-fps" => {
+                let value = value();
+                self.max_fps = value.parse().ok().filter(|&fps| fps > 0);
+                if self.max_fps.is_none() {
+                    log::warn!("Invalid frame rate cap {value:?}");
+                }
+            }
+            "backends" => {
+                let value = value();
+                match parse_backends(&value) {
+                    Some(backends) => self.backends = backends,
+                    None => log::warn!(
+                        "Invalid backends {value:?}, expected a comma separated list of vulkan, \
+                         dx12, dx11, metal, gl or webgpu"
+                    ),
+                }
+            }
+            // Shorthand for the present mode, turning vsync off also reports frame timings
+            "vsync" => match value().as_str() {
+                "on" => self.present_mode = Some(wgpu::PresentMode::Fifo),
+                "off" => {
+                    self.present_mode = Some(wgpu::PresentMode::Immediate);
+                    self.stats = true;
+                }
+                other => log::warn!("Unknown vsync setting {other:?}, expected on or off"),
+            },
+            "adapter" => self.adapter = Some(value()),
+            "limits" => {
+                self.print_limits = true;
+                match value().as_str() {
+                    "" => {}
+                    "max" => self.max_limits = true,
+                    other => log::warn!("Unknown limits {other:?}, expected max or nothing"),
+                }
+            }
+            "present-mode" => {
+                self.present_mode = match value().as_str() {
+                    "fifo" => Some(wgpu::PresentMode::Fifo),
+                    "mailbox" => Some(wgpu::PresentMode::Mailbox),
+                    "immediate" => Some(wgpu::PresentMode::Immediate),
+                    other => {
+                        log::warn!(
+                            "Unknown present mode {other:?}, expected fifo, mailbox or immediate"
+                        );
+                        None
+                    }
+                }
+            }
+            "color-space" => {
+                self.color_space = match value().as_str() {
+                    "srgb" => Some(ColorSpace::Srgb),
+                    "linear" => Some(ColorSpace::Linear),
+                    other => {
+                        log::warn!("Unknown color space {other:?}, expected srgb or linear");
+                        None
+                    }
+                }
+            }
+            "clear-color" => {
+                let value = value();
+                match Color::from_hex(&value) {
+                    Some(color) => self.clear_color = color,
+                    None => log::error!(
+                        "Invalid clear color {value:?}, expected six hex digits like ff8800, \
+                         keeping black"
+                    ),
+                }
+            }
+            "tint" => {
+                let value = value();
+                match Color::from_hex(&value) {
+                    Some(color) => self.tint = color,
+                    None => log::error!(
+                        "Invalid tint {value:?}, expected six hex digits like ff8800, keeping \
+                         white"
+                    ),
+                }
+            }
+            "msaa" => {
+                let value = value();
+                self.msaa = value.parse().unwrap_or_else(|_| {
+                    log::warn!("Invalid MSAA sample count {value:?}");
+                    1
+                });
+            }
+            "ssaa" => {
+                let value = value();
+                self.ssaa = value
+                    .parse()
+                    .ok()
+                    .filter(|factor| (1..=MAX_SSAA).contains(factor))
+                    .unwrap_or_else(|| {
+                        log::warn!(
+                            "Invalid supersampling factor {value:?}, expected 1 to {MAX_SSAA}"
+                        );
+                        1
+                    });
+            }
+            "seed" => {
+                let value = value();
+                self.seed = value.parse().unwrap_or_else(|_| {
+                    log::warn!("Invalid seed {value:?}, expected a whole number");
+                    0
+                });
+            }
+            "shader" => self.shader = Some(value().into()),
+            "include-dir" => self.include_dirs.push(value().into()),
+            "texture" => {
+                let value = value();
+                if self.textures.len() < MAX_TEXTURES {
+                    self.textures.push(value.into());
+                } else {
+                    log::warn!("Ignoring texture {value:?}, at most {MAX_TEXTURES} can be bound");
+                }
+            }
+            "load" => self.load = Some(value().into()),
+            "monitor" => {
+                let value = value();
+                self.monitor = value.parse().ok();
+                if self.monitor.is_none() {
+                    log::warn!("Invalid monitor {value:?}, expected a number from --list-monitors");
+                }
+            }
+            "windows" => {
+                let value = value();
+                self.windows = value
+                    .parse()
+                    .ok()
+                    .filter(|&count| count > 0)
+                    .unwrap_or_else(|| {
+                        log::warn!("Invalid window count {value:?}");
+                        1
+                    });
+            }
+            "bench" => {
+                let value = value();
+                self.bench = value.parse().ok().filter(|&frames| frames > 0);
+                if self.bench.is_none() {
+                    log::warn!("Invalid frame count {value:?}");
+                }
+            }
+            "output" => self.output = Some(value().into()),
+            "record" => self.record = Some(value().into()),
+            "record-input" => self.record_input = Some(value().into()),
+            "replay-input" => self.replay_input = Some(value().into()),
+            "serve" => self.serve = Some(value()),
+            "connect" => self.connect = Some(value()),
+            "frames" => {
+                let value = value();
+                self.frames = value.parse().ok().filter(|&frames| frames > 0);
+                if self.frames.is_none() {
+                    log::warn!("Invalid frame count {value:?}");
+                }
+            }
+            "internal-res" => {
+                let value = value();
+                self.internal_res = parse_size(&value);
+                if self.internal_res.is_none() {
+                    log::warn!("Invalid internal resolution {value:?}, expected WIDTHxHEIGHT");
+                }
+            }
+            "target-ms" => {
+                let value = value();
+                self.target_ms = value.parse().ok().filter(|&ms: &f32| ms > 0.0);
+                if self.target_ms.is_none() {
+                    log::warn!("Invalid frame time target {value:?}, expected milliseconds");
+                }
+            }
+            "follow-damping" => {
+                let value = value();
+                self.follow_damping = value
+                    .parse()
+                    .ok()
+                    .filter(|&rate: &f32| rate > 0.0 && rate.is_finite())
+                    .unwrap_or_else(|| {
+                        log::warn!("Invalid follow damping {value:?}, expected a rate like 8");
+                        FOLLOW_DAMPING
+                    });
+            }
+            "dead-zone" => {
+                let value = value();
+                self.dead_zone = parse_dead_zone(&value).unwrap_or_else(|| {
+                    log::warn!(
+                        "Invalid dead zone {value:?}, expected WIDTHxHEIGHT in fractions of the \
+                         screen like 0.2x0.1"
+                    );
+                    DEAD_ZONE
+                });
+            }
+            "minimap-size" => {
+                let value = value();
+                self.minimap_size =
+                    value
+                        .parse()
+                        .ok()
+                        .filter(|&size| size > 0)
+                        .unwrap_or_else(|| {
+                            log::warn!("Invalid minimap size {value:?}, expected pixels like 160");
+                            MINIMAP_SIZE
+                        });
+            }
+            "minimap-every" => {
+                let value = value();
+                self.minimap_every = value
+                    .parse()
+                    .ok()
+                    .filter(|&frames| frames > 0)
+                    .unwrap_or_else(|| {
+                        log::warn!("Invalid minimap interval {value:?}, expected frames like 4");
+                        MINIMAP_EVERY
+                    });
+            }
+            "resolution" => {
+                let value = value();
+                self.resolution = parse_size(&value);
+                if self.resolution.is_none() {
+                    log::warn!("Invalid resolution {value:?}, expected WIDTHxHEIGHT");
+                }
+            }
+            "refresh-rate" => {
+                let value = value();
+                self.refresh_rate = value.parse().ok().filter(|&hertz| hertz > 0);
+                if self.refresh_rate.is_none() {
+                    log::warn!("Invalid refresh rate {value:?}, expected hertz like 144");
+                }
+            }
+            "size" => {
+                let value = value();
+                self.size = parse_size(&value);
+                if self.size.is_none() {
+                    log::warn!("Invalid size {value:?}, expected WIDTHxHEIGHT");
+                }
+            }
+            _ => return false,
+        }
+        true
+    }
+}
+
+// Parse a resolution like 1920x1080
+fn parse_size(value: &str) -> Option<(u32, u32)> {
+    let (width, height) = value.split_once('x')?;
+    let size = (width.parse().ok()?, height.parse().ok()?);
+    (size.0 > 0 && size.1 > 0).then_some(size)
+}
+
+// Parse a dead zone like 0.2x0.1, the player can't leave the screen inside one so it's at most 1
+fn parse_dead_zone(value: &str) -> Option<[f32; 2]> {
+    let (width, height) = value.split_once('x')?;
+    let size: [f32; 2] = [width.parse().ok()?, height.parse().ok()?];
+    size.iter()
+        .all(|side| (0.0..=1.0).contains(side))
+        .then_some(size)
+}
+
+// Parse a comma separated list of backends like vulkan,dx12
+fn parse_backends(value: &str) -> Option<wgpu::Backends> {
+    let mut backends = wgpu::Backends::empty();
+    for name in value.split(',') {
+        backends |= match name.trim().to_lowercase().as_str() {
+            "vulkan" => wgpu::Backends::VULKAN,
+            "dx12" => wgpu::Backends::DX12,
+            "dx11" => wgpu::Backends::DX11,
+            "metal" => wgpu::Backends::METAL,
+            "gl" => wgpu::Backends::GL,
+            "webgpu" => wgpu::Backends::BROWSER_WEBGPU,
+            _ => return None,
+        };
+    }
+    Some(backends)
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use super::*;
+
+    fn from_config(text: &str) -> Options {
+        let mut options = Options::default();
+        let table = toml::from_str(text).unwrap();
+        options.apply_config(table, std::path::Path::new(CONFIG_PATH));
+        options
+    }
+
+    #[test]
+    fn config_files_take_every_kind_of_value() {
+        let options = from_config(
+            "single-pass = true\nmsaa = 4\nshader = \"scene.wgsl\"\ntarget-ms = 16.6\n",
+        );
+        assert!(options.single_pass);
+        assert_eq!(options.msaa, 4);
+        assert_eq!(
+            options.shader.as_deref(),
+            Some(std::path::Path::new("scene.wgsl"))
+        );
+        assert_eq!(options.target_ms, Some(16.6));
+    }
+
+    // Whole numbers written with a point are floats in TOML, not integers
+    #[test]
+    fn follow_damping_comes_from_the_config_file() {
+        let options = from_config("follow = true\nfollow-damping = 3.0\n");
+        assert!(options.follow);
+        assert_eq!(options.follow_damping, 3.0);
+        assert_ne!(FOLLOW_DAMPING, 3.0);
+    }
+
+    #[test]
+    fn lists_come_from_arrays_and_the_command_line_replaces_them() {
+        let mut options =
+            from_config("texture = [\"a.png\", \"b.png\"]\ninclude-dir = [\"lib\"]\n");
+        assert_eq!(
+            options.textures,
+            ["a.png", "b.png"].map(std::path::PathBuf::from)
+        );
+        assert_eq!(options.include_dirs, [std::path::PathBuf::from("lib")]);
+        let args = ["--texture", "c.png", "--texture", "d.png"];
+        options.apply_args(args.into_iter().map(String::from));
+        assert_eq!(
+            options.textures,
+            ["c.png", "d.png"].map(std::path::PathBuf::from)
+        );
+        assert_eq!(options.include_dirs, [std::path::PathBuf::from("lib")]);
+    }
+}
@@ -0,0 +1,182 @@
+// GPU particles with --particles, moved by a compute pass and drawn as instanced quads over the
+// scene. Dead ones respawn at the cursor, like sparks from a wand.
+use crate::quad;
+use crate::state::{create_validated_render_pipeline, PipelineSettings};
+use anyhow::Context;
+use bytemuck::{Pod, Zeroable};
+use std::borrow::Cow;
+use wgpu::util::DeviceExt;
+
+// Must match particles_step.wgsl, the lifetime is in seconds
+const PARTICLE_COUNT: u32 = 4096;
+const LIFETIME: f32 = 1.5;
+// Has to be a literal in the shader, keep it in step with particles_step.wgsl
+const WORKGROUP_SIZE: u32 = 64;
+
+// Mirrors `Particle` in particles_step.wgsl and the instance inputs of particles.wgsl
+#[repr(C)]
+#[derive(Debug, Copy, Clone, Pod, Zeroable)]
+struct Particle {
+    // World units, and world units per second
+    pos: [f32; 2],
+    vel: [f32; 2],
+    // Seconds left before it respawns, or below zero the time until it first spawns
+    life: f32,
+    padding: f32,
+}
+
+impl Particle {
+    // Location 0 is the quad corner
+    const ATTRIBUTES: [wgpu::VertexAttribute; 3] =
+        wgpu::vertex_attr_array![1 => Float32x2, 2 => Float32x2, 3 => Float32];
+
+    fn layout() -> wgpu::VertexBufferLayout<'static> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<Self>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Instance,
+            attributes: &Self::ATTRIBUTES,
+        }
+    }
+}
+
+pub struct Particles {
+    compute_pipeline: wgpu::ComputePipeline,
+    render_pipeline: wgpu::RenderPipeline,
+    // Written by the compute pass and read as the instance buffer
+    buffer: wgpu::Buffer,
+    step_bind_group: wgpu::BindGroup,
+    quad_buffer: wgpu::Buffer,
+    // Whether the next `step` call moves the particles
+    step_due: bool,
+}
+
+impl Particles {
+    // `bind_group_layout` is the scene's, both passes read the clock, cursor and camera from its
+    // uniforms
+    pub async fn new(
+        device: &wgpu::Device,
+        bind_group_layout: &wgpu::BindGroupLayout,
+        settings: &PipelineSettings,
+    ) -> anyhow::Result<Self> {
+        // The first spawns are spread over a lifetime, after that the stream stays steady
+        let particles: Vec<Particle> = (0..PARTICLE_COUNT)
+            .map(|index| Particle {
+                life: -(index as f32 / PARTICLE_COUNT as f32) * LIFETIME,
+                ..Particle::zeroed()
+            })
+            .collect();
+        let buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("particle_buffer"),
+            contents: bytemuck::cast_slice(&particles),
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::VERTEX,
+        });
+
+        let step_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("particle_step_bind_group_layout"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::COMPUTE,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Storage { read_only: false },
+                    has_dynamic_offset: false,
+                    min_binding_size: wgpu::BufferSize::new(buffer.size()),
+                },
+                count: None,
+            }],
+        });
+        let step_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("particle_step_bind_group"),
+            layout: &step_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: buffer.as_entire_binding(),
+            }],
+        });
+
+        let compute_pipeline = create_compute_pipeline(device, bind_group_layout, &step_layout);
+        // Like the other overlays, the particles always read the uniform buffer
+        let settings = PipelineSettings {
+            push_constants: false,
+            ..*settings
+        };
+        let render_pipeline = create_validated_render_pipeline(
+            device,
+            include_str!("particles.wgsl"),
+            &[bind_group_layout],
+            &[quad::CORNER_LAYOUT, Particle::layout()],
+            &settings,
+        )
+        .await
+        .context("Failed to build the particle pipeline")?;
+
+        Ok(Self {
+            compute_pipeline,
+            render_pipeline,
+            buffer,
+            step_bind_group,
+            quad_buffer: quad::create_quad_buffer(device),
+            step_due: false,
+        })
+    }
+
+    // Let the next `step` move the particles, once per update however many windows draw
+    pub fn advance(&mut self) {
+        self.step_due = true;
+    }
+
+    // Move the particles by the uniforms' dt and respawn the dead ones at their cursor, must be
+    // encoded before the pass that draws them
+    pub fn step(
+        &mut self,
+        encoder: &mut wgpu::CommandEncoder,
+        bind_group: &wgpu::BindGroup,
+        uniform_offset: u32,
+    ) {
+        if !std::mem::take(&mut self.step_due) {
+            return;
+        }
+        let mut cpass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: Some("particle_pass"),
+        });
+        cpass.set_pipeline(&self.compute_pipeline);
+        cpass.set_bind_group(0, bind_group, &[uniform_offset]);
+        cpass.set_bind_group(1, &self.step_bind_group, &[]);
+        cpass.dispatch_workgroups(PARTICLE_COUNT.div_ceil(WORKGROUP_SIZE), 1, 1);
+    }
+
+    // Inside the scene's pass, after the scene so they land on top
+    pub fn draw<'a>(
+        &'a self,
+        rpass: &mut wgpu::RenderPass<'a>,
+        bind_group: &'a wgpu::BindGroup,
+        uniform_offset: u32,
+    ) {
+        rpass.set_pipeline(&self.render_pipeline);
+        rpass.set_bind_group(0, bind_group, &[uniform_offset]);
+        rpass.set_vertex_buffer(0, self.quad_buffer.slice(..));
+        rpass.set_vertex_buffer(1, self.buffer.slice(..));
+        rpass.draw(0..6, 0..PARTICLE_COUNT);
+    }
+}
+
+fn create_compute_pipeline(
+    device: &wgpu::Device,
+    bind_group_layout: &wgpu::BindGroupLayout,
+    step_layout: &wgpu::BindGroupLayout,
+) -> wgpu::ComputePipeline {
+    let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some("particle_step_shader"),
+        source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(include_str!("particles_step.wgsl"))),
+    });
+    let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+        label: Some("particle_step_pipeline_layout"),
+        bind_group_layouts: &[bind_group_layout, step_layout],
+        push_constant_ranges: &[],
+    });
+    device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+        label: Some("particle_step_pipeline"),
+        layout: Some(&pipeline_layout),
+        module: &shader,
+        entry_point: "integrate",
+    })
+}
@@ -0,0 +1,62 @@
+// Draws each particle as a small round dot, fading and cooling from yellow to red as it dies
+
+// Must match LIFETIME in particles_step.wgsl
+const LIFETIME: f32 = 1.5;
+// Side of a particle's quad in world units
+const SIZE: f32 = 0.008;
+
+// Same layout as shader.wgsl, only the camera is used here
+struct Uniforms {
+    resolution: vec2<f32>,
+    mouse: vec2<f32>,
+    time: f32,
+    dt: f32,
+    dpi: f32,
+    zoom: f32,
+    offset: vec2<f32>,
+    frame: u32,
+    aspect: f32,
+    params: vec4<f32>,
+    look: vec2<f32>,
+    click: vec2<f32>,
+    mouse_buttons: u32,
+    seed: u32,
+    padding: vec2<u32>,
+    view_projection: mat4x4<f32>,
+    inverse_view_projection: mat4x4<f32>,
+};
+@group(0) @binding(0) var<uniform> uniforms: Uniforms;
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    // -1 to 1 across the quad
+    @location(0) local: vec2<f32>,
+    // 1 when just spawned down to 0 when it respawns
+    @location(1) age: f32,
+};
+
+@vertex
+fn vs_main(
+    @location(0) corner: vec2<f32>,
+    @location(1) pos: vec2<f32>,
+    @location(2) vel: vec2<f32>,
+    @location(3) life: f32,
+) -> VertexOutput {
+    let world = pos + (corner - 0.5) * SIZE;
+    // The camera's transform, like the sprites
+    var out: VertexOutput;
+    out.position = uniforms.view_projection * vec4<f32>(world, 0.0, 1.0);
+    out.local = corner * 2.0 - 1.0;
+    out.age = clamp(life / LIFETIME, 0.0, 1.0);
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    // Not yet spawned or outside the dot, cut away rather than blended so nothing needs sorting
+    if in.age <= 0.0 || dot(in.local, in.local) > 1.0 {
+        discard;
+    }
+    let colour = mix(vec3<f32>(0.8, 0.1, 0.0), vec3<f32>(1.0, 0.9, 0.3), in.age);
+    return vec4<f32>(colour, 1.0);
+}
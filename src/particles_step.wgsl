@@ -0,0 +1,91 @@
+// Moves every particle by a frame, respawning the dead ones at the cursor
+
+// Must match PARTICLE_COUNT and LIFETIME in particles.rs
+const PARTICLE_COUNT: u32 = 4096u;
+// How long a particle lives, in seconds
+const LIFETIME: f32 = 1.5;
+// Pulls the particles down, in world units per second squared
+const GRAVITY: f32 = -0.6;
+
+// Same layout as shader.wgsl, the clock, cursor and camera are used here
+struct Uniforms {
+    resolution: vec2<f32>,
+    mouse: vec2<f32>,
+    time: f32,
+    dt: f32,
+    dpi: f32,
+    zoom: f32,
+    offset: vec2<f32>,
+    frame: u32,
+    aspect: f32,
+    params: vec4<f32>,
+    look: vec2<f32>,
+    click: vec2<f32>,
+    mouse_buttons: u32,
+    seed: u32,
+    padding: vec2<u32>,
+    view_projection: mat4x4<f32>,
+    inverse_view_projection: mat4x4<f32>,
+};
+@group(0) @binding(0) var<uniform> uniforms: Uniforms;
+
+struct Particle {
+    pos: vec2<f32>,
+    vel: vec2<f32>,
+    life: f32,
+    padding: f32,
+};
+@group(1) @binding(0)
+var<storage, read_write> particles: array<Particle>;
+
+// Same as pcg_hash in shader.wgsl
+fn pcg_hash(value: u32) -> u32 {
+    let state = value * 747796405u + 2891336453u;
+    let word = ((state >> ((state >> 28u) + 4u)) ^ state) * 277803737u;
+    return (word >> 22u) ^ word;
+}
+
+fn random(value: u32) -> f32 {
+    return f32(pcg_hash(value)) / 4294967296.0;
+}
+
+// The cursor in world units, back through the camera's transform like the scene shader
+fn cursor_world() -> vec2<f32> {
+    let clip = vec2<f32>(
+        uniforms.mouse.x / uniforms.resolution.x * 2.0 - 1.0,
+        1.0 - uniforms.mouse.y / uniforms.resolution.y * 2.0,
+    );
+    return (uniforms.inverse_view_projection * vec4<f32>(clip, 0.0, 1.0)).xy;
+}
+
+// Has to be a literal here, keep it in step with WORKGROUP_SIZE in particles.rs
+@compute @workgroup_size(64)
+fn integrate(@builtin(global_invocation_id) id: vec3<u32>) {
+    if id.x >= PARTICLE_COUNT {
+        return;
+    }
+    var particle = particles[id.x];
+    let dt = uniforms.dt;
+    if particle.life < 0.0 {
+        // Counting up to its first spawn, see `Particles::new`
+        particle.life = min(particle.life + dt, 0.0);
+        if particle.life < 0.0 {
+            particles[id.x] = particle;
+            return;
+        }
+    }
+    particle.life -= dt;
+    if particle.life <= 0.0 && dt > 0.0 {
+        // A different direction and speed for every particle and frame
+        let hash = pcg_hash(pcg_hash(uniforms.seed + uniforms.frame) + id.x);
+        let angle = random(hash) * 6.2831853;
+        let speed = 0.05 + 0.25 * random(hash + 1u);
+        particle.pos = cursor_world();
+        particle.vel = vec2<f32>(cos(angle), sin(angle)) * speed;
+        // Carry the overshoot so a steady stream doesn't bunch up into frames
+        particle.life += LIFETIME;
+    }
+    particle.vel.y += GRAVITY * dt;
+    particle.pos += particle.vel * dt;
+    particles[id.x] = particle;
+}
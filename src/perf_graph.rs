@@ -0,0 +1,112 @@
+// The last few hundred frame times as a line in the corner, F2 shows it. A stutter stands out as a
+// spike, and frames over the budget are drawn red.
+use std::collections::VecDeque;
+
+// Frames kept and drawn, one per pixel across
+const HISTORY: usize = 300;
+const HEIGHT: f32 = 80.0;
+// The budget when neither --target-ms nor --max-fps give one, a 60 Hz display's
+const DEFAULT_FPS: u32 = 60;
+
+pub struct PerfGraph {
+    // Seconds each frame took, oldest first
+    times: VecDeque<f32>,
+    // Seconds a frame should take at most
+    budget: f32,
+}
+
+impl PerfGraph {
+    // The budget is --target-ms's if given, otherwise a frame at the --max-fps cap
+    pub fn new(target_ms: Option<f32>, max_fps: Option<u32>) -> Self {
+        let budget = target_ms.map_or_else(
+            || 1.0 / max_fps.unwrap_or(DEFAULT_FPS).max(1) as f32,
+            |ms| ms / 1000.0,
+        );
+        Self {
+            times: VecDeque::with_capacity(HISTORY),
+            budget,
+        }
+    }
+
+    pub fn record(&mut self, frame_time: f32) {
+        if self.times.len() == HISTORY {
+            self.times.pop_front();
+        }
+        self.times.push_back(frame_time);
+    }
+
+    // The time at the top of the graph, room for the slowest frame and never less than twice the
+    // budget, so the budget's line stays in the lower half and a steady graph doesn't jump about
+    fn ceiling(&self) -> f32 {
+        self.times.iter().copied().fold(2.0 * self.budget, f32::max)
+    }
+
+    pub fn show(&self, context: &egui::Context) {
+        egui::Window::new("Frame times")
+            .anchor(egui::Align2::LEFT_BOTTOM, [16.0, -16.0])
+            .collapsible(false)
+            .resizable(false)
+            .show(context, |ui| {
+                let (response, painter) =
+                    ui.allocate_painter(egui::vec2(HISTORY as f32, HEIGHT), egui::Sense::hover());
+                let rect = response.rect;
+                painter.rect_filled(rect, 0.0, egui::Color32::from_black_alpha(160));
+                let ceiling = self.ceiling();
+                let y = |time: f32| rect.bottom() - time / ceiling * rect.height();
+                let budget = y(self.budget);
+                painter.line_segment(
+                    [
+                        egui::pos2(rect.left(), budget),
+                        egui::pos2(rect.right(), budget),
+                    ],
+                    egui::Stroke::new(1.0, egui::Color32::GRAY),
+                );
+                // The newest frame at the right edge, scrolling left as more come in
+                let start = HISTORY - self.times.len();
+                let points: Vec<egui::Pos2> = self
+                    .times
+                    .iter()
+                    .enumerate()
+                    .map(|(index, &time)| egui::pos2(rect.left() + (start + index) as f32, y(time)))
+                    .collect();
+                for (ends, &time) in points.windows(2).zip(self.times.iter().skip(1)) {
+                    let colour = if time > self.budget {
+                        egui::Color32::LIGHT_RED
+                    } else {
+                        egui::Color32::LIGHT_GREEN
+                    };
+                    painter.line_segment([ends[0], ends[1]], egui::Stroke::new(1.5, colour));
+                }
+                let latest = self.times.back().copied().unwrap_or_default();
+                let worst = self.times.iter().copied().fold(0.0, f32::max);
+                ui.label(
+                    egui::RichText::new(format!(
+                        "{:.1} ms, {:.1} ms worst, {:.1} ms budget",
+                        latest * 1000.0,
+                        worst * 1000.0,
+                        self.budget * 1000.0
+                    ))
+                    .monospace(),
+                );
+            });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_the_latest_frames_under_a_ceiling() {
+        let mut graph = PerfGraph::new(None, Some(50));
+        assert_eq!(graph.budget, 0.02);
+        assert_eq!(graph.ceiling(), 0.04);
+        for frame in 0..HISTORY + 10 {
+            graph.record(frame as f32 / 1000.0);
+        }
+        assert_eq!(graph.times.len(), HISTORY);
+        assert_eq!(graph.times.front(), Some(&0.01));
+        assert_eq!(graph.ceiling(), 0.309);
+        assert_eq!(PerfGraph::new(Some(8.0), Some(50)).budget, 0.008);
+    }
+}
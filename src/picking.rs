@@ -0,0 +1,141 @@
+// Finding which entity is under the cursor with --picking. The scene's pass gets a second colour
+// target that the sprites write their entity's id into, and a left click reads back the one texel
+// under the cursor.
+use crate::options::Options;
+
+pub const ID_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::R32Uint;
+// What the id target is cleared to, where only the scene shader or the tile map drew
+pub const NO_ID: u32 = 0;
+// The player isn't an entity, see `ecs::Entity::pick_id` for the rest
+pub const PLAYER_ID: u32 = u32::MAX;
+
+// A pipeline's part in the id target
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PickTarget {
+    // The pass has no id target, without --picking and outside the scene
+    None,
+    // Drawn in a pass with one but leaves it as it is, like the scene shader and the grid
+    Keep,
+    // Writes its id to @location(1), the sprites
+    Write,
+}
+
+impl PickTarget {
+    // The same pass, drawn by a pipeline that writes ids
+    pub fn writing(self) -> Self {
+        match self {
+            Self::None => Self::None,
+            Self::Keep | Self::Write => Self::Write,
+        }
+    }
+
+    // The pipeline's second colour target
+    pub fn target(self) -> Option<wgpu::ColorTargetState> {
+        let write_mask = match self {
+            Self::None => return None,
+            Self::Keep => wgpu::ColorWrites::empty(),
+            Self::Write => wgpu::ColorWrites::ALL,
+        };
+        Some(wgpu::ColorTargetState {
+            format: ID_FORMAT,
+            // Ids can't be blended, which is why they're integers
+            blend: None,
+            write_mask,
+        })
+    }
+}
+
+// Whether the scene pass can have the id target. It can't be multisampled, as integers can't be
+// resolved, and the pipelines that keep it mask it differently from their colour target.
+pub fn supported(adapter: &wgpu::Adapter, options: &Options, sample_count: u32) -> bool {
+    if !options.picking {
+        return false;
+    }
+    if cfg!(target_arch = "wasm32") {
+        log::warn!("--picking waits on the GPU to read the id back, which browsers can't");
+        return false;
+    }
+    if sample_count > 1 {
+        log::warn!("--picking can't be used with MSAA, ignoring it");
+        return false;
+    }
+    let independent_blend = adapter
+        .get_downlevel_capabilities()
+        .flags
+        .contains(wgpu::DownlevelFlags::INDEPENDENT_BLEND);
+    if !independent_blend {
+        log::warn!("The device can't mask render targets separately, ignoring --picking");
+        return false;
+    }
+    true
+}
+
+pub fn create_id_texture(device: &wgpu::Device, (width, height): (u32, u32)) -> wgpu::Texture {
+    device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("id_texture"),
+        size: wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: ID_FORMAT,
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+        view_formats: &[],
+    })
+}
+
+// The id at a texel of the last frame drawn into `texture`, waiting for the GPU. Fine once per
+// click, unlike every frame.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn read_id(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    texture: &wgpu::Texture,
+    [x, y]: [u32; 2],
+) -> anyhow::Result<u32> {
+    use anyhow::Context;
+
+    let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("pick_buffer"),
+        size: std::mem::size_of::<u32>() as wgpu::BufferAddress,
+        usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+        mapped_at_creation: false,
+    });
+    let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+        label: Some("pick_encoder"),
+    });
+    encoder.copy_texture_to_buffer(
+        wgpu::ImageCopyTexture {
+            origin: wgpu::Origin3d { x, y, z: 0 },
+            ..texture.as_image_copy()
+        },
+        wgpu::ImageCopyBuffer {
+            buffer: &buffer,
+            layout: wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT),
+                rows_per_image: None,
+            },
+        },
+        wgpu::Extent3d {
+            width: 1,
+            height: 1,
+            depth_or_array_layers: 1,
+        },
+    );
+    queue.submit(Some(encoder.finish()));
+
+    let slice = buffer.slice(..);
+    let (sender, receiver) = std::sync::mpsc::channel();
+    slice.map_async(wgpu::MapMode::Read, move |result| {
+        sender.send(result).ok();
+    });
+    device.poll(wgpu::Maintain::Wait);
+    receiver.recv()?.context("Failed to map the pick buffer")?;
+    let id = bytemuck::pod_read_unaligned(&slice.get_mapped_range());
+    buffer.unmap();
+    Ok(id)
+}
@@ -0,0 +1,494 @@
+// Post-processing passes run after the scene, each samples the output of the one before it
+use crate::dynamic_res;
+use crate::graph::{self, Node};
+use std::borrow::Cow;
+
+// Declared in post.wgsl, switched on for the last pass when it has to encode its output
+const ENCODE_SRGB_OFF: &str = "const ENCODE_SRGB: bool = false;";
+const ENCODE_SRGB_ON: &str = "const ENCODE_SRGB: bool = true;";
+// Also in post.wgsl, the last pass averages this many samples squared with --ssaa
+const SUPERSAMPLE_OFF: &str = "const SUPERSAMPLE: u32 = 1u;";
+
+// What the passes read and write, for scheduling them and sharing their textures
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Stage {
+    // The scene's target, kept apart so it can become the previous frame
+    Scene,
+    // What a pass but the last writes, the next one reads it
+    Between(usize),
+    Output,
+}
+
+// The texture in `PostChain::between` a pass reads and the one it writes, None for the scene and
+// the output
+type PassSlots = (Option<usize>, Option<usize>);
+
+// How the scene's size follows the output's
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Scaling {
+    // The output's size, or a fraction of it with --target-ms
+    Output,
+    // A fixed size with --internal-res, letterboxed into the output at a whole scale
+    Internal((u32, u32)),
+    // With --ssaa, this many times the output along each axis. The last pass averages each
+    // output pixel's square back down, which smooths the edges inside a procedural shader that
+    // MSAA can't see.
+    Supersample(u32),
+}
+
+pub struct PostChain {
+    format: wgpu::TextureFormat,
+    bind_group_layout: wgpu::BindGroupLayout,
+    sampler: wgpu::Sampler,
+    // Run in order, the last one writes to the final target
+    pipelines: Vec<wgpu::RenderPipeline>,
+    // The scene renders into this, the first pass reads it
+    scene: (wgpu::TextureView, wgpu::BindGroup),
+    // Textures between passes, as many as have to exist at once. With longer chains these are
+    // ping-ponged instead of one per pass.
+    between: Vec<(wgpu::TextureView, wgpu::BindGroup)>,
+    slots: Vec<PassSlots>,
+    slot_count: usize,
+    feedback: bool,
+    // The scene and every pass but the last run at the size this gives, and the last one scales
+    // it to fit the output
+    scaling: Scaling,
+    // Without an internal resolution, the scene is drawn at this fraction of its size for
+    // --target-ms and the last pass stretches it over all of the output
+    render_scale: f32,
+    // Supersampling is cut back to keep the scene's textures within this
+    max_texture_size: u32,
+    output_size: (u32, u32),
+    // The scene target from the frame before, swapped with the first input after each frame so
+    // the scene can sample it. None unless `feedback`.
+    previous: Option<(wgpu::TextureView, wgpu::BindGroup)>,
+}
+
+impl PostChain {
+    // `format` is used for the final target and the intermediate textures alike, `feedback` keeps
+    // a second scene target for the previous frame and `encode_srgb` has the last pass encode
+    // its output, for targets that store it as is but are shown as sRGB
+    pub fn new(
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        width: u32,
+        height: u32,
+        feedback: bool,
+        scaling: Scaling,
+        encode_srgb: bool,
+    ) -> Self {
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("post_bind_group_layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+        });
+        // Scaled up pixels stay sharp instead of blurring together
+        let sampler = match scaling {
+            Scaling::Internal(_) => device.create_sampler(&wgpu::SamplerDescriptor {
+                label: Some("pixel_sampler"),
+                ..Default::default()
+            }),
+            Scaling::Output | Scaling::Supersample(_) => create_frame_sampler(device),
+        };
+        // Add more shaders here to chain further passes
+        let sources = [include_str!("post.wgsl")];
+        let pipelines: Vec<_> = sources
+            .iter()
+            .enumerate()
+            .map(|(index, source)| {
+                let last = index + 1 == sources.len();
+                let mut source = Cow::Borrowed(*source);
+                if encode_srgb && last {
+                    source = Cow::Owned(source.replace(ENCODE_SRGB_OFF, ENCODE_SRGB_ON));
+                }
+                if let (Scaling::Supersample(factor), true) = (scaling, last) {
+                    let line = format!("const SUPERSAMPLE: u32 = {factor}u;");
+                    source = Cow::Owned(source.replace(SUPERSAMPLE_OFF, &line));
+                }
+                create_post_pipeline(device, &bind_group_layout, source, format)
+            })
+            .collect();
+
+        let (slots, slot_count) = schedule_slots(pipelines.len());
+        // Replaced at the scene's size by `create_targets` below
+        let scene = create_input(device, &bind_group_layout, &sampler, format, (1, 1));
+        let mut chain = Self {
+            format,
+            bind_group_layout,
+            sampler,
+            pipelines,
+            scene,
+            between: Vec::new(),
+            slots,
+            slot_count,
+            feedback,
+            scaling,
+            render_scale: 1.0,
+            max_texture_size: device.limits().max_texture_dimension_2d,
+            output_size: (width, height),
+            previous: None,
+        };
+        chain.create_targets(device);
+        chain
+    }
+
+    // Recreate the intermediate textures to match a new output size, unless the scene's size
+    // stays the same like it does with an internal resolution. Returns whether they were. Fresh
+    // textures are zeroed, so the first previous frame after this is black.
+    pub fn resize(&mut self, device: &wgpu::Device, width: u32, height: u32) -> bool {
+        let scene_size = self.scene_size();
+        self.output_size = (width, height);
+        if self.scene_size() == scene_size {
+            return false;
+        }
+        self.create_targets(device);
+        true
+    }
+
+    fn create_targets(&mut self, device: &wgpu::Device) {
+        if let Scaling::Supersample(factor) = self.scaling {
+            let supersample = self.supersample();
+            if supersample < factor {
+                let (width, height) = self.output_size;
+                log::warn!(
+                    "{width}x{height} at {factor}x supersampling is past the device's \
+                     {}x{} texture limit, supersampling {supersample}x",
+                    self.max_texture_size,
+                    self.max_texture_size
+                );
+            }
+        }
+        let size = self.scene_size();
+        let create = || {
+            create_input(
+                device,
+                &self.bind_group_layout,
+                &self.sampler,
+                self.format,
+                size,
+            )
+        };
+        self.between = (0..self.slot_count).map(|_| create()).collect();
+        self.scene = create();
+        self.previous = self.feedback.then(create);
+    }
+
+    // Where the scene should be rendered so the chain can pick it up
+    pub fn input_view(&self) -> &wgpu::TextureView {
+        &self.scene.0
+    }
+
+    // Draw the scene at `scale` of the output from the next `resize`, returns whether it changed
+    pub fn set_render_scale(&mut self, scale: f32) -> bool {
+        let changed = scale != self.render_scale;
+        self.render_scale = scale;
+        changed
+    }
+
+    // The size the scene is drawn at, the output's unless there's an internal resolution,
+    // supersampling or it's scaled down
+    pub fn scene_size(&self) -> (u32, u32) {
+        match self.scaling {
+            Scaling::Internal(size) => size,
+            Scaling::Output => dynamic_res::scaled(self.output_size, self.render_scale),
+            Scaling::Supersample(_) => {
+                let (width, height) = dynamic_res::scaled(self.output_size, self.render_scale);
+                let supersample = self.supersample();
+                (width * supersample, height * supersample)
+            }
+        }
+    }
+
+    // The --ssaa factor the output size allows, 1 without it
+    fn supersample(&self) -> u32 {
+        let Scaling::Supersample(factor) = self.scaling else {
+            return 1;
+        };
+        let (width, height) = dynamic_res::scaled(self.output_size, self.render_scale);
+        factor
+            .min(self.max_texture_size / width.max(height).max(1))
+            .max(1)
+    }
+
+    // Map a point in the output to the scene's pixels
+    pub fn to_scene(&self, position: [f32; 2]) -> [f32; 2] {
+        let viewport = self.viewport().unwrap_or(Viewport {
+            x: 0.0,
+            y: 0.0,
+            width: self.output_size.0 as f32,
+            height: self.output_size.1 as f32,
+        });
+        viewport.to_scene(position, self.scene_size())
+    }
+
+    // Where the last pass draws the scene in the output, None when it fills all of it
+    pub fn viewport(&self) -> Option<Viewport> {
+        match self.scaling {
+            Scaling::Internal(internal_size) => Some(letterbox(internal_size, self.output_size)),
+            Scaling::Output | Scaling::Supersample(_) => None,
+        }
+    }
+
+    // What the scene rendered the frame before, None without feedback
+    pub fn previous_view(&self) -> Option<&wgpu::TextureView> {
+        self.previous.as_ref().map(|(view, _)| view)
+    }
+
+    // Make this frame's scene the previous one, the next frame renders over the older target
+    pub fn swap_feedback(&mut self) {
+        if let Some(previous) = &mut self.previous {
+            std::mem::swap(&mut self.scene, previous);
+        }
+    }
+
+    // Run every pass, writing the result of the last one to `output`
+    pub fn encode(&self, encoder: &mut wgpu::CommandEncoder, output: &wgpu::TextureView) {
+        for (index, (pipeline, &(read, write))) in
+            self.pipelines.iter().zip(&self.slots).enumerate()
+        {
+            let last = index + 1 == self.pipelines.len();
+            let (_, bind_group) = read.map_or(&self.scene, |slot| &self.between[slot]);
+            let target = write.map_or(output, |slot| &self.between[slot].0);
+            let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("post_pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: target,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                        store: true,
+                    },
+                })],
+                depth_stencil_attachment: None,
+            });
+            // The clear above leaves the borders around it black
+            if let Some(viewport) = self.viewport().filter(|_| last) {
+                viewport.apply(&mut rpass);
+            }
+            rpass.set_pipeline(pipeline);
+            rpass.set_bind_group(0, bind_group, &[]);
+            rpass.draw(0..3, 0..1);
+        }
+    }
+}
+
+// A rectangle of the output in pixels from the top left
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Viewport {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+impl Viewport {
+    pub fn apply(self, rpass: &mut wgpu::RenderPass) {
+        rpass.set_viewport(self.x, self.y, self.width, self.height, 0.0, 1.0);
+    }
+
+    // Map a point in the output to the scene of `scene_size` drawn inside the viewport
+    pub fn to_scene(self, position: [f32; 2], scene_size: (u32, u32)) -> [f32; 2] {
+        [
+            (position[0] - self.x) * scene_size.0 as f32 / self.width,
+            (position[1] - self.y) * scene_size.1 as f32 / self.height,
+        ]
+    }
+}
+
+// Centre `scene` in `output` at the largest whole number scale that fits, so every scene pixel
+// covers the same square of output pixels. An output smaller than the scene scales it down
+// instead, still keeping the aspect ratio.
+pub fn letterbox(scene: (u32, u32), output: (u32, u32)) -> Viewport {
+    let fit = (output.0 as f32 / scene.0 as f32).min(output.1 as f32 / scene.1 as f32);
+    let scale = if fit >= 1.0 { fit.floor() } else { fit };
+    let width = scene.0 as f32 * scale;
+    let height = scene.1 as f32 * scale;
+    Viewport {
+        x: ((output.0 as f32 - width) / 2.0).floor(),
+        y: ((output.1 as f32 - height) / 2.0).floor(),
+        width,
+        height,
+    }
+}
+
+// Which texture between passes each of `pass_count` passes reads and writes, from a chain of
+// them in the render graph, and how many textures that takes
+fn schedule_slots(pass_count: usize) -> (Vec<PassSlots>, usize) {
+    let stage = |index: usize| match index {
+        0 => Stage::Scene,
+        index if index == pass_count => Stage::Output,
+        index => Stage::Between(index),
+    };
+    let nodes: Vec<Node<usize, Stage>> = (0..pass_count)
+        .map(|index| Node::new(index, [stage(index)], [stage(index + 1)]))
+        .collect();
+    // A straight chain can't have a cycle or two passes writing one texture
+    let schedule = graph::schedule(&nodes, &[Stage::Output]).expect("The post chain is a chain");
+    let slots = (0..pass_count)
+        .map(|index| {
+            let slot = |stage| schedule.slots.get(&stage).copied();
+            (slot(stage(index)), slot(stage(index + 1)))
+        })
+        .collect();
+    (slots, schedule.slot_count)
+}
+
+// A texture a pass draws into and the bind group the next one reads it with
+fn create_input(
+    device: &wgpu::Device,
+    bind_group_layout: &wgpu::BindGroupLayout,
+    sampler: &wgpu::Sampler,
+    format: wgpu::TextureFormat,
+    (width, height): (u32, u32),
+) -> (wgpu::TextureView, wgpu::BindGroup) {
+    let view = create_post_target(device, format, width, height);
+    let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("post_bind_group"),
+        layout: bind_group_layout,
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::TextureView(&view),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: wgpu::BindingResource::Sampler(sampler),
+            },
+        ],
+    });
+    (view, bind_group)
+}
+
+// Clamped so effects sampling past the edge of a frame don't pick up the opposite side
+pub fn create_frame_sampler(device: &wgpu::Device) -> wgpu::Sampler {
+    device.create_sampler(&wgpu::SamplerDescriptor {
+        label: Some("frame_sampler"),
+        mag_filter: wgpu::FilterMode::Linear,
+        min_filter: wgpu::FilterMode::Linear,
+        ..Default::default()
+    })
+}
+
+// Bound as the previous frame when there's no feedback, a single black texel
+pub fn create_empty_frame(device: &wgpu::Device) -> wgpu::TextureView {
+    create_post_target(device, wgpu::TextureFormat::Rgba8Unorm, 1, 1)
+}
+
+fn create_post_target(
+    device: &wgpu::Device,
+    format: wgpu::TextureFormat,
+    width: u32,
+    height: u32,
+) -> wgpu::TextureView {
+    device
+        .create_texture(&wgpu::TextureDescriptor {
+            label: Some("post_target"),
+            size: wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+            view_formats: &[],
+        })
+        .create_view(&wgpu::TextureViewDescriptor::default())
+}
+
+fn create_post_pipeline(
+    device: &wgpu::Device,
+    bind_group_layout: &wgpu::BindGroupLayout,
+    source: Cow<str>,
+    format: wgpu::TextureFormat,
+) -> wgpu::RenderPipeline {
+    let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some("post_shader"),
+        source: wgpu::ShaderSource::Wgsl(source),
+    });
+    let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+        label: Some("post_pipeline_layout"),
+        bind_group_layouts: &[bind_group_layout],
+        push_constant_ranges: &[],
+    });
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("post_pipeline"),
+        layout: Some(&pipeline_layout),
+        vertex: wgpu::VertexState {
+            module: &shader,
+            entry_point: "vs_main",
+            buffers: &[],
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: &shader,
+            entry_point: "fs_main",
+            targets: &[Some(format.into())],
+        }),
+        primitive: wgpu::PrimitiveState::default(),
+        depth_stencil: None,
+        multisample: wgpu::MultisampleState::default(),
+        multiview: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn letterbox_uses_the_largest_whole_scale_and_centres() {
+        let viewport = letterbox((320, 180), (1920, 1200));
+        assert_eq!(
+            viewport,
+            Viewport {
+                x: 0.0,
+                y: 60.0,
+                width: 1920.0,
+                height: 1080.0,
+            }
+        );
+        let viewport = letterbox((320, 180), (1000, 600));
+        assert_eq!((viewport.width, viewport.height), (960.0, 540.0));
+        assert_eq!((viewport.x, viewport.y), (20.0, 30.0));
+    }
+
+    #[test]
+    fn passes_share_the_textures_between_them() {
+        // One pass reads the scene and writes the output
+        assert_eq!(schedule_slots(1), (vec![(None, None)], 0));
+        let (slots, slot_count) = schedule_slots(4);
+        assert_eq!(slot_count, 2);
+        assert_eq!(slots[0], (None, Some(0)));
+        assert_eq!(slots[3], (Some(0), None));
+        // Each reads what the one before wrote, ping-ponging between two
+        for pair in slots.windows(2) {
+            assert_eq!(pair[0].1, pair[1].0);
+        }
+        assert!(slots.iter().all(|&(read, write)| read != write));
+    }
+
+    #[test]
+    fn letterbox_shrinks_into_a_small_output() {
+        let viewport = letterbox((320, 180), (160, 180));
+        assert_eq!((viewport.width, viewport.height), (160.0, 90.0));
+        assert_eq!(viewport.to_scene([80.0, 90.0], (320, 180)), [160.0, 90.0]);
+    }
+}
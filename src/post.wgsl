@@ -0,0 +1,69 @@
+// Fullscreen pass run on the scene after it's rendered, samples the previous pass's output
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) tex_coords: vec2<f32>,
+};
+
+// One triangle covering the whole screen, the parts outside get clipped
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> VertexOutput {
+    let position = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+    var out: VertexOutput;
+    out.position = vec4<f32>(position * 2.0 - 1.0, 0.0, 1.0);
+    // Texture rows go down while clip space goes up
+    out.tex_coords = vec2<f32>(position.x, 1.0 - position.y);
+    return out;
+}
+
+@group(0) @binding(0)
+var input_texture: texture_2d<f32>;
+@group(0) @binding(1)
+var input_sampler: sampler;
+
+// Set for the last pass when the target stores what's written as is but is shown as sRGB, like
+// the 10-bit surface --hdr can pick. The line is replaced when the pipeline is built.
+const ENCODE_SRGB: bool = false;
+
+// With --ssaa the scene is this many times the output's size along each axis. The line is
+// replaced when the pipeline is built, like ENCODE_SRGB.
+const SUPERSAMPLE: u32 = 1u;
+
+// The sRGB transfer function, what sRGB targets do in hardware
+fn encode_srgb(linear: vec3<f32>) -> vec3<f32> {
+    let clamped = clamp(linear, vec3<f32>(0.0), vec3<f32>(1.0));
+    let low = clamped * 12.92;
+    let high = 1.055 * pow(clamped, vec3<f32>(1.0 / 2.4)) - 0.055;
+    return select(high, low, clamped <= vec3<f32>(0.0031308));
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    var colour = textureSample(input_texture, input_sampler, in.tex_coords);
+    if SUPERSAMPLE > 1u {
+        // Average an evenly spaced square of samples over the output pixel. At the full factor
+        // each lands on the middle of a scene pixel, a box filter.
+        let pixel = vec2<f32>(dpdx(in.tex_coords.x), dpdy(in.tex_coords.y));
+        colour = vec4<f32>(0.0);
+        for (var y = 0u; y < SUPERSAMPLE; y++) {
+            for (var x = 0u; x < SUPERSAMPLE; x++) {
+                let offset = (vec2<f32>(f32(x), f32(y)) + 0.5) / f32(SUPERSAMPLE) - 0.5;
+                colour += textureSampleLevel(
+                    input_texture,
+                    input_sampler,
+                    in.tex_coords + offset * pixel,
+                    0.0
+                );
+            }
+        }
+        colour /= f32(SUPERSAMPLE * SUPERSAMPLE);
+    }
+    // Darken towards the corners
+    let from_centre = in.tex_coords - 0.5;
+    let vignette = 1.0 - 0.5 * dot(from_centre, from_centre);
+    var rgb = colour.rgb * vignette;
+    if ENCODE_SRGB {
+        rgb = encode_srgb(rgb);
+    }
+    return vec4<f32>(rgb, colour.a);
+}
@@ -0,0 +1,148 @@
+// Picks the frame rate cap from the power source. Rendering continuously as fast as the GPU goes
+// drains a laptop's battery and spins its fans, so on battery it's capped unless asked otherwise.
+use crate::options::Options;
+
+// The cap on battery, smooth enough while doing a fraction of the work of an uncapped loop
+const BATTERY_FPS: u32 = 60;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PowerSource {
+    Ac,
+    Battery,
+    // No way to tell on this platform, or nothing reported either
+    Unknown,
+}
+
+// A power supply as Linux lists them in /sys/class/power_supply
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+struct Supply {
+    // "Mains", "Battery", "USB" and so on
+    kind: String,
+    // Whether a mains or USB supply is plugged in, these don't say for batteries
+    online: bool,
+    // The battery of a wireless mouse or gamepad, which says nothing about this machine's power
+    peripheral: bool,
+}
+
+// Any charger plugged in means AC, otherwise a battery means running on it. Desktops often list
+// nothing at all.
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn classify(supplies: &[Supply]) -> PowerSource {
+    let supplies: Vec<&Supply> = supplies
+        .iter()
+        .filter(|supply| !supply.peripheral)
+        .collect();
+    if supplies
+        .iter()
+        .any(|supply| supply.kind != "Battery" && supply.online)
+    {
+        PowerSource::Ac
+    } else if supplies.iter().any(|supply| supply.kind == "Battery") {
+        PowerSource::Battery
+    } else {
+        PowerSource::Unknown
+    }
+}
+
+#[cfg(target_os = "linux")]
+pub fn power_source() -> PowerSource {
+    let Ok(entries) = std::fs::read_dir("/sys/class/power_supply") else {
+        return PowerSource::Unknown;
+    };
+    let read = |path: std::path::PathBuf| {
+        std::fs::read_to_string(path)
+            .map(|text| text.trim().to_string())
+            .unwrap_or_default()
+    };
+    let supplies: Vec<Supply> = entries
+        .flatten()
+        .map(|entry| Supply {
+            kind: read(entry.path().join("type")),
+            online: read(entry.path().join("online")) == "1",
+            peripheral: read(entry.path().join("scope")) == "Device",
+        })
+        .collect();
+    classify(&supplies)
+}
+
+// Other platforms need their own APIs for this, they run uncapped as before
+#[cfg(not(target_os = "linux"))]
+pub fn power_source() -> PowerSource {
+    PowerSource::Unknown
+}
+
+// The cap to render continuously at, None for as fast as the present mode allows. Logs which it
+// went with and why.
+pub fn max_fps(options: &Options) -> Option<u32> {
+    if let Some(max_fps) = options.max_fps {
+        log::info!("Capped at {max_fps} fps by --max-fps");
+        return Some(max_fps);
+    }
+    let vsync_off = matches!(
+        options.present_mode,
+        Some(wgpu::PresentMode::Immediate | wgpu::PresentMode::Mailbox)
+    );
+    match power_source() {
+        PowerSource::Battery if !options.uncapped => {
+            log::info!(
+                "On battery, capped at {BATTERY_FPS} fps to save power, --uncapped or --max-fps \
+                 overrides it"
+            );
+            Some(BATTERY_FPS)
+        }
+        PowerSource::Battery if vsync_off => {
+            log::warn!(
+                "Uncapped with vsync off on battery, every frame the GPU can draw is drawn and \
+                 the battery will drain fast"
+            );
+            None
+        }
+        PowerSource::Battery => {
+            log::info!("On battery but uncapped by --uncapped");
+            None
+        }
+        PowerSource::Ac => {
+            log::info!("On AC power, uncapped");
+            None
+        }
+        PowerSource::Unknown => {
+            log::info!("Couldn't tell whether this is running on battery, uncapped");
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn supply(kind: &str, online: bool) -> Supply {
+        Supply {
+            kind: kind.to_string(),
+            online,
+            peripheral: false,
+        }
+    }
+
+    #[test]
+    fn a_plugged_in_charger_means_ac() {
+        assert_eq!(
+            classify(&[supply("Battery", false), supply("Mains", true)]),
+            PowerSource::Ac
+        );
+        assert_eq!(
+            classify(&[supply("Battery", false), supply("Mains", false)]),
+            PowerSource::Battery
+        );
+        assert_eq!(
+            classify(&[supply("USB", true), supply("Battery", false)]),
+            PowerSource::Ac
+        );
+        assert_eq!(classify(&[]), PowerSource::Unknown);
+        let mouse = Supply {
+            peripheral: true,
+            ..supply("Battery", false)
+        };
+        assert_eq!(classify(&[mouse]), PowerSource::Unknown);
+    }
+}
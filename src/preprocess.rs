@@ -0,0 +1,273 @@
+// Combining a shader split across files, `#include "common.wgsl"` on a line of its own is
+// replaced with that file. Names are looked up next to the including file, then in each include
+// directory. A file that's already been included is skipped, so every file can include what it
+// needs without its definitions being repeated.
+use crate::shader_check::ShaderError;
+use anyhow::Context;
+use std::borrow::Cow;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+const DIRECTIVE: &str = "#include";
+
+// The combined source with where each of its lines came from
+#[derive(Clone)]
+pub struct ShaderSource {
+    pub text: Cow<'static, str>,
+    pub map: SourceMap,
+}
+
+impl ShaderSource {
+    // Source that wasn't read from files, like the embedded shader, its lines map nowhere
+    pub fn embedded(text: &'static str) -> Self {
+        Self {
+            text: Cow::Borrowed(text),
+            map: SourceMap::default(),
+        }
+    }
+}
+
+// The file and line each line of a combined source came from
+#[derive(Debug, Default, Clone)]
+pub struct SourceMap {
+    files: Vec<PathBuf>,
+    // File index and line number, both of the line in the combined source at that index
+    lines: Vec<(usize, u32)>,
+}
+
+impl SourceMap {
+    // Every file that went into the source, the one it started from first
+    #[cfg_attr(target_arch = "wasm32", allow(dead_code))]
+    pub fn files(&self) -> &[PathBuf] {
+        &self.files
+    }
+
+    // Which file and line a line of the combined source is, both lines counted from 1
+    pub fn locate(&self, line_number: u32) -> Option<(&Path, u32)> {
+        let &(file, line) = self.lines.get(line_number.checked_sub(1)? as usize)?;
+        Some((&self.files[file], line))
+    }
+
+    // Add where a shader error really is as context, naga only knows its place in the combined
+    // source. Other errors pass through.
+    pub fn annotate(&self, error: anyhow::Error) -> anyhow::Error {
+        let location = error
+            .downcast_ref::<ShaderError>()
+            .and_then(|shader_error| shader_error.location)
+            .and_then(|location| {
+                let (file, line) = self.locate(location.line_number)?;
+                Some(SourceLocation {
+                    file: file.to_path_buf(),
+                    line,
+                    column: location.line_position,
+                })
+            });
+        match location {
+            Some(location) => error.context(location),
+            None => error,
+        }
+    }
+}
+
+// A place in one of the files a shader was combined from, shown as path:line:column
+#[derive(Debug, Clone)]
+pub struct SourceLocation {
+    pub file: PathBuf,
+    pub line: u32,
+    pub column: u32,
+}
+
+impl fmt::Display for SourceLocation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}:{}", self.file.display(), self.line, self.column)
+    }
+}
+
+// Read the shader at `path` and everything it includes
+pub fn preprocess(path: &Path, include_dirs: &[PathBuf]) -> anyhow::Result<ShaderSource> {
+    combine(path, include_dirs, |path: &Path| {
+        std::fs::read_to_string(path)
+    })
+}
+
+// The same with `text` in place of the file at `path`, like an edit that hasn't been saved. What
+// it includes is still read from disk.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn preprocess_text(
+    path: &Path,
+    text: String,
+    include_dirs: &[PathBuf],
+) -> anyhow::Result<ShaderSource> {
+    let mut text = Some(text);
+    combine(path, include_dirs, |path: &Path| match text.take() {
+        Some(text) => Ok(text),
+        None => std::fs::read_to_string(path),
+    })
+}
+
+// Read files with `read` instead of from disk
+fn combine(
+    path: &Path,
+    include_dirs: &[PathBuf],
+    read: impl FnMut(&Path) -> std::io::Result<String>,
+) -> anyhow::Result<ShaderSource> {
+    let mut preprocessor = Preprocessor {
+        include_dirs,
+        read,
+        text: String::new(),
+        map: SourceMap::default(),
+        stack: Vec::new(),
+    };
+    preprocessor.include(path.to_path_buf())?;
+    Ok(ShaderSource {
+        text: Cow::Owned(preprocessor.text),
+        map: preprocessor.map,
+    })
+}
+
+struct Preprocessor<'a, R> {
+    include_dirs: &'a [PathBuf],
+    read: R,
+    text: String,
+    map: SourceMap,
+    // Files being included right now, each included by the one before, to catch cycles
+    stack: Vec<PathBuf>,
+}
+
+impl<R: FnMut(&Path) -> std::io::Result<String>> Preprocessor<'_, R> {
+    fn include(&mut self, path: PathBuf) -> anyhow::Result<()> {
+        let source = (self.read)(&path)
+            .with_context(|| format!("Failed to read shader {}", path.display()))?;
+        let file = self.map.files.len();
+        self.map.files.push(path.clone());
+        self.stack.push(path.clone());
+        for (index, line) in source.lines().enumerate() {
+            let line_number = index as u32 + 1;
+            let Some(rest) = line.trim_start().strip_prefix(DIRECTIVE) else {
+                self.text.push_str(line);
+                self.text.push('\n');
+                self.map.lines.push((file, line_number));
+                continue;
+            };
+            let at = || format!("{}:{line_number}", path.display());
+            let name = rest
+                .trim()
+                .strip_prefix('"')
+                .and_then(|rest| rest.strip_suffix('"'))
+                .with_context(|| format!("{}: expected {DIRECTIVE} \"file.wgsl\"", at()))?;
+            let included = self
+                .find(&path, name)
+                .with_context(|| format!("{}: can't find {name:?} to include", at()))?;
+            let identity = canonical(&included);
+            if let Some(start) = self
+                .stack
+                .iter()
+                .position(|path| canonical(path) == identity)
+            {
+                let cycle: Vec<String> = self.stack[start..]
+                    .iter()
+                    .chain([&included])
+                    .map(|path| path.display().to_string())
+                    .collect();
+                anyhow::bail!("{}: include cycle {}", at(), cycle.join(" -> "));
+            }
+            if !self
+                .map
+                .files
+                .iter()
+                .any(|path| canonical(path) == identity)
+            {
+                self.include(included)?;
+            }
+        }
+        self.stack.pop();
+        Ok(())
+    }
+
+    // The first of the including file's directory and the include directories that has `name`
+    fn find(&mut self, including: &Path, name: &str) -> Option<PathBuf> {
+        let beside = including
+            .parent()
+            .map_or_else(PathBuf::new, Path::to_path_buf);
+        std::iter::once(beside)
+            .chain(self.include_dirs.iter().cloned())
+            .map(|dir| dir.join(name))
+            .find(|candidate| (self.read)(candidate).is_ok())
+    }
+}
+
+// The same file reached through different relative paths is still the same file
+fn canonical(path: &Path) -> PathBuf {
+    std::fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn run(files: &[(&str, &str)], include_dirs: &[&str]) -> anyhow::Result<ShaderSource> {
+        let files: HashMap<PathBuf, String> = files
+            .iter()
+            .map(|(path, text)| (PathBuf::from(path), text.to_string()))
+            .collect();
+        let include_dirs: Vec<PathBuf> = include_dirs.iter().map(PathBuf::from).collect();
+        combine(
+            Path::new("shaders/main.wgsl"),
+            &include_dirs,
+            |path: &Path| {
+                files
+                    .get(path)
+                    .cloned()
+                    .ok_or_else(|| std::io::ErrorKind::NotFound.into())
+            },
+        )
+    }
+
+    #[test]
+    fn includes_map_back_to_their_files() {
+        let source = run(
+            &[
+                (
+                    "shaders/main.wgsl",
+                    "a\n#include \"common.wgsl\"\nb\n#include \"common.wgsl\"\n",
+                ),
+                ("shaders/common.wgsl", "c\n  #include \"lib/noise.wgsl\"\n"),
+                ("shared/lib/noise.wgsl", "d\n"),
+            ],
+            &["shared"],
+        )
+        .unwrap();
+        assert_eq!(source.text, "a\nc\nd\nb\n");
+        let noise = source.map.locate(3).unwrap();
+        assert_eq!(noise, (Path::new("shared/lib/noise.wgsl"), 1));
+        assert_eq!(source.map.locate(4).unwrap().1, 3);
+        assert!(source.map.locate(5).is_none());
+    }
+
+    #[test]
+    fn cycles_are_errors() {
+        let error = run(
+            &[
+                ("shaders/main.wgsl", "#include \"a.wgsl\"\n"),
+                ("shaders/a.wgsl", "#include \"b.wgsl\"\n"),
+                ("shaders/b.wgsl", "\n#include \"a.wgsl\"\n"),
+            ],
+            &[],
+        )
+        .err()
+        .unwrap();
+        assert_eq!(
+            error.to_string(),
+            "shaders/b.wgsl:2: include cycle shaders/a.wgsl -> shaders/b.wgsl -> shaders/a.wgsl"
+        );
+    }
+
+    #[test]
+    fn missing_and_malformed_includes_are_errors() {
+        let missing = run(&[("shaders/main.wgsl", "#include \"gone.wgsl\"\n")], &[]);
+        assert!(missing.err().unwrap().to_string().contains("gone.wgsl"));
+        let malformed = run(&[("shaders/main.wgsl", "#include common.wgsl\n")], &[]);
+        assert!(malformed.is_err());
+    }
+}
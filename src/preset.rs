@@ -0,0 +1,44 @@
+// Numbered slots keeping how the scene was set up, so a look found by exploring the parameters
+// isn't lost. Ctrl+Shift+1 to 9 save a slot and Ctrl+1 to 9 load it, from RON files in the config
+// directory.
+use crate::camera::Camera;
+use crate::ui::PARAM_COUNT;
+use crate::window_config;
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Preset {
+    pub camera: Camera,
+    pub params: [f32; PARAM_COUNT],
+    pub seed: u32,
+    // As given to --clear-color, before any conversion for the surface
+    pub clear_color: [f64; 4],
+}
+
+// ~/.config/danrpg/presets/1.ron and so on
+fn path(slot: u8) -> anyhow::Result<PathBuf> {
+    let dir = window_config::config_dir().context("No config directory to keep presets in")?;
+    Ok(dir.join("presets").join(format!("{slot}.ron")))
+}
+
+// Returns where it was saved
+pub fn save(slot: u8, preset: &Preset) -> anyhow::Result<PathBuf> {
+    let path = path(slot)?;
+    let text = ron::ser::to_string_pretty(preset, ron::ser::PrettyConfig::default())?;
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create {}", parent.display()))?;
+    }
+    std::fs::write(&path, text)
+        .with_context(|| format!("Failed to save preset {}", path.display()))?;
+    Ok(path)
+}
+
+pub fn load(slot: u8) -> anyhow::Result<Preset> {
+    let path = path(slot)?;
+    let text = std::fs::read_to_string(&path)
+        .with_context(|| format!("Failed to read preset {}", path.display()))?;
+    ron::from_str(&text).with_context(|| format!("Failed to parse preset {}", path.display()))
+}
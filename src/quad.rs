@@ -0,0 +1,32 @@
+// Unit square shared by the instanced pipelines, each instance scales and moves it into place
+
+use wgpu::util::DeviceExt;
+
+// Two triangles covering 0..1 on both axes, drawn with `draw(0..6, instances)`
+const QUAD_CORNERS: [[f32; 2]; 6] = [
+    [0.0, 0.0],
+    [1.0, 0.0],
+    [0.0, 1.0],
+    [0.0, 1.0],
+    [1.0, 0.0],
+    [1.0, 1.0],
+];
+
+// The first vertex buffer of an instanced pipeline, the corner arrives at location 0
+pub const CORNER_LAYOUT: wgpu::VertexBufferLayout<'static> = wgpu::VertexBufferLayout {
+    array_stride: std::mem::size_of::<[f32; 2]>() as wgpu::BufferAddress,
+    step_mode: wgpu::VertexStepMode::Vertex,
+    attributes: &[wgpu::VertexAttribute {
+        format: wgpu::VertexFormat::Float32x2,
+        offset: 0,
+        shader_location: 0,
+    }],
+};
+
+pub fn create_quad_buffer(device: &wgpu::Device) -> wgpu::Buffer {
+    device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some("quad_buffer"),
+        contents: bytemuck::cast_slice(&QUAD_CORNERS),
+        usage: wgpu::BufferUsages::VERTEX,
+    })
+}
@@ -0,0 +1,90 @@
+// Rendering the animation offscreen at a fixed frame rate and saving every frame as a numbered
+// image, to put together into a video afterwards
+use crate::headless::Offscreen;
+use crate::options::Options;
+use crate::state::FrameTime;
+use anyhow::Context;
+use std::path::{Path, PathBuf};
+
+// Frames saved when --frames isn't given, five seconds
+const DEFAULT_FRAMES: u32 = 300;
+// The clock moves on by this much every frame however long it took to render
+const FPS: u32 = 60;
+
+pub async fn run(options: &Options, pattern: &Path) -> anyhow::Result<()> {
+    let pattern = pattern.to_string_lossy();
+    let frames = options.frames.unwrap_or(DEFAULT_FRAMES);
+    let dt = 1.0 / FPS as f32;
+    let mut offscreen = Offscreen::new(options).await?;
+    for frame in 0..frames {
+        offscreen.advance_simulation(dt);
+        offscreen.render(FrameTime {
+            time: frame as f32 * dt,
+            dt,
+            frame,
+        });
+        let path = frame_path(&pattern, frame);
+        if let Some(parent) = path
+            .parent()
+            .filter(|parent| !parent.as_os_str().is_empty())
+        {
+            std::fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create {}", parent.display()))?;
+        }
+        offscreen
+            .read_frame()?
+            .save(&path)
+            .with_context(|| format!("Failed to save {}", path.display()))?;
+    }
+    log::info!(
+        "Recorded {frames} frames to {pattern}, make a video with \
+         ffmpeg -framerate {FPS} -i {pattern} -pix_fmt yuv420p out.mp4"
+    );
+    Ok(())
+}
+
+// Replace the first %d, or %0Nd padded to N digits, with the frame number. A pattern without one
+// gets _%05d before its extension.
+fn frame_path(pattern: &str, frame: u32) -> PathBuf {
+    if let Some(start) = pattern.find('%') {
+        let rest = &pattern[start + 1..];
+        if let Some(end) = rest.find('d') {
+            let spec = &rest[..end];
+            if spec.is_empty() || (spec.starts_with('0') && spec.parse::<usize>().is_ok()) {
+                let width = spec.parse().unwrap_or(0);
+                let number = format!("{frame:0width$}");
+                return format!("{}{number}{}", &pattern[..start], &rest[end + 1..]).into();
+            }
+        }
+    }
+    let path = Path::new(pattern);
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let name = match path.extension() {
+        Some(extension) => format!("{stem}_{frame:05}.{}", extension.to_string_lossy()),
+        None => format!("{stem}_{frame:05}.png"),
+    };
+    path.with_file_name(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frame_numbers_are_padded_like_printf() {
+        assert_eq!(
+            frame_path("out/frame_%05d.png", 42),
+            PathBuf::from("out/frame_00042.png")
+        );
+        assert_eq!(frame_path("%d.png", 7), PathBuf::from("7.png"));
+    }
+
+    #[test]
+    fn patterns_without_a_number_get_one() {
+        assert_eq!(
+            frame_path("out/frame.png", 3),
+            PathBuf::from("out/frame_00003.png")
+        );
+        assert_eq!(frame_path("frames", 3), PathBuf::from("frames_00003.png"));
+    }
+}
@@ -0,0 +1,128 @@
+// The game world's state, independent of how it's drawn, saved to and loaded from RON files
+use serde::{Deserialize, Serialize};
+#[cfg(not(target_arch = "wasm32"))]
+use std::path::Path;
+
+#[cfg(not(target_arch = "wasm32"))]
+use anyhow::Context;
+
+// Where F5 saves to when the world wasn't loaded from a file
+#[cfg(not(target_arch = "wasm32"))]
+pub const DEFAULT_SAVE_PATH: &str = "save.ron";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct World {
+    // World position the camera is centred on
+    pub player: [f32; 2],
+    // One string per row, top row first. `.` is empty and `,` `~` `#` `:` are grass, water,
+    // stone and sand, see `tilemap::tile_index`.
+    pub tiles: Vec<String>,
+    pub entities: Vec<Entity>,
+}
+
+// Anything placed in the world that isn't a tile, like items and characters
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Entity {
+    pub name: String,
+    pub position: [f32; 2],
+    // Atlas tile it's drawn with, see atlas.rs
+    pub sprite: u32,
+}
+
+// The world used when nothing is loaded, a walled field with two ponds
+impl Default for World {
+    fn default() -> Self {
+        let tiles = [
+            "################",
+            "#,,,,,,,,,,,,~~#",
+            "#,::::::::::,~~#",
+            "#,:........:,,,#",
+            "#,:........:,,,#",
+            "#,:..........,,#",
+            "#,:..........,,#",
+            "#,:..........,,#",
+            "#,:..........,,#",
+            "#,:..........,,#",
+            "#,:..........,,#",
+            "#,:........:,,,#",
+            "#,:........:,~~#",
+            "#,::::::::::,~~#",
+            "#,,,,,,,,,,,,,,#",
+            "################",
+        ];
+        Self {
+            player: [0.5, 0.5],
+            tiles: tiles.iter().map(|row| row.to_string()).collect(),
+            entities: vec![
+                Entity {
+                    name: "stone".to_string(),
+                    position: [0.875, 0.875],
+                    sprite: crate::atlas::STONE,
+                },
+                Entity {
+                    name: "stone".to_string(),
+                    position: [0.875, 0.125],
+                    sprite: crate::atlas::STONE,
+                },
+            ],
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn load(path: &Path) -> anyhow::Result<World> {
+    let text = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read save {}", path.display()))?;
+    ron::from_str(&text).with_context(|| format!("Failed to parse save {}", path.display()))
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn save(world: &World, path: &Path) -> anyhow::Result<()> {
+    let text = ron::ser::to_string_pretty(world, ron::ser::PrettyConfig::default())?;
+    std::fs::write(path, text).with_context(|| format!("Failed to write save {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_world_round_trips() {
+        let world = World::default();
+        let text = ron::ser::to_string_pretty(&world, ron::ser::PrettyConfig::default()).unwrap();
+        assert_eq!(ron::from_str::<World>(&text).unwrap(), world);
+    }
+
+    #[test]
+    fn handwritten_save_parses() {
+        let text = r##"(
+            player: (0.25, 0.75),
+            tiles: ["#~", ".,"],
+            entities: [(name: "chest", position: (1.0, 2.0), sprite: 3)],
+        )"##;
+        let world: World = ron::from_str(text).unwrap();
+        assert_eq!(world.player, [0.25, 0.75]);
+        assert_eq!(world.tiles, ["#~", ".,"]);
+        assert_eq!(
+            world.entities,
+            [Entity {
+                name: "chest".to_string(),
+                position: [1.0, 2.0],
+                sprite: 3,
+            }]
+        );
+    }
+
+    #[test]
+    fn save_and_load_through_a_file() {
+        let path = std::env::temp_dir().join(format!("danrpg_save_{}.ron", std::process::id()));
+        let world = World {
+            player: [3.5, -1.25],
+            ..World::default()
+        };
+        save(&world, &path).unwrap();
+        let loaded = load(&path);
+        std::fs::remove_file(&path).ok();
+        assert_eq!(loaded.unwrap(), world);
+    }
+}
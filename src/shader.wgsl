@@ -10,7 +10,9 @@ fn sphereSDF(samplePoint: vec3<f32>) -> f32 {
 
 // Signed distance function describing the scene.
 fn sceneSDF(samplePoint: vec3<f32>) -> f32 {
-    return sphereSDF(samplePoint);
+    // The first debug panel parameter grows the sphere
+    let radius = 1.0 + uniforms.params.x;
+    return sphereSDF(samplePoint / radius) * radius;
 }
 
 // Return the shortest distance from the eyepoint to the scene surface along
@@ -60,23 +62,92 @@ fn vs_main(@builtin(vertex_index) index: u32) -> VertexOutput {
 
 struct Uniforms {
     resolution: vec2<f32>,
+    mouse: vec2<f32>,
     time: f32,
-    padding: f32,
+    dt: f32,
+    dpi: f32,
+    zoom: f32,
+    offset: vec2<f32>,
+    // Counts up once per rendered frame
+    frame: u32,
+    // resolution.x / resolution.y, the convention is to scale x by it so y spans 0 to 1
+    aspect: f32,
+    // Generic values tweaked from the debug panel
+    params: vec4<f32>,
+    // Yaw and pitch in radians, turned by the mouse while the cursor is captured with Tab
+    look: vec2<f32>,
+    // Where the left button was last pressed in pixels, and the held buttons as MOUSE_ bits
+    click: vec2<f32>,
+    mouse_buttons: u32,
+    // Set with --seed and rerolled with R, for noise that's the same every run until rerolled
+    seed: u32,
+    padding: vec2<u32>,
+    // The camera's world to clip space transform and its inverse, shared with the overlays
+    view_projection: mat4x4<f32>,
+    inverse_view_projection: mat4x4<f32>,
+    // Multiplies the colour, white unless --tint is given
+    tint: vec4<f32>,
 };
-@group(0) @binding(0)
-var<uniform> uniforms: Uniforms;
+// Bits of `uniforms.mouse_buttons`
+const MOUSE_LEFT: u32 = 1u;
+const MOUSE_RIGHT: u32 = 2u;
+const MOUSE_MIDDLE: u32 = 4u;
 
+// PCG hash, the renderer rerolls the seed with the same one. Mix the seed in before hashing
+// anything else so every seed gives unrelated noise, and add the frame for noise that changes
+// each frame but replays the same from the same seed:
+//   let pixel = vec2<u32>(in.position.xy);
+//   let noise = random(pcg_hash(uniforms.seed + pixel.x) + pixel.y);
+//   let temporal = random(pcg_hash(pcg_hash(uniforms.seed + uniforms.frame) + pixel.x) + pixel.y);
+fn pcg_hash(value: u32) -> u32 {
+    let state = value * 747796405u + 2891336453u;
+    let word = ((state >> ((state >> 28u) + 4u)) ^ state) * 277803737u;
+    return (word >> 22u) ^ word;
+}
+
+// A float from 0 up to 1 out of a hash
+fn random(value: u32) -> f32 {
+    return f32(pcg_hash(value)) / 4294967296.0;
+}
+
+// Kept on one line so the renderer can swap it for a push constant block
+@group(0) @binding(0) var<uniform> uniforms: Uniforms;
+// The first image given with --texture, plain white when there isn't one. On GPUs that can bind
+// texture arrays, Vulkan, DX12 and Metal, every --texture is also at binding 5, up to
+// MAX_TEXTURES in options.rs and white past the ones given, each at its own size:
+//   @group(0) @binding(5) var input_textures: binding_array<texture_2d<f32>, 4>;
+// It's left out here so the shader still builds elsewhere.
+@group(0) @binding(1)
+var input_texture: texture_2d<f32>;
+@group(0) @binding(2)
+var input_sampler: sampler;
+// What the scene drew the frame before with --feedback, black on the first frame and without it
+@group(0) @binding(3)
+var previous_frame: texture_2d<f32>;
+@group(0) @binding(4)
+var previous_sampler: sampler;
+
+// Return linear colour. The surface is sRGB unless --color-space linear is given, and the GPU
+// encodes on write, so doing the encode here as well would wash the image out. With --hdr the
+// post pass encodes instead or the surface takes linear colour directly.
 @fragment
 fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
     let eye = vec3<f32>(0.0, 0.0, 5.0);
-    let dir = rayDirection(45.0, vec2<f32>(1.0, 1.0), in.tex_coords);
+    // Zoom around the centre of the screen then pan by the camera offset
+    let clip = in.tex_coords * 2.0 - 1.0;
+    let uv = (uniforms.inverse_view_projection * vec4<f32>(clip, 0.0, 1.0)).xy;
+    let dir = rayDirection(45.0, vec2<f32>(1.0, 1.0), uv);
     let dist = shortestDistanceToSurface(eye, dir, MIN_DIST, MAX_DIST);
     let time = uniforms.time;
+    // Sampling has to happen in uniform control flow, so before the early return
+    let tint = textureSample(input_texture, input_sampler, uv).rgb;
 
     if dist > MAX_DIST - EPSILON {
         // Didn't hit anything
         return vec4<f32>(0.0, 0.0, 0.0, 0.0);
     }
 
-    return vec4<f32>(time % 1.0, 0.0, 0.0, 1.0);
+    // Pulse between dark and bright red once every 2*pi seconds
+    let pulse = 0.5 + 0.5 * sin(time);
+    return vec4<f32>(vec3<f32>(pulse, 0.0, 0.0) * tint * uniforms.tint.rgb, 1.0);
 }
\ No newline at end of file
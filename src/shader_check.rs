@@ -0,0 +1,67 @@
+// Checking WGSL with naga before it's handed to wgpu, so mistakes come back with where they are
+use std::fmt;
+
+// Why a shader was rejected and where, `Display` shows naga's report with the offending lines.
+// Only hot reloading reads the fields, browsers just get the report.
+#[cfg_attr(target_arch = "wasm32", allow(dead_code))]
+#[derive(Debug, Clone)]
+pub struct ShaderError {
+    pub message: String,
+    // Line, column and byte range of the first labelled span, None when naga gives no position
+    pub location: Option<naga::SourceLocation>,
+    report: String,
+}
+
+impl fmt::Display for ShaderError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.report.trim_end())
+    }
+}
+
+impl std::error::Error for ShaderError {}
+
+// Parse and validate `source` as WGSL. Every capability is allowed, whether the device has them
+// is still checked by wgpu when the module is created.
+pub fn validate_wgsl(source: &str) -> Result<(), ShaderError> {
+    let module = naga::front::wgsl::parse_str(source).map_err(|error| ShaderError {
+        message: error.message().to_string(),
+        location: error.location(source),
+        report: error.emit_to_string(source),
+    })?;
+    naga::valid::Validator::new(
+        naga::valid::ValidationFlags::all(),
+        naga::valid::Capabilities::all(),
+    )
+    .validate(&module)
+    .map_err(|error| ShaderError {
+        message: error.as_inner().to_string(),
+        location: error.location(source),
+        report: error.emit_to_string(source),
+    })?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn embedded_shader_is_valid() {
+        validate_wgsl(include_str!("shader.wgsl")).unwrap();
+    }
+
+    #[test]
+    fn parse_error_has_location() {
+        let error = validate_wgsl("fn main() {\n    let x = ;\n}\n").unwrap_err();
+        let location = error.location.unwrap();
+        assert_eq!((location.line_number, location.line_position), (2, 13));
+        assert!(error.to_string().contains("wgsl:2:13"));
+    }
+
+    #[test]
+    fn validation_error_has_location() {
+        let source = "fn main() -> f32 {\n    return 1u;\n}\n";
+        let error = validate_wgsl(source).unwrap_err();
+        assert_eq!(error.location.unwrap().line_number, 1);
+    }
+}
@@ -0,0 +1,75 @@
+// Resources that depend on a size, like render targets, rebuilt only when the size they're needed
+// at really changes. Dragging a window edge sends resizes that often repeat a size, and with
+// --internal-res the scene's targets don't follow the window at all.
+
+pub struct SizedCache<T> {
+    size: (u32, u32),
+    value: T,
+}
+
+impl<T> SizedCache<T> {
+    pub fn new(size: (u32, u32), create: impl FnOnce((u32, u32)) -> T) -> Self {
+        Self {
+            size,
+            value: create(size),
+        }
+    }
+
+    // Rebuild at `size` unless that's the size already, returns whether it was rebuilt
+    pub fn resize(&mut self, size: (u32, u32), create: impl FnOnce((u32, u32)) -> T) -> bool {
+        if size == self.size {
+            return false;
+        }
+        self.size = size;
+        self.value = create(size);
+        true
+    }
+
+    pub fn get(&self) -> &T {
+        &self.value
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+
+    #[test]
+    fn repeated_sizes_are_not_rebuilt() {
+        // A drag as the events arrive, with the sizes that were already applied repeated
+        let drag = [
+            (800, 600),
+            (800, 600),
+            (810, 600),
+            (810, 600),
+            (810, 600),
+            (820, 605),
+            (820, 605),
+            (800, 600),
+        ];
+        let created = Cell::new(0);
+        let create = |size: (u32, u32)| {
+            created.set(created.get() + 1);
+            size
+        };
+        let mut targets = SizedCache::new((800, 600), create);
+        for size in drag {
+            targets.resize(size, create);
+        }
+        assert_eq!(*targets.get(), (800, 600));
+        // Recreating on every event would have made 9
+        assert_eq!(created.get(), 4);
+    }
+
+    #[test]
+    fn fixed_internal_size_is_built_once() {
+        let created = Cell::new(0);
+        let create = |_| created.set(created.get() + 1);
+        let mut targets = SizedCache::new((320, 180), create);
+        for _window_size in [(1280, 720), (1300, 720), (1920, 1080)] {
+            targets.resize((320, 180), create);
+        }
+        assert_eq!(created.get(), 1);
+    }
+}
@@ -0,0 +1,220 @@
+// Sprites collected over a frame and drawn together with one instanced draw call
+use crate::atlas::Atlas;
+use crate::quad;
+use crate::state::{create_pipeline_with_wireframe, PipelineSettings, PipelineWithWireframe};
+use anyhow::Context;
+use bytemuck::{Pod, Zeroable};
+
+// Sprites the instance buffer holds at first, it doubles whenever a frame needs more
+const INITIAL_CAPACITY: usize = 64;
+
+// One sprite as it's stored in the instance buffer, mirrors the instance inputs of vs_main in
+// sprite.wgsl
+#[repr(C)]
+#[derive(Debug, Copy, Clone, PartialEq, Pod, Zeroable)]
+pub struct Sprite {
+    // Centre of the sprite in world units
+    pub position: [f32; 2],
+    pub size: [f32; 2],
+    // Counterclockwise radians around the centre
+    pub rotation: f32,
+    // x, y, width and height in the atlas's texture coordinates, see `atlas::tile_rect`
+    pub atlas_rect: [f32; 4],
+    // Written to the id target with --picking, 0 for nothing, see picking.rs
+    pub id: u32,
+}
+
+impl Sprite {
+    // Tightly packed in field order, location 0 is the quad corner
+    const ATTRIBUTES: [wgpu::VertexAttribute; 5] = wgpu::vertex_attr_array![
+        1 => Float32x2, 2 => Float32x2, 3 => Float32, 4 => Float32x4, 5 => Uint32
+    ];
+
+    fn layout() -> wgpu::VertexBufferLayout<'static> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<Self>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Instance,
+            attributes: &Self::ATTRIBUTES,
+        }
+    }
+}
+
+pub struct SpriteBatch {
+    render_pipeline: PipelineWithWireframe,
+    quad_buffer: wgpu::Buffer,
+    instance_buffer: wgpu::Buffer,
+    // Sprites the instance buffer has room for
+    capacity: usize,
+    // Added since the last upload
+    pending: Vec<Sprite>,
+    // Uploaded and drawn by `flush`
+    instance_count: u32,
+}
+
+impl SpriteBatch {
+    // `bind_group_layout` is the scene's, sprites read the camera from its uniforms
+    pub async fn new(
+        device: &wgpu::Device,
+        bind_group_layout: &wgpu::BindGroupLayout,
+        atlas: &Atlas,
+        settings: &PipelineSettings,
+    ) -> anyhow::Result<Self> {
+        // The world's sprites are what can be picked
+        let settings = PipelineSettings {
+            pick_target: settings.pick_target.writing(),
+            ..*settings
+        };
+        Self::with_shader(
+            device,
+            bind_group_layout,
+            atlas,
+            include_str!("sprite.wgsl"),
+            &settings,
+        )
+        .await
+    }
+
+    // Draw the instances with another shader taking the same inputs, like text
+    pub async fn with_shader(
+        device: &wgpu::Device,
+        bind_group_layout: &wgpu::BindGroupLayout,
+        atlas: &Atlas,
+        source: &str,
+        settings: &PipelineSettings,
+    ) -> anyhow::Result<Self> {
+        // Like the tile map, sprites always read the uniform buffer
+        let settings = PipelineSettings {
+            push_constants: false,
+            ..*settings
+        };
+        let render_pipeline = create_pipeline_with_wireframe(
+            device,
+            source,
+            &[bind_group_layout, &atlas.bind_group_layout],
+            &[quad::CORNER_LAYOUT, Sprite::layout()],
+            &settings,
+        )
+        .await
+        .context("Failed to build the sprite pipeline")?;
+        Ok(Self {
+            render_pipeline,
+            quad_buffer: quad::create_quad_buffer(device),
+            instance_buffer: create_instance_buffer(device, INITIAL_CAPACITY),
+            capacity: INITIAL_CAPACITY,
+            pending: Vec::new(),
+            instance_count: 0,
+        })
+    }
+
+    // Queue a sprite for the next upload, later sprites draw over earlier ones
+    pub fn add(&mut self, sprite: Sprite) {
+        self.pending.push(sprite);
+    }
+
+    // Whether the last upload had nothing in it
+    pub fn is_empty(&self) -> bool {
+        self.instance_count == 0
+    }
+
+    // Copy the sprites added since last time into the instance buffer, must happen before the
+    // frame is submitted, growing the buffer if they don't fit
+    pub fn upload(&mut self, device: &wgpu::Device, queue: &wgpu::Queue) {
+        if self.pending.len() > self.capacity {
+            self.capacity = self.pending.len().next_power_of_two();
+            self.instance_buffer = create_instance_buffer(device, self.capacity);
+        }
+        if !self.pending.is_empty() {
+            queue.write_buffer(
+                &self.instance_buffer,
+                0,
+                bytemuck::cast_slice(&self.pending),
+            );
+        }
+        self.instance_count = self.pending.len() as u32;
+        self.pending.clear();
+    }
+
+    // Draw everything from the last upload in one call, inside the scene's pass
+    pub fn flush<'a>(
+        &'a self,
+        rpass: &mut wgpu::RenderPass<'a>,
+        bind_group: &'a wgpu::BindGroup,
+        uniform_offset: u32,
+        atlas: &'a Atlas,
+        wireframe: bool,
+    ) {
+        if self.is_empty() {
+            return;
+        }
+        rpass.set_pipeline(self.render_pipeline.get(wireframe));
+        rpass.set_bind_group(0, bind_group, &[uniform_offset]);
+        rpass.set_bind_group(1, &atlas.bind_group, &[]);
+        rpass.set_vertex_buffer(0, self.quad_buffer.slice(..));
+        rpass.set_vertex_buffer(1, self.instance_buffer.slice(..));
+        rpass.draw(0..6, 0..self.instance_count);
+    }
+}
+
+fn create_instance_buffer(device: &wgpu::Device, capacity: usize) -> wgpu::Buffer {
+    device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("sprite_instance_buffer"),
+        size: (capacity * std::mem::size_of::<Sprite>()) as wgpu::BufferAddress,
+        usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn attributes_match_the_struct_layout() {
+        let offsets = [
+            std::mem::offset_of!(Sprite, position),
+            std::mem::offset_of!(Sprite, size),
+            std::mem::offset_of!(Sprite, rotation),
+            std::mem::offset_of!(Sprite, atlas_rect),
+            std::mem::offset_of!(Sprite, id),
+        ];
+        for (attribute, offset) in Sprite::ATTRIBUTES.iter().zip(offsets) {
+            assert_eq!(attribute.offset, offset as u64);
+        }
+        let last = Sprite::ATTRIBUTES[4];
+        assert_eq!(
+            last.offset + last.format.size(),
+            std::mem::size_of::<Sprite>() as u64
+        );
+    }
+
+    #[test]
+    fn instances_are_packed_back_to_back() {
+        let sprites = [
+            Sprite {
+                position: [1.0, 2.0],
+                size: [3.0, 4.0],
+                rotation: 5.0,
+                atlas_rect: [6.0, 7.0, 8.0, 9.0],
+                id: 10,
+            },
+            Sprite {
+                position: [11.0, 12.0],
+                size: [13.0, 14.0],
+                rotation: 15.0,
+                atlas_rect: [16.0, 17.0, 18.0, 19.0],
+                id: 20,
+            },
+        ];
+        // Every field is 4 bytes, the ids are where the 10th and 20th floats would be
+        let words: &[u32] = bytemuck::cast_slice(&sprites);
+        let expected: Vec<u32> = (1..=20)
+            .map(|value| match value {
+                10 | 20 => value,
+                _ => (value as f32).to_bits(),
+            })
+            .collect();
+        assert_eq!(words, expected.as_slice());
+        let bytes: &[u8] = bytemuck::cast_slice(&sprites);
+        assert_eq!(bytes.len(), 2 * Sprite::layout().array_stride as usize);
+    }
+}
@@ -0,0 +1,72 @@
+// Textured quads placed, sized and rotated per instance, cut out of the shared atlas
+
+// Same layout as shader.wgsl, only the camera is used here
+struct Uniforms {
+    resolution: vec2<f32>,
+    mouse: vec2<f32>,
+    time: f32,
+    dt: f32,
+    dpi: f32,
+    zoom: f32,
+    offset: vec2<f32>,
+    frame: u32,
+    aspect: f32,
+    params: vec4<f32>,
+    look: vec2<f32>,
+    click: vec2<f32>,
+    mouse_buttons: u32,
+    seed: u32,
+    padding: vec2<u32>,
+    view_projection: mat4x4<f32>,
+    inverse_view_projection: mat4x4<f32>,
+};
+@group(0) @binding(0) var<uniform> uniforms: Uniforms;
+@group(1) @binding(0)
+var atlas_texture: texture_2d<f32>;
+@group(1) @binding(1)
+var atlas_sampler: sampler;
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) tex_coords: vec2<f32>,
+    @location(1) @interpolate(flat) id: u32,
+};
+
+struct FragmentOutput {
+    @location(0) colour: vec4<f32>,
+    // The id target with --picking, ignored without it
+    @location(1) id: u32,
+};
+
+@vertex
+fn vs_main(
+    @location(0) corner: vec2<f32>,
+    @location(1) position: vec2<f32>,
+    @location(2) size: vec2<f32>,
+    @location(3) rotation: f32,
+    @location(4) atlas_rect: vec4<f32>,
+    @location(5) id: u32,
+) -> VertexOutput {
+    // Rotate counterclockwise around the sprite's centre
+    let local = (corner - 0.5) * size;
+    let c = cos(rotation);
+    let s = sin(rotation);
+    let world = position + vec2<f32>(local.x * c - local.y * s, local.x * s + local.y * c);
+    // The camera's transform, like the tile map
+    var out: VertexOutput;
+    out.position = uniforms.view_projection * vec4<f32>(world, 0.0, 1.0);
+    // Texture rows go down while the world goes up
+    out.tex_coords = atlas_rect.xy + vec2<f32>(corner.x, 1.0 - corner.y) * atlas_rect.zw;
+    out.id = id;
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> FragmentOutput {
+    let colour = textureSample(atlas_texture, atlas_sampler, in.tex_coords);
+    // Cut out the transparent parts rather than blending, so sprites need no sorting
+    if colour.a < 0.5 {
+        discard;
+    }
+    return FragmentOutput(colour, in.id);
+}
@@ -0,0 +1,82 @@
+// How long each step of starting up took with --profile-startup, logged once the first frame is
+// drawn. Getting the adapter and device is usually most of it. Without the flag nothing reads the
+// clock and nothing is kept.
+use std::time::Duration;
+
+pub struct StartupProfile {
+    // When startup began and when the last step finished, None without --profile-startup
+    clock: Option<(instant::Instant, instant::Instant)>,
+    steps: Vec<(&'static str, Duration)>,
+}
+
+impl StartupProfile {
+    pub fn new(enabled: bool) -> Self {
+        Self {
+            clock: enabled.then(|| {
+                let now = instant::Instant::now();
+                (now, now)
+            }),
+            steps: Vec::new(),
+        }
+    }
+
+    // Nothing timed, for starting over after losing the device
+    #[cfg_attr(target_arch = "wasm32", allow(dead_code))] // The browser reloads instead
+    pub fn off() -> Self {
+        Self::new(false)
+    }
+
+    // The step called `name` finished just now, it took the time since the last one did
+    pub fn step(&mut self, name: &'static str) {
+        if let Some((_, last)) = &mut self.clock {
+            let now = instant::Instant::now();
+            self.steps.push((name, now - *last));
+            *last = now;
+        }
+    }
+
+    // Log the steps so far, once, as the first frame is drawn
+    pub fn report(&mut self) {
+        if let Some((start, _)) = self.clock.take() {
+            log::info!("{}", summary(&self.steps, start.elapsed()));
+        }
+    }
+}
+
+// A line per step with its share of the total, in the order they ran
+fn summary(steps: &[(&str, Duration)], total: Duration) -> String {
+    let width = steps.iter().map(|(name, _)| name.len()).max().unwrap_or(0);
+    let mut summary = format!("Started in {:.1} ms", total.as_secs_f64() * 1000.0);
+    for (name, time) in steps {
+        summary += &format!(
+            "\n  {name:<width$}  {:>8.1} ms  {:>3.0}%",
+            time.as_secs_f64() * 1000.0,
+            time.as_secs_f64() / total.as_secs_f64().max(f64::EPSILON) * 100.0
+        );
+    }
+    summary
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn steps_are_listed_with_their_share() {
+        let steps = [
+            ("create_instance", Duration::from_millis(10)),
+            ("create_device_queue", Duration::from_millis(150)),
+        ];
+        assert_eq!(
+            summary(&steps, Duration::from_millis(200)),
+            concat!(
+                "Started in 200.0 ms\n",
+                "  create_instance          10.0 ms    5%\n",
+                "  create_device_queue     150.0 ms   75%",
+            )
+        );
+        let mut off = StartupProfile::off();
+        off.step("create_instance");
+        assert!(off.steps.is_empty());
+    }
+}
@@ -0,0 +1,3299 @@
+#[cfg(all(feature = "audio", not(target_arch = "wasm32")))]
+use crate::audio::Audio;
+use crate::camera::{self, Camera, Follow};
+#[cfg(not(target_arch = "wasm32"))]
+use crate::capture;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::color::Color;
+use crate::compute::{self, Simulation};
+use crate::device_loss;
+use crate::dynamic_res::DynamicResolution;
+use crate::ecs;
+use crate::gamepad::Gamepads;
+use crate::gpu_timer::{GpuTimer, Pass};
+use crate::graph::{self, Node};
+use crate::grid::Grid;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::hot_reload::ShaderWatcher;
+use crate::input::{Action, KeyStates, Shortcuts};
+use crate::limits;
+use crate::minimap::Minimap;
+#[cfg(all(feature = "net", not(target_arch = "wasm32")))]
+use crate::net::Network;
+use crate::options::{ColorSpace, Options, MAX_TEXTURES};
+use crate::particles::Particles;
+use crate::perf_graph::PerfGraph;
+use crate::picking::{self, PickTarget};
+use crate::post::{self, PostChain, Scaling};
+#[cfg(not(target_arch = "wasm32"))]
+use crate::preprocess::SourceLocation;
+use crate::preprocess::{self, ShaderSource};
+#[cfg(not(target_arch = "wasm32"))]
+use crate::preset::{self, Preset};
+#[cfg(not(target_arch = "wasm32"))]
+use crate::save;
+use crate::save::World;
+use crate::shader_check;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::shader_check::ShaderError;
+use crate::sized::SizedCache;
+use crate::startup::StartupProfile;
+use crate::stats::FrameStats;
+use crate::text::TextRenderer;
+use crate::texture;
+use crate::timestep::{FixedTimestep, LOGIC_DT};
+use crate::touch::{TouchAction, Touches};
+use crate::ui::{EditorRequest, Ui, PARAM_COUNT};
+use crate::uniform_slots::UniformSlots;
+use crate::world::WorldRenderer;
+use anyhow::Context;
+use bytemuck::{Pod, Zeroable};
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::AtomicBool;
+use std::sync::Arc;
+use winit::{
+    event::{ElementState, KeyboardInput, MouseButton, MouseScrollDelta, WindowEvent},
+    window::{CursorGrabMode, Fullscreen, Window, WindowId},
+};
+
+// Mirrors the `Uniforms` struct in shader.wgsl, the size must stay a multiple of 16 bytes
+#[repr(C)]
+#[derive(Debug, Copy, Clone, Pod, Zeroable)]
+pub struct Uniforms {
+    resolution: [f32; 2],
+    // Cursor position in physical pixels, clamped to the window
+    mouse: [f32; 2],
+    // Seconds elapsed since startup
+    time: f32,
+    // Seconds since the previous frame
+    dt: f32,
+    // Physical pixels per logical pixel
+    dpi: f32,
+    // Camera magnification and the world position at the centre of the screen
+    zoom: f32,
+    offset: [f32; 2],
+    // Frames rendered before this one, a u32 is 4 bytes like the f32s so nothing shifts
+    frame: u32,
+    // Width over height, multiply x by it to get UVs that aren't stretched
+    aspect: f32,
+    // Generic values tweaked from the debug panel
+    params: [f32; PARAM_COUNT],
+    // Mouselook yaw and pitch in radians, see `Camera::look_by`
+    look: [f32; 2],
+    // Like Shadertoy's iMouse, where the left button was last pressed and which buttons are held
+    click: [f32; 2],
+    mouse_buttons: u32,
+    // From --seed, rerolled with R, see `pcg_hash` in shader.wgsl
+    seed: u32,
+    padding: [u32; 2],
+    // The camera's world to clip transform and back, see `Camera2D`. WGSL aligns matrices to 16
+    // bytes, which the padding above lines them up on.
+    view_projection: [[f32; 4]; 4],
+    inverse_view_projection: [[f32; 4]; 4],
+    // From --tint, already converted for the scene's target like the clear colour. Last, so the
+    // overlays' shaders can leave it out of their copy of the struct.
+    tint: [f32; 4],
+}
+
+// Caught when building rather than as garbage read by the shader on some backends, the tests
+// below check each field against the WGSL too
+const _: () = assert!(std::mem::size_of::<Uniforms>().is_multiple_of(16));
+
+impl Uniforms {
+    // Flip the camera for the depth buffer --reverse-z sets up
+    pub fn reverse_depth(&mut self) {
+        self.view_projection = camera::reverse_depth(self.view_projection);
+        self.inverse_view_projection = camera::reverse_depth_inverse(self.inverse_view_projection);
+    }
+
+    pub fn set_tint(&mut self, tint: wgpu::Color) {
+        self.tint = [tint.r, tint.g, tint.b, tint.a].map(|value| value as f32);
+    }
+}
+
+// Bits of `mouse_buttons`, matching the constants in shader.wgsl
+pub const MOUSE_LEFT: u32 = 1;
+pub const MOUSE_RIGHT: u32 = 2;
+pub const MOUSE_MIDDLE: u32 = 4;
+
+// The cursor as the shader sees it, positions are physical pixels from the top left
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Mouse {
+    pub position: [f32; 2],
+    // Held buttons as `MOUSE_LEFT` and the others
+    pub buttons: u32,
+    // Where the left button was last pressed, for dragging
+    pub click: [f32; 2],
+}
+
+// Where a frame sits in time
+#[derive(Debug, Default, Clone, Copy)]
+pub struct FrameTime {
+    // Seconds since startup and since the previous frame
+    pub time: f32,
+    pub dt: f32,
+    // Index of the frame, counting up once per redraw
+    pub frame: u32,
+}
+
+// Location of the embedded shader's source, read from disk when hot reloading
+const SHADER_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/src/shader.wgsl");
+
+pub const DEPTH_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Depth32Float;
+
+// How shader.wgsl declares its uniforms, swapped for a push constant block when the device
+// supports them so per-frame values skip the buffer write
+const UNIFORM_DECLARATION: &str = "@group(0) @binding(0) var<uniform> uniforms: Uniforms;";
+const PUSH_CONSTANT_DECLARATION: &str = "var<push_constant> uniforms: Uniforms;";
+
+// Longest frame delta passed to the shader, so stalls don't cause a huge jump
+const MAX_DT: f32 = 0.1;
+// How far one step moves the clock while paused, a frame at 60 fps
+const STEP_DT: f32 = 1.0 / 60.0;
+// How many steps a line of scrolling moves the paused clock with Shift held, rather than one
+const FAST_SCRUB_STEPS: f32 = 10.0;
+// How long a notice like a dropped file being loaded stays on screen
+const NOTICE_DURATION: std::time::Duration = std::time::Duration::from_secs(2);
+// Frames in a row a surface can come back lost or outdated, reconfigured each time, before it's
+// replaced with a new one made from the window
+const SURFACE_RETRIES: u32 = 3;
+
+// Fixed state the render pipeline is built with
+#[derive(Debug, Clone, Copy)]
+pub struct PipelineSettings {
+    pub format: wgpu::TextureFormat,
+    // Samples per pixel, anything above 1 renders into a multisampled texture and resolves
+    pub sample_count: u32,
+    // Whether the device has push constants, WebGL2 and some older GPUs don't
+    pub push_constants: bool,
+    // How fragments combine with what's already drawn, None overwrites it
+    pub blend: Option<wgpu::BlendState>,
+    // Whether the pass has the depth attachment, only the scene's pass does
+    pub depth_test: bool,
+    // Line only draws the edges of triangles, see `PipelineWithWireframe`
+    pub polygon_mode: wgpu::PolygonMode,
+    // With --reverse-z the depth buffer clears to 0 and nearer means greater. Floats are densest
+    // near 0, and a perspective projection crowds most distances up against the far plane, so
+    // putting the far plane at 0 lines the two up and keeps precision even over distance instead
+    // of wasting it right in front of the camera. Everything is the same depth in the 2D views
+    // drawn now, so they look the same either way, it's for 3D scenes with a real far plane.
+    pub reverse_z: bool,
+    pub pick_target: PickTarget,
+}
+
+// The far plane, what the depth buffer is cleared to
+pub fn depth_clear(reverse_z: bool) -> f32 {
+    if reverse_z {
+        0.0
+    } else {
+        1.0
+    }
+}
+
+// One window's surface and the targets it renders into, the device and pipelines are shared
+pub struct WindowState {
+    window: Window,
+    surface: wgpu::Surface,
+    config: wgpu::SurfaceConfiguration,
+    // Which copy of the uniforms in the shared buffer is this window's, the resolution and cursor
+    // differ between windows
+    slot: u32,
+    uniforms: Uniforms,
+    // Drawn into at the scene's size, kept while it stays the same
+    scene_targets: SizedCache<SceneTargets>,
+    // Post-processing run on the scene before it reaches the window, unless --single-pass
+    post: Option<PostChain>,
+    // With --feedback, group 0 sampling each of the post chain's scene targets. The first is bound
+    // while drawing and reads the previous frame, they swap along with the targets.
+    feedback_bind_groups: Option<[wgpu::BindGroup; 2]>,
+    mouse: Mouse,
+    ui: Ui,
+    // Size to go back to when leaving fullscreen
+    windowed_size: Option<winit::dpi::PhysicalSize<u32>>,
+    // How the window was fullscreen last, so F11 goes back into the exclusive mode it started in
+    last_fullscreen: Option<Fullscreen>,
+    // The newest size from resize events, applied before the next frame so a burst of events
+    // reconfigures the surface once
+    pending_size: Option<winit::dpi::PhysicalSize<u32>>,
+    // A zero sized surface can't be configured, this happens while the window is minimized
+    minimized: bool,
+    // Completely covered by other windows or on another workspace, nothing drawn would be seen
+    occluded: bool,
+    // Frames in a row the surface was lost or outdated, see `State::recover_surface`
+    outdated_frames: u32,
+    // The cursor is hidden and held in the window, mouse movement turns the camera instead
+    cursor_captured: bool,
+    // Fingers on the window, which drag and pinch like the mouse and scroll wheel
+    touches: Touches,
+    #[cfg(not(target_arch = "wasm32"))]
+    screenshot_requested: bool,
+}
+
+impl WindowState {
+    pub fn window(&self) -> &Window {
+        &self.window
+    }
+
+    // The surface is always reconfigured, it may be stale even at the same size, but targets are
+    // only rebuilt when the size they're drawn at changes. Returns whether the post chain's were.
+    fn resize(
+        &mut self,
+        device: &wgpu::Device,
+        settings: &PipelineSettings,
+        size: winit::dpi::PhysicalSize<u32>,
+    ) -> bool {
+        self.minimized = size.width == 0 || size.height == 0;
+        if self.minimized {
+            return false;
+        }
+        self.config.width = size.width;
+        self.config.height = size.height;
+        self.surface.configure(device, &self.config);
+        let post_rebuilt = self
+            .post
+            .as_mut()
+            .is_some_and(|post| post.resize(device, size.width, size.height));
+        // The scene's targets stay at the internal resolution if there is one
+        self.scene_targets
+            .resize(scene_size(self.post.as_ref(), size), |size| {
+                create_scene_targets(device, settings, size)
+            });
+        post_rebuilt
+    }
+
+    // A point in physical pixels as screen widths and heights from the centre with up positive,
+    // how the camera takes its zoom anchors. Pixel y runs down the screen.
+    fn screen_anchor(&self, position: [f32; 2]) -> [f32; 2] {
+        let size = self.window.inner_size();
+        [
+            position[0] / size.width.max(1) as f32 - 0.5,
+            0.5 - position[1] / size.height.max(1) as f32,
+        ]
+    }
+
+    // The cursor in the scene's pixels, which differ from the window's with --internal-res or
+    // --target-ms
+    fn scene_mouse(&self) -> Mouse {
+        let Some(post) = &self.post else {
+            return self.mouse;
+        };
+        Mouse {
+            position: post.to_scene(self.mouse.position),
+            click: post.to_scene(self.mouse.click),
+            ..self.mouse
+        }
+    }
+
+    // Print what's under the cursor in the frame last drawn, with --picking
+    #[cfg(not(target_arch = "wasm32"))]
+    fn pick(&self, device: &wgpu::Device, queue: &wgpu::Queue, entities: &ecs::World) {
+        use picking::{NO_ID, PLAYER_ID};
+
+        let Some((texture, _)) = &self.scene_targets.get().id_texture else {
+            return;
+        };
+        let [x, y] = self.scene_mouse().position.map(f32::floor);
+        // Off the scene, in the bars around an internal resolution or on the far edge
+        if x < 0.0 || y < 0.0 || x >= texture.width() as f32 || y >= texture.height() as f32 {
+            log::info!("Picked nothing");
+            return;
+        }
+        match picking::read_id(device, queue, texture, [x as u32, y as u32]) {
+            Ok(NO_ID) => log::info!("Picked nothing"),
+            Ok(PLAYER_ID) => log::info!("Picked the player"),
+            Ok(id) => match entities.picked(id) {
+                Some(entity) => log::info!("Picked {entity:?}"),
+                None => log::info!("Picked an entity that's been despawned"),
+            },
+            Err(error) => log::warn!("Failed to read back the picked id: {error:#}"),
+        }
+    }
+
+    // This frame becomes the previous one, the next renders over the older target
+    fn swap_feedback(&mut self) {
+        if let (Some(post), Some(bind_groups)) = (&mut self.post, &mut self.feedback_bind_groups) {
+            post.swap_feedback();
+            bind_groups.swap(0, 1);
+        }
+    }
+
+    // Switch between windowed and borderless fullscreen, the resize that follows reconfigures
+    // the surface
+    fn toggle_fullscreen(&mut self) {
+        if let Some(fullscreen) = self.window.fullscreen() {
+            self.last_fullscreen = Some(fullscreen);
+            self.window.set_fullscreen(None);
+            if let Some(size) = self.windowed_size.take() {
+                self.window.set_inner_size(size);
+            }
+        } else {
+            self.windowed_size = Some(self.window.inner_size());
+            let fullscreen = self
+                .last_fullscreen
+                .take()
+                .unwrap_or(Fullscreen::Borderless(None));
+            self.window.set_fullscreen(Some(fullscreen));
+        }
+    }
+
+    // Hide the cursor and keep it in the window for mouselook. Locking it in place is best but
+    // X11 and Windows can only confine it to the window, which works as well for relative motion.
+    fn capture_cursor(&mut self) {
+        let result = self
+            .window
+            .set_cursor_grab(CursorGrabMode::Locked)
+            .or_else(|_| self.window.set_cursor_grab(CursorGrabMode::Confined));
+        match result {
+            Ok(()) => {
+                self.window.set_cursor_visible(false);
+                self.cursor_captured = true;
+                log::info!("Captured the cursor, press Escape to release it");
+            }
+            Err(error) => log::warn!("Failed to capture the cursor: {error}"),
+        }
+    }
+
+    fn release_cursor(&mut self) {
+        if !std::mem::take(&mut self.cursor_captured) {
+            return;
+        }
+        if let Err(error) = self.window.set_cursor_grab(CursorGrabMode::None) {
+            log::warn!("Failed to release the cursor: {error}");
+        }
+        self.window.set_cursor_visible(true);
+    }
+}
+
+// Everything the windows share, and the windows themselves
+pub struct State {
+    // Kept to create surfaces for windows added after startup
+    instance: wgpu::Instance,
+    adapter: wgpu::Adapter,
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    // Raised by the uncaptured error handler, see device_loss.rs
+    device_lost: Arc<AtomicBool>,
+    pipeline_settings: PipelineSettings,
+    // Background behind the scene, already converted for the surface format
+    clear_color: wgpu::Color,
+    // The --clear-color it was converted from, or the one from the last preset loaded
+    #[cfg(not(target_arch = "wasm32"))]
+    background: Color,
+    // Whether the surface takes linear colour, so the background is decoded from sRGB for it
+    #[cfg(not(target_arch = "wasm32"))]
+    linear_clear: bool,
+    // --tint converted the same way
+    tint: wgpu::Color,
+    present_mode: Option<wgpu::PresentMode>,
+    // The surfaces composite with premultiplied alpha, only with --transparent
+    transparent: bool,
+    single_pass: bool,
+    // Each window keeps its previous frame for the scene to sample, with --feedback
+    feedback: bool,
+    // The scene's fixed size with --internal-res, or its multiple of the window's with --ssaa
+    scaling: Scaling,
+    // Scales the scene to hold --target-ms, None without it or when the scene's size is fixed
+    dynamic_resolution: Option<DynamicResolution>,
+    // The last post pass encodes to sRGB itself, for the 10-bit surface --hdr can pick
+    encode_srgb: bool,
+    bind_group_layout: wgpu::BindGroupLayout,
+    // The input textures, kept to rebuild the bind group when the uniform buffer grows
+    textures: Vec<wgpu::TextureView>,
+    // How a dropped image is loaded, like the --texture ones
+    #[cfg(not(target_arch = "wasm32"))]
+    linear_texture: bool,
+    sampler: wgpu::Sampler,
+    // Reads the previous frame, which is a black texel in the shared bind group
+    frame_sampler: wgpu::Sampler,
+    empty_frame: wgpu::TextureView,
+    uniform_slots: UniformSlots,
+    bind_group: wgpu::BindGroup,
+    render_pipeline: wgpu::RenderPipeline,
+    // The current shader reads its uniforms from push constants rather than the buffer
+    push_uniforms: bool,
+    // The game's state, the player follows the camera unless the camera follows it
+    world: World,
+    // The world's entities as the game runs, spawned from the save's
+    entities: ecs::World,
+    #[cfg(not(target_arch = "wasm32"))]
+    save_path: PathBuf,
+    // Tiles and sprites drawn over the scene with --tilemap or --load
+    world_renderer: Option<WorldRenderer>,
+    minimap: Option<Minimap>,
+    // Whether the next window drawn redraws the minimap, set by the frame's update
+    minimap_due: bool,
+    // Cellular automaton run with --compute, unless the adapter has no compute shaders
+    simulation: Option<Simulation>,
+    // Sparks following the cursor with --particles
+    particles: Option<Particles>,
+    // Drawn over the scene while `show_grid` is set
+    grid: Grid,
+    show_grid: bool,
+    // On-screen text drawn over every window
+    hud: TextRenderer,
+    // Where the shader was loaded from, None for the embedded copy, and where else to look for
+    // the files it includes
+    #[cfg(not(target_arch = "wasm32"))]
+    shader_path: Option<PathBuf>,
+    #[cfg(not(target_arch = "wasm32"))]
+    include_dirs: Vec<PathBuf>,
+    #[cfg(not(target_arch = "wasm32"))]
+    shader_watcher: Option<ShaderWatcher>,
+    // Why the last hot reload or edit failed, shown on screen until the shader compiles again
+    shader_error: Option<String>,
+    // The shader's source in the --editor panel, with any changes that haven't been applied
+    editor: Option<String>,
+    // What the pipeline was last built from, a new device builds it from the same
+    #[cfg(not(target_arch = "wasm32"))]
+    shader: ShaderSource,
+    frame_stats: Option<FrameStats>,
+    // Drawn by the UI while `show_perf_graph` is set, recording all the while so it's full when
+    // shown
+    perf_graph: PerfGraph,
+    show_perf_graph: bool,
+    gpu_timer: Option<GpuTimer>,
+    // The frame's passes in the order the render graph runs them
+    frame_order: Vec<Pass>,
+    last_frame: Option<instant::Instant>,
+    // The shader's clock, it only advances while running or when stepping a frame
+    time: f32,
+    frame: u32,
+    // Where the last update left the clock, every window drawn after it uses the same time
+    frame_time: FrameTime,
+    // Real time not yet run through the fixed logic steps
+    timestep: FixedTimestep,
+    // Whether this frame's sprites are in the instance buffer yet
+    sprites_uploaded: bool,
+    // Space freezes the clock and period then steps it one frame at a time, or the scroll wheel
+    // with Ctrl held scrubs it either way
+    paused: bool,
+    single_step: bool,
+    // A line shown on the HUD until the time it was set plus NOTICE_DURATION
+    notice: Option<(String, instant::Instant)>,
+    // Whether one of the windows has focus, nothing is drawn while another app is in front
+    focused: bool,
+    // When the last window stopped being drawn, and whether the clock catches up on that time
+    // once one is drawn again rather than carrying on from where it stopped
+    hidden_since: Option<instant::Instant>,
+    background_animation: bool,
+    // Which keys are down and which changed this frame
+    keys: KeyStates,
+    shortcuts: Shortcuts,
+    camera: Camera,
+    // Eases the camera after the player with --follow, which the keys then move instead
+    follow: Option<Follow>,
+    gamepads: Option<Gamepads>,
+    // Held here so the output stream lives as long as the event loop, None without a device
+    #[cfg(all(feature = "audio", not(target_arch = "wasm32")))]
+    audio: Option<Audio>,
+    // Other players from --serve or --connect, kept in `entities`
+    #[cfg(all(feature = "net", not(target_arch = "wasm32")))]
+    network: Option<Network>,
+    params: [f32; PARAM_COUNT],
+    // Passed to the shaders for their noise, R replaces it with its hash
+    seed: u32,
+    // Draw the tile map and sprites as lines, toggled with F3
+    wireframe: bool,
+    windows: HashMap<WindowId, WindowState>,
+}
+
+impl State {
+    // Picks an adapter that can present to `window`, more windows can be added afterwards. Each
+    // step is timed in `startup` with --profile-startup.
+    pub async fn new(
+        window: Window,
+        options: &Options,
+        startup: &mut StartupProfile,
+    ) -> anyhow::Result<Self> {
+        let shader = load_shader_source(shader_path(options).as_deref(), &options.include_dirs)?;
+        startup.step("load_shader_source");
+        let mut state = Self::with_device(window, options, startup, shader).await?;
+        state.gamepads = Gamepads::new();
+        #[cfg(all(feature = "audio", not(target_arch = "wasm32")))]
+        {
+            state.audio = Audio::new();
+        }
+        #[cfg(all(feature = "net", not(target_arch = "wasm32")))]
+        {
+            state.network = Network::start(options);
+        }
+        startup.step("start_gamepads_audio_network");
+        Ok(state)
+    }
+
+    // Everything made from the device, with `shader` in the pipeline. What outlives the device
+    // starts out empty for `new` to start or `recreate` to move over from the lost one.
+    async fn with_device(
+        window: Window,
+        options: &Options,
+        startup: &mut StartupProfile,
+        shader: ShaderSource,
+    ) -> anyhow::Result<Self> {
+        let instance = create_instance(options.backends);
+        startup.step("create_instance");
+        let surface = unsafe { create_surface(&instance, &window) }?;
+        startup.step("create_surface");
+        let (adapter, device, queue) =
+            create_device_queue(&instance, Some(&surface), options).await?;
+        startup.step("create_device_queue");
+        let device_lost = device_loss::watch(&device);
+        let shader_path = shader_path(options);
+
+        let world = load_world(options)?;
+        let camera = Camera::centred_on(world.player);
+        #[cfg(not(all(feature = "net", not(target_arch = "wasm32"))))]
+        if options.serve.is_some() || options.connect.is_some() {
+            log::warn!("Built without the net feature, ignoring --serve and --connect");
+        }
+        let textures = load_input_textures(&device, &queue, options)?;
+        startup.step("load_world_and_textures");
+        let sampler = texture::create_sampler(&device);
+        let frame_sampler = post::create_frame_sampler(&device);
+        let empty_frame = post::create_empty_frame(&device);
+        let bind_group_layout = create_bind_group_layout(&device);
+        // The minimap's camera has a slot of its own after the windows'
+        let minimap_slot =
+            (options.minimap && draws_world(options)).then_some(options.windows as u32);
+        let uniform_slots = UniformSlots::new(
+            &device,
+            options.windows as u32 + u32::from(minimap_slot.is_some()),
+        );
+        let bind_group = create_bind_group(
+            &device,
+            &bind_group_layout,
+            uniform_slots.buffer(),
+            &textures,
+            &sampler,
+            &empty_frame,
+            &frame_sampler,
+        );
+        // The previous frame is kept by the post chain
+        if options.feedback && options.single_pass {
+            log::warn!("--feedback needs the post-processing pass, ignoring it with --single-pass");
+        }
+        // So is the upscaling from an internal resolution
+        if options.internal_res.is_some() && options.single_pass {
+            log::warn!(
+                "--internal-res needs the post-processing pass, ignoring it with --single-pass"
+            );
+        }
+
+        let (swapchain_format, hdr) = get_swapchain_format(&surface, &adapter, options)?;
+        let transparent = options.transparent && supports_transparency(&surface, &adapter);
+        let mut pipeline_settings = PipelineSettings {
+            format: swapchain_format,
+            sample_count: validate_sample_count(&adapter, swapchain_format, options.msaa),
+            push_constants: supports_push_constants(&device),
+            // Blending onto the transparent clear leaves premultiplied colour for the compositor
+            blend: transparent.then_some(wgpu::BlendState::ALPHA_BLENDING),
+            depth_test: true,
+            polygon_mode: wgpu::PolygonMode::Fill,
+            reverse_z: options.reverse_z,
+            pick_target: PickTarget::None,
+        };
+        if picking::supported(&adapter, options, pipeline_settings.sample_count) {
+            pipeline_settings.pick_target = PickTarget::Keep;
+        }
+        let push_uniforms = uses_push_constants(&shader.text, &pipeline_settings);
+        startup.step("create_bind_groups");
+        let render_pipeline = create_validated_render_pipeline(
+            &device,
+            &shader.text,
+            &[&bind_group_layout],
+            &[],
+            &pipeline_settings,
+        )
+        .await
+        .map_err(|error| shader.map.annotate(error))
+        .context("Failed to build the render pipeline")?;
+        startup.step("create_render_pipeline");
+        let mut world_renderer = if draws_world(options) {
+            Some(
+                WorldRenderer::new(
+                    &device,
+                    &queue,
+                    &bind_group_layout,
+                    &world,
+                    &pipeline_settings,
+                )
+                .await?,
+            )
+        } else {
+            None
+        };
+        if options.minimap && world_renderer.is_none() {
+            log::warn!("--minimap shows the world from --tilemap or --load, ignoring it");
+        }
+        let minimap = match (minimap_slot, &mut world_renderer) {
+            (Some(slot), Some(world_renderer)) => {
+                let minimap = Minimap::new(
+                    &device,
+                    &pipeline_settings,
+                    options.minimap_size,
+                    options.minimap_every,
+                    slot,
+                );
+                world_renderer
+                    .add_dots(
+                        &device,
+                        &bind_group_layout,
+                        &pipeline_settings,
+                        minimap.dot_size(),
+                    )
+                    .await
+                    .context("Failed to set up the minimap")?;
+                Some(minimap)
+            }
+            _ => None,
+        };
+        startup.step("create_world_renderer");
+        let simulation = create_simulation(
+            &adapter,
+            &device,
+            &bind_group_layout,
+            &pipeline_settings,
+            options,
+        )
+        .await?;
+        let particles = create_particles(
+            &adapter,
+            &device,
+            &bind_group_layout,
+            &pipeline_settings,
+            options,
+        )
+        .await?;
+        let grid = Grid::new(&device, &bind_group_layout, &pipeline_settings).await?;
+        let hud = TextRenderer::new(&device, &queue, &bind_group_layout, swapchain_format)
+            .await
+            .context("Failed to set up the HUD")?;
+        startup.step("create_overlays");
+        let dynamic_resolution = create_dynamic_resolution(options);
+        // The GPU's time is what the resolution changes, so it's measured for --target-ms too
+        let gpu_timer = (options.stats || dynamic_resolution.is_some())
+            .then(|| GpuTimer::new(&device, &queue, options.profile))
+            .flatten();
+        if dynamic_resolution.is_some() && gpu_timer.is_none() {
+            log::warn!(
+                "The device can't time the GPU, --target-ms goes by whole frames instead, which \
+                 vsync can hold at the refresh rate"
+            );
+        } else if options.profile && gpu_timer.is_none() {
+            log::warn!("The device can't time the GPU, --profile has no passes to report");
+        }
+        let wireframe = starts_wireframe(&device, options);
+        let frame_order = schedule_frame().context("Failed to order the frame's passes")?;
+
+        let mut state = Self {
+            instance,
+            adapter,
+            device,
+            queue,
+            device_lost,
+            pipeline_settings,
+            clear_color: if transparent {
+                wgpu::Color::TRANSPARENT
+            } else {
+                options
+                    .clear_color
+                    .for_target(swapchain_format.is_srgb() || hdr)
+            },
+            #[cfg(not(target_arch = "wasm32"))]
+            background: options.clear_color,
+            #[cfg(not(target_arch = "wasm32"))]
+            linear_clear: swapchain_format.is_srgb() || hdr,
+            tint: options.tint.for_target(swapchain_format.is_srgb() || hdr),
+            present_mode: options.present_mode,
+            transparent,
+            single_pass: options.single_pass,
+            feedback: options.feedback && !options.single_pass,
+            scaling: create_scaling(options),
+            dynamic_resolution,
+            encode_srgb: hdr && encodes_in_shader(swapchain_format),
+            bind_group_layout,
+            textures,
+            #[cfg(not(target_arch = "wasm32"))]
+            linear_texture: options.linear_texture,
+            sampler,
+            frame_sampler,
+            empty_frame,
+            uniform_slots,
+            bind_group,
+            render_pipeline,
+            push_uniforms,
+            entities: ecs::World::from_save(&world.entities),
+            world,
+            #[cfg(not(target_arch = "wasm32"))]
+            save_path: options
+                .load
+                .clone()
+                .unwrap_or_else(|| save::DEFAULT_SAVE_PATH.into()),
+            world_renderer,
+            minimap,
+            minimap_due: false,
+            simulation,
+            particles,
+            grid,
+            show_grid: options.grid,
+            hud,
+            #[cfg(not(target_arch = "wasm32"))]
+            shader_watcher: shader_path
+                .as_ref()
+                .filter(|_| options.hot_reload)
+                .map(|_| ShaderWatcher::new(shader.map.files())),
+            #[cfg(not(target_arch = "wasm32"))]
+            include_dirs: options.include_dirs.clone(),
+            shader_error: None,
+            editor: create_editor(options, shader_path.as_deref()),
+            #[cfg(not(target_arch = "wasm32"))]
+            shader,
+            #[cfg(not(target_arch = "wasm32"))]
+            shader_path,
+            frame_stats: options
+                .stats
+                .then(|| FrameStats::new(gpu_timer.is_some(), options.profile)),
+            perf_graph: PerfGraph::new(options.target_ms, options.max_fps),
+            show_perf_graph: options.perf_graph,
+            gpu_timer,
+            frame_order,
+            last_frame: None,
+            time: 0.0,
+            frame: 0,
+            frame_time: FrameTime::default(),
+            timestep: FixedTimestep::default(),
+            sprites_uploaded: false,
+            paused: false,
+            single_step: false,
+            notice: None,
+            focused: true,
+            hidden_since: None,
+            background_animation: options.background_animation,
+            keys: KeyStates::default(),
+            shortcuts: Shortcuts::default(),
+            camera,
+            follow: options.follow.then_some(Follow {
+                damping: options.follow_damping,
+                dead_zone: options.dead_zone,
+            }),
+            gamepads: None,
+            #[cfg(all(feature = "audio", not(target_arch = "wasm32")))]
+            audio: None,
+            #[cfg(all(feature = "net", not(target_arch = "wasm32")))]
+            network: None,
+            params: [0.0; PARAM_COUNT],
+            seed: options.seed,
+            wireframe,
+            windows: HashMap::new(),
+        };
+        startup.step("create_state");
+        state.insert_window(window, surface)?;
+        startup.step("configure_surface");
+        Ok(state)
+    }
+
+    pub fn device_lost(&self) -> bool {
+        device_loss::is_lost(&self.device_lost)
+    }
+
+    // Start again on a new device after the old one was lost, rebuilding everything made from it
+    // but keeping the windows and where the game was
+    #[cfg(not(target_arch = "wasm32"))]
+    pub async fn recreate(&mut self, options: &Options) -> anyhow::Result<()> {
+        for window in self.windows.values_mut() {
+            window.release_cursor();
+        }
+        // The old surfaces go first, a window can't have two at once on some platforms
+        let mut windows = std::mem::take(&mut self.windows)
+            .into_values()
+            .map(|window| window.window);
+        let first = windows.next().context("No window left to render into")?;
+        let shader = self.shader.clone();
+        let mut state =
+            Self::with_device(first, options, &mut StartupProfile::off(), shader).await?;
+        for window in windows {
+            state.add_window(window)?;
+        }
+        state.world = std::mem::take(&mut self.world);
+        state.entities = std::mem::take(&mut self.entities);
+        state.camera = self.camera;
+        state.params = self.params;
+        state.seed = self.seed;
+        state.set_background(self.background);
+        state.wireframe = self.wireframe;
+        state.show_grid = self.show_grid;
+        state.time = self.time;
+        state.frame = self.frame;
+        state.paused = self.paused;
+        state.timestep = self.timestep;
+        state.dynamic_resolution = self.dynamic_resolution;
+        state.show_perf_graph = self.show_perf_graph;
+        // The shader being edited and where it's saved to, the watcher keeps watching its files
+        state.shader_path = self.shader_path.take();
+        state.shader_watcher = self.shader_watcher.take();
+        state.shader_error = self.shader_error.take();
+        state.editor = self.editor.take();
+        state.notice = self.notice.take();
+        state.keys = std::mem::take(&mut self.keys);
+        state.shortcuts = std::mem::take(&mut self.shortcuts);
+        state.follow = self.follow.take();
+        // Nothing of these is on the GPU, opening them again would stop the music playing
+        state.gamepads = self.gamepads.take();
+        #[cfg(all(feature = "audio", not(target_arch = "wasm32")))]
+        {
+            state.audio = self.audio.take();
+        }
+        // Its server and connection go on as they were, a second couldn't bind the same address
+        #[cfg(all(feature = "net", not(target_arch = "wasm32")))]
+        {
+            state.network = self.network.take();
+        }
+        *self = state;
+        log::info!("Recreated the GPU device");
+        Ok(())
+    }
+
+    // Render into another window with the same device and pipelines
+    pub fn add_window(&mut self, window: Window) -> anyhow::Result<()> {
+        let surface = unsafe { create_surface(&self.instance, &window) }?;
+        self.insert_window(window, surface)
+    }
+
+    fn insert_window(&mut self, window: Window, surface: wgpu::Surface) -> anyhow::Result<()> {
+        let capabilities = surface.get_capabilities(&self.adapter);
+        let format = self.pipeline_settings.format;
+        // The pipelines were built for the first window's format, so every window needs it
+        anyhow::ensure!(
+            capabilities.formats.contains(&format),
+            "The window's surface doesn't support {format:?}, which the pipelines were built for"
+        );
+        let size = window.inner_size();
+        let config = create_surface_config(
+            &capabilities,
+            format,
+            size,
+            self.present_mode,
+            self.transparent,
+        );
+        let minimized = size.width == 0 || size.height == 0;
+        if !minimized {
+            surface.configure(&self.device, &config);
+        }
+
+        let mouse = Mouse::default();
+        let mut uniforms = create_uniforms(
+            size,
+            window.scale_factor(),
+            self.frame_time,
+            mouse,
+            &self.camera,
+            self.params,
+            self.seed,
+        );
+        if self.pipeline_settings.reverse_z {
+            uniforms.reverse_depth();
+        }
+        uniforms.set_tint(self.tint);
+        // Reuse the slot of a closed window before growing the buffer, leaving the minimap's
+        let minimap_slot = self.minimap.as_ref().map(Minimap::slot);
+        let slot = (0..)
+            .find(|&slot| {
+                minimap_slot != Some(slot)
+                    && self.windows.values().all(|window| window.slot != slot)
+            })
+            .unwrap_or_default();
+        if slot >= self.uniform_slots.capacity() {
+            self.uniform_slots = UniformSlots::new(&self.device, (slot + 1).next_power_of_two());
+            self.rebuild_uniform_bind_groups();
+        }
+        let settings = &self.pipeline_settings;
+        let mut post = (!self.single_pass).then(|| {
+            PostChain::new(
+                &self.device,
+                format,
+                size.width.max(1),
+                size.height.max(1),
+                self.feedback,
+                self.scaling,
+                self.encode_srgb,
+            )
+        });
+        // Start at the scale the other windows have come to
+        if let (Some(post), Some(resolution)) = (&mut post, &self.dynamic_resolution) {
+            post.set_render_scale(resolution.scale());
+            post.resize(&self.device, size.width.max(1), size.height.max(1));
+        }
+        let feedback_bind_groups = self.create_feedback_bind_groups(post.as_ref());
+        let scene_targets = SizedCache::new(scene_size(post.as_ref(), size), |size| {
+            create_scene_targets(&self.device, settings, size)
+        });
+        let ui = Ui::new(&window, &self.device, format);
+
+        self.windows.insert(
+            window.id(),
+            WindowState {
+                window,
+                surface,
+                config,
+                slot,
+                uniforms,
+                scene_targets,
+                post,
+                feedback_bind_groups,
+                mouse,
+                ui,
+                windowed_size: None,
+                last_fullscreen: None,
+                pending_size: None,
+                minimized,
+                occluded: false,
+                outdated_frames: 0,
+                cursor_captured: false,
+                touches: Touches::default(),
+                #[cfg(not(target_arch = "wasm32"))]
+                screenshot_requested: false,
+            },
+        );
+        Ok(())
+    }
+
+    // Group 0 with the uniforms and input texture, `previous_frame` is what the scene samples as
+    // the frame before
+    fn create_uniform_bind_group(&self, previous_frame: &wgpu::TextureView) -> wgpu::BindGroup {
+        create_bind_group(
+            &self.device,
+            &self.bind_group_layout,
+            self.uniform_slots.buffer(),
+            &self.textures,
+            &self.sampler,
+            previous_frame,
+            &self.frame_sampler,
+        )
+    }
+
+    // Rebind group 0 everywhere it's bound, after the uniform buffer or a texture is replaced
+    fn rebuild_uniform_bind_groups(&mut self) {
+        self.bind_group = self.create_uniform_bind_group(&self.empty_frame);
+        let feedback_bind_groups: Vec<_> = self
+            .windows
+            .iter()
+            .map(|(&id, window)| (id, self.create_feedback_bind_groups(window.post.as_ref())))
+            .collect();
+        for (id, bind_groups) in feedback_bind_groups {
+            if let Some(window) = self.windows.get_mut(&id) {
+                window.feedback_bind_groups = bind_groups;
+            }
+        }
+    }
+
+    // See `WindowState::feedback_bind_groups`, None unless the chain keeps a previous frame
+    fn create_feedback_bind_groups(
+        &self,
+        post: Option<&PostChain>,
+    ) -> Option<[wgpu::BindGroup; 2]> {
+        let post = post?;
+        let previous = post.previous_view()?;
+        Some([previous, post.input_view()].map(|view| self.create_uniform_bind_group(view)))
+    }
+
+    // Drop a closed window along with its surface, the others keep running
+    pub fn remove_window(&mut self, id: WindowId) {
+        self.windows.remove(&id);
+    }
+
+    pub fn window(&self, id: WindowId) -> Option<&Window> {
+        self.windows.get(&id).map(WindowState::window)
+    }
+
+    pub fn windows(&self) -> impl Iterator<Item = &Window> {
+        self.windows.values().map(WindowState::window)
+    }
+
+    // Whether the window is worth drawing into, it isn't while minimized, covered or in the
+    // background
+    pub fn drawable(&self, id: WindowId) -> bool {
+        self.windows
+            .get(&id)
+            .is_some_and(|window| self.focused && !window.minimized && !window.occluded)
+    }
+
+    pub fn any_drawable(&self) -> bool {
+        self.windows.keys().any(|&id| self.drawable(id))
+    }
+
+    // Keep track of when drawing stopped and started again, call after anything `drawable`
+    // depends on changed with what `any_drawable` was before
+    fn visibility_changed(&mut self, was_drawable: bool) {
+        match (was_drawable, self.any_drawable()) {
+            (true, false) => {
+                log::info!("No window is visible, pausing rendering");
+                self.hidden_since = Some(instant::Instant::now());
+            }
+            (false, true) => {
+                log::info!("Resuming rendering");
+                let hidden_for = self.hidden_since.take().map(|since| since.elapsed());
+                if let Some(hidden_for) = hidden_for.filter(|_| self.background_animation) {
+                    if !self.paused {
+                        self.time += hidden_for.as_secs_f32();
+                    }
+                }
+                // The first frame back has no time step, like the very first frame
+                self.last_frame = None;
+                self.request_redraws();
+            }
+            _ => {}
+        }
+    }
+
+    pub fn request_redraw(&self, id: WindowId) {
+        if let Some(window) = self.window(id) {
+            window.request_redraw();
+        }
+    }
+
+    // Ask every window that can be drawn into for a new frame
+    pub fn request_redraws(&self) {
+        for (&id, window) in &self.windows {
+            if self.drawable(id) {
+                window.window.request_redraw();
+            }
+        }
+    }
+
+    // Resize before the window's next frame, replacing any size still waiting. Minimizing takes
+    // effect straight away so no frame is drawn to the zero sized window in between.
+    pub fn queue_resize(&mut self, id: WindowId, size: winit::dpi::PhysicalSize<u32>) {
+        let was_drawable = self.any_drawable();
+        if let Some(window) = self.windows.get_mut(&id) {
+            window.minimized = size.width == 0 || size.height == 0;
+            window.pending_size = Some(size);
+        }
+        self.visibility_changed(was_drawable);
+    }
+
+    // Reconfigure the surface and size dependent targets now
+    pub fn resize(&mut self, id: WindowId, size: winit::dpi::PhysicalSize<u32>) {
+        let Some(window) = self.windows.get_mut(&id) else {
+            return;
+        };
+        let post_rebuilt = window.resize(&self.device, &self.pipeline_settings, size);
+        // The feedback targets were recreated, so the bind groups reading them are too
+        if post_rebuilt && window.feedback_bind_groups.is_some() {
+            let bind_groups = self.create_feedback_bind_groups(self.windows[&id].post.as_ref());
+            if let Some(window) = self.windows.get_mut(&id) {
+                window.feedback_bind_groups = bind_groups;
+            }
+        }
+    }
+
+    // After a window's next frame couldn't be had because its surface is lost or outdated. That's
+    // usually fixed by reconfiguring it at the window's size, like after going fullscreen. One
+    // that stays that way, like after dragging the window to a monitor driven by the other GPU of
+    // a hybrid graphics laptop, is replaced by a new surface. Fails when the adapter can't present
+    // to that either, and only a new device will do.
+    pub fn recover_surface(&mut self, id: WindowId) -> anyhow::Result<()> {
+        let Some(window) = self.windows.get_mut(&id) else {
+            return Ok(());
+        };
+        let size = window.window.inner_size();
+        window.outdated_frames += 1;
+        if window.outdated_frames <= SURFACE_RETRIES {
+            self.resize(id, size);
+            return Ok(());
+        }
+        log::warn!(
+            "The surface was still outdated after {SURFACE_RETRIES} reconfigures, creating a \
+             new one"
+        );
+        window.outdated_frames = 0;
+        let surface = unsafe { create_surface(&self.instance, &window.window) }?;
+        anyhow::ensure!(
+            self.adapter.is_surface_supported(&surface),
+            "The adapter can't present to the window's new surface"
+        );
+        let capabilities = surface.get_capabilities(&self.adapter);
+        let format = self.pipeline_settings.format;
+        anyhow::ensure!(
+            capabilities.formats.contains(&format),
+            "The window's new surface doesn't support {format:?}, which the pipelines were built \
+             for"
+        );
+        window.config = create_surface_config(
+            &capabilities,
+            format,
+            size,
+            self.present_mode,
+            self.transparent,
+        );
+        // The old swapchain goes before the new one is configured, a window can't have two
+        drop(std::mem::replace(&mut window.surface, surface));
+        self.resize(id, size);
+        Ok(())
+    }
+
+    // Give the debug panel first look at an event, returns true if it captured it
+    pub fn ui_input(&mut self, id: WindowId, event: &WindowEvent) -> bool {
+        let captured = self
+            .windows
+            .get_mut(&id)
+            .is_some_and(|window| window.ui.input(&window.window, event));
+        // A button let go over the panel may have been pressed over the scene, so the release
+        // always gets through to `input` too
+        let release = matches!(
+            event,
+            WindowEvent::MouseInput {
+                state: ElementState::Released,
+                ..
+            }
+        );
+        captured && !release
+    }
+
+    // Handle input the renderer cares about, returns true if the event was used
+    pub fn input(&mut self, id: WindowId, event: &WindowEvent) -> bool {
+        let Some(window) = self.windows.get_mut(&id) else {
+            return false;
+        };
+        match event {
+            // The position stands still while captured, `mouse_motion` gets the movement
+            WindowEvent::CursorMoved { .. } if window.cursor_captured => true,
+            WindowEvent::CursorMoved { position, .. } => {
+                let size = window.window.inner_size();
+                window.mouse.position = [
+                    (position.x as f32).clamp(0.0, size.width as f32),
+                    (position.y as f32).clamp(0.0, size.height as f32),
+                ];
+                true
+            }
+            WindowEvent::MouseInput { state, button, .. } => {
+                let bit = match button {
+                    MouseButton::Left => MOUSE_LEFT,
+                    MouseButton::Right => MOUSE_RIGHT,
+                    MouseButton::Middle => MOUSE_MIDDLE,
+                    MouseButton::Other(_) => return false,
+                };
+                match state {
+                    ElementState::Pressed => {
+                        window.mouse.buttons |= bit;
+                        if bit == MOUSE_LEFT {
+                            window.mouse.click = window.mouse.position;
+                            #[cfg(not(target_arch = "wasm32"))]
+                            window.pick(&self.device, &self.queue, &self.entities);
+                        }
+                    }
+                    ElementState::Released => window.mouse.buttons &= !bit,
+                }
+                true
+            }
+            // The release would go to whichever window has focus next
+            WindowEvent::Focused(false) => {
+                window.mouse.buttons = 0;
+                self.keys.release_all();
+                let was_drawable = self.any_drawable();
+                self.focused = false;
+                self.visibility_changed(was_drawable);
+                true
+            }
+            // Moving focus between windows loses it in one before gaining it in the other
+            WindowEvent::Focused(true) => {
+                let was_drawable = self.any_drawable();
+                self.focused = true;
+                self.visibility_changed(was_drawable);
+                true
+            }
+            // The surface may have gone stale while hidden, so reconfigure it at the window's
+            // current size before drawing into it again
+            WindowEvent::Occluded(occluded) => {
+                let was_drawable = self.any_drawable();
+                if let Some(window) = self.windows.get_mut(&id) {
+                    window.occluded = *occluded;
+                    if !occluded {
+                        window.pending_size = Some(window.window.inner_size());
+                    }
+                }
+                self.visibility_changed(was_drawable);
+                true
+            }
+            WindowEvent::ModifiersChanged(modifiers) => {
+                self.shortcuts.set_modifiers(*modifiers);
+                true
+            }
+            WindowEvent::KeyboardInput {
+                input:
+                    KeyboardInput {
+                        state,
+                        virtual_keycode,
+                        scancode,
+                        ..
+                    },
+                ..
+            } => {
+                let chord = self.shortcuts.command_held();
+                // Movement goes by where the key is, shortcuts by what it types
+                self.keys.update_scancode(*scancode, *state, chord);
+                if let Some(key) = virtual_keycode {
+                    let edge = self.keys.update(*key, *state, chord);
+                    if let Some(action) = self.shortcuts.key(*key, edge) {
+                        self.perform(id, action);
+                    }
+                }
+                true
+            }
+            WindowEvent::MouseWheel { delta, .. } => {
+                // Trackpads report pixels rather than lines, treat 100 pixels as one line
+                let [x, y] = match delta {
+                    MouseScrollDelta::LineDelta(x, y) => [*x, *y],
+                    MouseScrollDelta::PixelDelta(position) => {
+                        [position.x as f32 / 100.0, position.y as f32 / 100.0]
+                    }
+                };
+                if self.paused && self.shortcuts.ctrl_held() {
+                    // Scrub the frozen clock instead of zooming, a step per line or more with
+                    // Shift
+                    self.time = scrub(self.time, [x, y], self.shortcuts.shift_held());
+                    window.window.request_redraw();
+                } else {
+                    // Zoom towards the cursor
+                    let anchor = window.screen_anchor(window.mouse.position);
+                    self.camera.scroll_at(y, anchor);
+                }
+                true
+            }
+            WindowEvent::Touch(touch) => {
+                let position = [touch.location.x as f32, touch.location.y as f32];
+                match window.touches.update(touch.id, touch.phase, position) {
+                    TouchAction::Press(position) => {
+                        window.mouse.position = position;
+                        window.mouse.click = position;
+                        window.mouse.buttons |= MOUSE_LEFT;
+                    }
+                    TouchAction::Move(position) => window.mouse.position = position,
+                    TouchAction::Release => window.mouse.buttons &= !MOUSE_LEFT,
+                    TouchAction::Pinch { scale, centre } => {
+                        let anchor = window.screen_anchor(centre);
+                        self.camera.scale_at(scale, anchor);
+                    }
+                    TouchAction::None => {}
+                }
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn perform(&mut self, id: WindowId, action: Action) {
+        let Some(window) = self.windows.get_mut(&id) else {
+            return;
+        };
+        match action {
+            Action::ToggleFullscreen => window.toggle_fullscreen(),
+            #[cfg(not(target_arch = "wasm32"))]
+            Action::Screenshot => {
+                window.screenshot_requested = true;
+                window.window.request_redraw();
+            }
+            #[cfg(not(target_arch = "wasm32"))]
+            Action::SaveWorld => save_world(&self.world, &self.save_path),
+            #[cfg(not(target_arch = "wasm32"))]
+            Action::ReloadShader => {
+                self.reload_shader();
+                self.request_redraws();
+            }
+            #[cfg(not(target_arch = "wasm32"))]
+            Action::SavePreset(slot) => self.save_preset(slot),
+            #[cfg(not(target_arch = "wasm32"))]
+            Action::LoadPreset(slot) => self.load_preset(slot),
+            // Nothing to write to or reload from in the browser
+            #[cfg(target_arch = "wasm32")]
+            Action::Screenshot
+            | Action::SaveWorld
+            | Action::ReloadShader
+            | Action::SavePreset(_)
+            | Action::LoadPreset(_) => {}
+            Action::TogglePause => {
+                self.paused = !self.paused;
+                log::info!("{}", if self.paused { "Paused" } else { "Resumed" });
+            }
+            // Holding the key repeats the step, which scrubs forward slowly
+            Action::StepFrame if self.paused => {
+                self.single_step = true;
+                window.window.request_redraw();
+            }
+            Action::StepFrame => {}
+            // Hashing rather than picking at random, so the seeds after one are always the same
+            Action::RerollSeed => {
+                self.seed = pcg_hash(self.seed);
+                log::info!("Seed {}", self.seed);
+                self.request_redraws();
+            }
+            Action::ToggleWireframe if !supports_wireframe(&self.device) => {
+                log::warn!("The device can't draw lines, there's no wireframe");
+            }
+            Action::ToggleGrid => {
+                self.show_grid = !self.show_grid;
+                self.request_redraws();
+            }
+            Action::TogglePerfGraph => {
+                self.show_perf_graph = !self.show_perf_graph;
+                self.request_redraws();
+            }
+            Action::ToggleWireframe => {
+                self.wireframe = !self.wireframe;
+                log::info!("Wireframe {}", if self.wireframe { "on" } else { "off" });
+                self.request_redraws();
+            }
+            Action::ToggleCursorCapture if window.cursor_captured => window.release_cursor(),
+            Action::ToggleCursorCapture => window.capture_cursor(),
+            Action::ReleaseCursor => window.release_cursor(),
+            #[cfg(all(feature = "audio", not(target_arch = "wasm32")))]
+            Action::PlayTestSound => {
+                if let Some(audio) = &mut self.audio {
+                    if let Err(error) = audio.play_sound("test") {
+                        log::error!("{error:#}");
+                    }
+                }
+            }
+        }
+    }
+
+    // Relative mouse movement from the device rather than a window, it only turns the camera
+    // while one of the windows has captured the cursor
+    pub fn mouse_motion(&mut self, delta: (f64, f64)) {
+        if self.windows.values().any(|window| window.cursor_captured) {
+            self.camera.look_by([delta.0 as f32, delta.1 as f32]);
+        }
+    }
+
+    // Catch up on controller events, never blocks so it's safe to call every loop iteration
+    pub fn poll_gamepads(&mut self) {
+        if let Some(gamepads) = &mut self.gamepads {
+            gamepads.poll();
+        }
+    }
+
+    // Rebuild the pipeline if the shader changed on disk, returns true if it was reloaded
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn reload_shader_if_changed(&mut self) -> bool {
+        if !self
+            .shader_watcher
+            .as_ref()
+            .is_some_and(ShaderWatcher::changed)
+        {
+            return false;
+        }
+        self.reload_shader();
+        true
+    }
+
+    // Rebuild the pipeline from the shader on disk, whether or not it's being watched
+    #[cfg(not(target_arch = "wasm32"))]
+    fn reload_shader(&mut self) {
+        let Some(path) = self.shader_path.clone() else {
+            log::warn!(
+                "The embedded shader can't be reloaded, pass --shader to load one from disk"
+            );
+            return;
+        };
+        let shader = load_shader_source(Some(&path), &self.include_dirs);
+        match self.rebuild_render_pipeline(shader) {
+            Ok(()) => log::info!("Reloaded {}", path.display()),
+            Err(error) => log_shader_error("reload", &path, &error),
+        }
+    }
+
+    // Compile the --editor panel's text in place of the shader's file, or save it there
+    fn edit_shader(&mut self, request: EditorRequest) {
+        match request {
+            #[cfg(not(target_arch = "wasm32"))]
+            EditorRequest::Apply => {
+                let Some(text) = self.editor.clone() else {
+                    return;
+                };
+                // The embedded shader's includes are found next to its source file
+                let path = self
+                    .shader_path
+                    .clone()
+                    .unwrap_or_else(|| SHADER_PATH.into());
+                let shader = preprocess::preprocess_text(&path, text, &self.include_dirs);
+                match self.rebuild_render_pipeline(shader) {
+                    Ok(()) => log::info!("Applied the edited shader"),
+                    Err(error) => log_shader_error("apply", &path, &error),
+                }
+                self.request_redraws();
+            }
+            #[cfg(not(target_arch = "wasm32"))]
+            EditorRequest::Save => {
+                let (Some(text), Some(path)) = (&self.editor, &self.shader_path) else {
+                    self.show_notice("Pass --shader to save the shader to a file".to_string());
+                    return;
+                };
+                let notice = match std::fs::write(path, text) {
+                    Ok(()) => {
+                        log::info!("Saved the shader to {}", path.display());
+                        "Saved the shader".to_string()
+                    }
+                    Err(error) => {
+                        log::error!("Failed to save the shader to {}: {error}", path.display());
+                        "Failed to save the shader".to_string()
+                    }
+                };
+                self.show_notice(notice);
+            }
+            // The editor's never shown in the browser
+            #[cfg(target_arch = "wasm32")]
+            EditorRequest::Apply | EditorRequest::Save => {}
+        }
+    }
+
+    // Build the scene's pipeline from `shader`, carrying on with the last one that compiled if it
+    // doesn't. The error is kept to show on screen as well as returned.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn rebuild_render_pipeline(
+        &mut self,
+        shader: anyhow::Result<ShaderSource>,
+    ) -> anyhow::Result<()> {
+        let result = shader.and_then(|shader| {
+            // An edit may have added or removed includes, which are all watched too
+            if let Some(watcher) = &mut self.shader_watcher {
+                watcher.watch(shader.map.files());
+            }
+            let pipeline = reload_render_pipeline(
+                &self.device,
+                &shader.text,
+                &[&self.bind_group_layout],
+                &[],
+                &self.pipeline_settings,
+            )
+            .map_err(|error| shader.map.annotate(error))?;
+            Ok((
+                pipeline,
+                uses_push_constants(&shader.text, &self.pipeline_settings),
+                shader,
+            ))
+        });
+        match result {
+            Ok((pipeline, push_uniforms, shader)) => {
+                self.render_pipeline = pipeline;
+                self.push_uniforms = push_uniforms;
+                self.shader = shader;
+                self.shader_error = None;
+                Ok(())
+            }
+            Err(error) => {
+                self.shader_error = Some(format!("{error:#}"));
+                Err(error)
+            }
+        }
+    }
+
+    // Load a file dropped on a window, a shader replaces the scene's and an image the first input
+    // texture
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn drop_file(&mut self, path: &Path) {
+        let name = path
+            .file_name()
+            .unwrap_or(path.as_os_str())
+            .to_string_lossy();
+        let extension = path
+            .extension()
+            .map(|extension| extension.to_string_lossy().to_lowercase());
+        match extension.as_deref() {
+            Some("wgsl") => {
+                self.shader_path = Some(path.to_path_buf());
+                self.reload_shader();
+                let notice = match self.shader_error {
+                    None => format!("Loaded {name}"),
+                    Some(_) => format!("{name} failed to compile"),
+                };
+                self.show_notice(notice);
+            }
+            Some("png" | "jpg" | "jpeg") => {
+                match texture::load_texture(&self.device, &self.queue, path, self.linear_texture) {
+                    Ok(texture) => {
+                        self.textures[0] =
+                            texture.create_view(&wgpu::TextureViewDescriptor::default());
+                        self.rebuild_uniform_bind_groups();
+                        log::info!("Bound {} as the input texture", path.display());
+                        self.show_notice(format!("Loaded {name}"));
+                    }
+                    Err(error) => {
+                        log::error!("{error:#}");
+                        self.show_notice(format!("Failed to load {name}"));
+                    }
+                }
+            }
+            _ => log::warn!(
+                "Ignoring dropped {}, expected a .wgsl shader or a .png or .jpeg image",
+                path.display()
+            ),
+        }
+        self.request_redraws();
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn save_preset(&mut self, slot: u8) {
+        let preset = Preset {
+            camera: self.camera,
+            params: self.params,
+            seed: self.seed,
+            clear_color: self.background.to_array(),
+        };
+        match preset::save(slot, &preset) {
+            Ok(path) => {
+                log::info!("Saved preset {slot} to {}", path.display());
+                self.show_notice(format!("Saved preset {slot}"));
+            }
+            Err(error) => {
+                log::error!("{error:#}");
+                self.show_notice(format!("Failed to save preset {slot}"));
+            }
+        }
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn load_preset(&mut self, slot: u8) {
+        match preset::load(slot) {
+            Ok(preset) => {
+                self.camera = preset.camera;
+                self.params = preset.params;
+                self.seed = preset.seed;
+                self.set_background(Color::from_array(preset.clear_color));
+                log::info!("Loaded preset {slot}");
+                self.show_notice(format!("Loaded preset {slot}"));
+            }
+            Err(error) => {
+                log::error!("{error:#}");
+                self.show_notice(format!("No preset {slot}, Ctrl+Shift+{slot} saves one"));
+            }
+        }
+        self.request_redraws();
+    }
+
+    // A transparent window stays cleared to nothing whatever it's given
+    #[cfg(not(target_arch = "wasm32"))]
+    fn set_background(&mut self, color: Color) {
+        self.background = color;
+        if !self.transparent {
+            self.clear_color = color.for_target(self.linear_clear);
+        }
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn show_notice(&mut self, text: String) {
+        self.notice = Some((text, instant::Instant::now()));
+    }
+
+    // Seconds since the last frame, capped so stalls don't cause a huge jump. Zero on the first
+    // frame since there is no previous frame to measure from.
+    pub fn frame_dt(&mut self) -> f32 {
+        let now = instant::Instant::now();
+        let dt = self
+            .last_frame
+            .map_or(0.0, |last| (now - last).as_secs_f32().min(MAX_DT));
+        if let (Some(frame_stats), Some(last)) = (&mut self.frame_stats, self.last_frame) {
+            frame_stats.record((now - last).as_secs_f32());
+        }
+        if let Some(last) = self.last_frame {
+            self.perf_graph.record((now - last).as_secs_f32());
+        }
+        if let (Some(resolution), Some(last), None) = (
+            &mut self.dynamic_resolution,
+            self.last_frame,
+            &self.gpu_timer,
+        ) {
+            resolution.record((now - last).as_secs_f32());
+        }
+        self.last_frame = Some(now);
+        dt
+    }
+
+    // Draw each window's scene at the scale --target-ms has settled on
+    fn apply_render_scale(&mut self) {
+        let Some(scale) = self
+            .dynamic_resolution
+            .as_ref()
+            .map(DynamicResolution::scale)
+        else {
+            return;
+        };
+        if let Some(frame_stats) = &mut self.frame_stats {
+            frame_stats.set_render_scale(scale);
+        }
+        let changed: Vec<_> = self
+            .windows
+            .iter_mut()
+            .filter_map(|(&id, window)| {
+                let post = window.post.as_mut()?;
+                post.set_render_scale(scale)
+                    .then(|| (id, window.window.inner_size()))
+            })
+            .collect();
+        for (id, size) in changed {
+            self.resize(id, size);
+        }
+    }
+
+    // How far drawing is between the last two logic steps, see `render`
+    pub fn alpha(&self) -> f32 {
+        self.timestep.alpha()
+    }
+
+    // Advance the clocks by `dt` seconds and run the logic steps due in them, once per frame
+    // however many windows get drawn
+    pub fn update(&mut self, dt: f32) {
+        let (stick, zoom) = self
+            .gamepads
+            .as_ref()
+            .map_or(([0.0, 0.0], 0.0), |gamepads| {
+                (gamepads.movement(), gamepads.zoom())
+            });
+        self.camera.zoom_by(zoom, dt);
+        if let Some(follow) = &self.follow {
+            let travel = self.camera.travel(&self.keys, stick, dt);
+            let player = &mut self.world.player;
+            *player = [player[0] + travel[0], player[1] + travel[1]];
+            follow.update(&mut self.camera, *player, dt);
+        } else {
+            self.camera.update(&self.keys, stick, dt);
+            self.world.player = self.camera.centre();
+        }
+
+        // The camera above keeps moving while paused so a frozen moment can be looked around
+        let shader_dt = if !self.paused {
+            dt
+        } else if std::mem::take(&mut self.single_step) {
+            STEP_DT
+        } else {
+            0.0
+        };
+        self.frame_time = FrameTime {
+            time: self.time,
+            dt: shader_dt,
+            frame: self.frame,
+        };
+        if !self.paused || shader_dt > 0.0 {
+            self.time += shader_dt;
+            self.frame = self.frame.wrapping_add(1);
+        }
+        for _ in 0..self.timestep.advance(shader_dt) {
+            self.entities.begin_step();
+            #[cfg(all(feature = "net", not(target_arch = "wasm32")))]
+            if let Some(network) = &mut self.network {
+                network.step(&mut self.entities, self.world.player);
+            }
+            ecs::system_movement(&mut self.entities, LOGIC_DT);
+        }
+        self.sprites_uploaded = false;
+        self.minimap_due = self.minimap.as_mut().is_some_and(Minimap::due);
+        self.apply_render_scale();
+        if let Some(simulation) = &mut self.simulation {
+            simulation.advance(shader_dt);
+        }
+        if let Some(particles) = &mut self.particles {
+            particles.advance();
+        }
+        if self.paused {
+            let text = format!("Paused at {:.3} s", self.time);
+            self.hud.draw_text(&text, 16.0, 16.0, 32.0);
+        }
+        if let Some((text, shown)) = &self.notice {
+            if shown.elapsed() < NOTICE_DURATION {
+                let y = if self.paused { 56.0 } else { 16.0 };
+                self.hud.draw_text(text, 16.0, y, 24.0);
+            } else {
+                self.notice = None;
+            }
+        }
+        self.hud.upload(&self.device, &self.queue);
+    }
+
+    // Draw the scene, through the window's post-processing chain if it has one, into `view`
+    #[cfg(not(target_arch = "wasm32"))]
+    fn encode_frame(
+        &self,
+        window: &WindowState,
+        encoder: &mut wgpu::CommandEncoder,
+        view: &wgpu::TextureView,
+    ) {
+        for &pass in &self.frame_order {
+            if matches!(pass, Pass::Scene | Pass::Post | Pass::Hud | Pass::Minimap) {
+                self.encode_pass(window, encoder, view, pass);
+            }
+        }
+    }
+
+    // One of the passes drawing the frame into `view`, the others don't come through here
+    fn encode_pass(
+        &self,
+        window: &WindowState,
+        encoder: &mut wgpu::CommandEncoder,
+        view: &wgpu::TextureView,
+        pass: Pass,
+    ) {
+        let bind_group = window
+            .feedback_bind_groups
+            .as_ref()
+            .map_or(&self.bind_group, |bind_groups| &bind_groups[0]);
+        let uniform_offset = self.uniform_slots.offset(window.slot);
+        match pass {
+            Pass::Scene => self.encode_scene(window, encoder, view, bind_group),
+            Pass::Post => {
+                if let Some(post) = &window.post {
+                    post.encode(encoder, view);
+                }
+            }
+            // After post-processing so the effects don't blur the text
+            Pass::Hud => self.hud.encode(
+                encoder,
+                view,
+                bind_group,
+                uniform_offset,
+                window.post.as_ref().and_then(PostChain::viewport),
+            ),
+            // On top of the HUD, the texture's from the last time it was drawn
+            Pass::Minimap => {
+                if let Some(minimap) = &self.minimap {
+                    minimap.composite(encoder, view, (window.config.width, window.config.height));
+                }
+            }
+            Pass::Compute | Pass::Particles | Pass::Screenshot | Pass::Ui => {}
+        }
+    }
+
+    fn encode_scene(
+        &self,
+        window: &WindowState,
+        encoder: &mut wgpu::CommandEncoder,
+        view: &wgpu::TextureView,
+        bind_group: &wgpu::BindGroup,
+    ) {
+        let scene_view = window.post.as_ref().map_or(view, PostChain::input_view);
+        let uniform_offset = self.uniform_slots.offset(window.slot);
+        let targets = window.scene_targets.get();
+        let mut rpass = begin_render_pass(
+            encoder,
+            scene_view,
+            targets.msaa_view.as_ref(),
+            &targets.depth_view,
+            targets.id_texture.as_ref().map(|(_, view)| view),
+            self.clear_color,
+            depth_clear(self.pipeline_settings.reverse_z),
+        );
+        draw_scene(
+            &mut rpass,
+            &self.render_pipeline,
+            bind_group,
+            uniform_offset,
+            self.push_uniforms.then_some(&window.uniforms),
+        );
+        if let Some(simulation) = &self.simulation {
+            simulation.draw(&mut rpass, bind_group, uniform_offset);
+        }
+        if let Some(world_renderer) = &self.world_renderer {
+            world_renderer.draw(&mut rpass, bind_group, uniform_offset, self.wireframe);
+        }
+        if let Some(particles) = &self.particles {
+            particles.draw(&mut rpass, bind_group, uniform_offset);
+        }
+        if self.show_grid {
+            self.grid.draw(&mut rpass, bind_group, uniform_offset);
+        }
+    }
+
+    // The minimap looks at the world around the player through its own camera
+    fn write_minimap_uniforms(&self, minimap: &Minimap) {
+        let size = minimap.size();
+        let mut uniforms = create_uniforms(
+            winit::dpi::PhysicalSize::new(size, size),
+            1.0,
+            self.frame_time,
+            Mouse::default(),
+            &minimap.camera(self.world.player),
+            self.params,
+            self.seed,
+        );
+        if self.pipeline_settings.reverse_z {
+            uniforms.reverse_depth();
+        }
+        self.uniform_slots
+            .write(&self.queue, minimap.slot(), &uniforms);
+    }
+
+    // Draw the tiles and the entities' dots into the minimap's texture
+    fn encode_minimap(&self, encoder: &mut wgpu::CommandEncoder) {
+        let (Some(minimap), Some(world_renderer)) = (&self.minimap, &self.world_renderer) else {
+            return;
+        };
+        let mut rpass = minimap.begin_pass(
+            encoder,
+            self.clear_color,
+            depth_clear(self.pipeline_settings.reverse_z),
+        );
+        world_renderer.draw_minimap(
+            &mut rpass,
+            &self.bind_group,
+            self.uniform_slots.offset(minimap.slot()),
+        );
+    }
+
+    // Draw a window with the entities `alpha` of the way from the previous logic step to the
+    // latest one, so their movement is smooth whatever the frame rate
+    pub fn render(&mut self, id: WindowId, alpha: f32) -> Result<(), wgpu::SurfaceError> {
+        // The first window drawn after an update lays out the sprites for all of them
+        if let Some(world_renderer) = &mut self.world_renderer {
+            if !std::mem::replace(&mut self.sprites_uploaded, true) {
+                world_renderer.update(
+                    &self.device,
+                    &self.queue,
+                    &self.entities,
+                    self.world.player,
+                    alpha,
+                );
+            }
+        }
+        if let Some(size) = self
+            .windows
+            .get_mut(&id)
+            .and_then(|window| window.pending_size.take())
+        {
+            self.resize(id, size);
+        }
+        let Some(window) = self.windows.get_mut(&id) else {
+            return Ok(());
+        };
+        let request = window.ui.update(
+            &window.window,
+            &mut self.params,
+            self.shader_error.as_deref(),
+            self.editor.as_mut(),
+            self.show_perf_graph.then_some(&self.perf_graph),
+        );
+        if let Some(request) = request {
+            self.edit_shader(request);
+        }
+        let Some(window) = self.windows.get_mut(&id) else {
+            return Ok(());
+        };
+        let (width, height) = scene_size(window.post.as_ref(), window.window.inner_size());
+        window.uniforms = create_uniforms(
+            winit::dpi::PhysicalSize::new(width, height),
+            window.window.scale_factor(),
+            self.frame_time,
+            window.scene_mouse(),
+            &self.camera,
+            self.params,
+            self.seed,
+        );
+        if self.pipeline_settings.reverse_z {
+            window.uniforms.reverse_depth();
+        }
+        window.uniforms.set_tint(self.tint);
+        // Push constants are set while encoding the pass instead, the overlays always read the
+        // buffer
+        if !self.push_uniforms
+            || self.world_renderer.is_some()
+            || self.simulation.is_some()
+            || self.particles.is_some()
+            || self.show_grid
+            || !self.hud.is_empty()
+        {
+            self.uniform_slots
+                .write(&self.queue, window.slot, &window.uniforms);
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        let screenshot_requested = std::mem::take(&mut window.screenshot_requested);
+
+        if let Some(gpu_time) = self
+            .gpu_timer
+            .as_mut()
+            .and_then(|gpu_timer| gpu_timer.read(&self.device))
+        {
+            if let Some(frame_stats) = &mut self.frame_stats {
+                frame_stats.record_gpu_time(&gpu_time);
+            }
+            if let Some(resolution) = &mut self.dynamic_resolution {
+                resolution.record(gpu_time.frame);
+            }
+        }
+
+        let frame = window.surface.get_current_texture()?;
+        window.outdated_frames = 0;
+        // Waiting for the swapchain above isn't counted as CPU time
+        let cpu_start = instant::Instant::now();
+        let view = frame
+            .texture
+            .create_view(&wgpu::TextureViewDescriptor::default());
+
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+        if let Some(gpu_timer) = &mut self.gpu_timer {
+            gpu_timer.begin(&mut encoder);
+        }
+        // One window redraws it for all of them
+        let minimap_due = std::mem::take(&mut self.minimap_due);
+        if let (Some(minimap), true) = (&self.minimap, minimap_due) {
+            self.write_minimap_uniforms(minimap);
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        let mut screenshot_texture = None;
+        let mut ui_command_buffers = Vec::new();
+        for index in 0..self.frame_order.len() {
+            let pass = self.frame_order[index];
+            let window = &self.windows[&id];
+            match pass {
+                // Stepped here rather than in `encode_pass` so screenshots don't advance it
+                // twice, and only the first window drawn after an update runs the step
+                Pass::Compute => {
+                    if let Some(simulation) = &mut self.simulation {
+                        simulation.step(&mut encoder);
+                    }
+                }
+                Pass::Particles => {
+                    if let Some(particles) = &mut self.particles {
+                        let uniform_offset = self.uniform_slots.offset(window.slot);
+                        particles.step(&mut encoder, &self.bind_group, uniform_offset);
+                    }
+                }
+                Pass::Scene | Pass::Post | Pass::Hud => {
+                    self.encode_pass(window, &mut encoder, &view, pass);
+                }
+                Pass::Minimap => {
+                    if minimap_due {
+                        self.encode_minimap(&mut encoder);
+                    }
+                    self.encode_pass(window, &mut encoder, &view, pass);
+                }
+                // Render the same frame a second time into a texture that can be copied out
+                Pass::Screenshot =>
+                {
+                    #[cfg(not(target_arch = "wasm32"))]
+                    if screenshot_requested {
+                        let texture = capture::create_capture_texture(
+                            &self.device,
+                            self.pipeline_settings.format,
+                            window.config.width,
+                            window.config.height,
+                        );
+                        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+                        self.encode_frame(window, &mut encoder, &view);
+                        screenshot_texture = Some(texture);
+                    }
+                }
+                // The panel goes on the window only, screenshots show just the shader
+                Pass::Ui => {
+                    let Some(window) = self.windows.get_mut(&id) else {
+                        return Ok(());
+                    };
+                    ui_command_buffers = window.ui.render(
+                        &self.device,
+                        &self.queue,
+                        &mut encoder,
+                        &view,
+                        [window.config.width, window.config.height],
+                    );
+                }
+            }
+            // The last pass is ended with the frame
+            if let (Some(gpu_timer), false) = (&self.gpu_timer, pass == Pass::Ui) {
+                gpu_timer.end_pass(&mut encoder, pass);
+            }
+        }
+        let Some(window) = self.windows.get_mut(&id) else {
+            return Ok(());
+        };
+        window.swap_feedback();
+
+        if let Some(gpu_timer) = &mut self.gpu_timer {
+            gpu_timer.end(&mut encoder);
+        }
+        self.queue
+            .submit(ui_command_buffers.into_iter().chain(Some(encoder.finish())));
+        if let Some(gpu_timer) = &mut self.gpu_timer {
+            gpu_timer.after_submit();
+        }
+        if let Some(frame_stats) = &mut self.frame_stats {
+            frame_stats.record_cpu_time(cpu_start.elapsed().as_secs_f32());
+        }
+        frame.present();
+
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(texture) = screenshot_texture {
+            save_screenshot(&self.device, &self.queue, &texture);
+        }
+        Ok(())
+    }
+}
+
+// The helper functions used to create and setup the components
+
+// What the frame's passes read and write, from the simulation's cells through to the surface.
+// Each of these is owned by whatever draws it, only the order comes from the graph.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum FrameResource {
+    Cells,
+    Sparks,
+    Scene,
+    // The surface, after each pass that draws into it
+    Composited,
+    WithHud,
+    WithMinimap,
+    WithUi,
+    Capture,
+}
+
+// The frame as a render graph. A new pass only has to say what it reads and writes to find its
+// place. `Pass::ALL` is in the same order, which is what --profile times them by, and a test
+// keeps the two together.
+fn create_frame_graph() -> Vec<Node<Pass, FrameResource>> {
+    use FrameResource::*;
+    vec![
+        Node::new(Pass::Compute, [], [Cells]),
+        Node::new(Pass::Particles, [], [Sparks]),
+        Node::new(Pass::Scene, [Cells, Sparks], [Scene]),
+        Node::new(Pass::Post, [Scene], [Composited]),
+        Node::new(Pass::Hud, [Composited], [WithHud]),
+        Node::new(Pass::Minimap, [WithHud], [WithMinimap]),
+        // Drawn again from the start rather than copied, so it leaves out the UI
+        Node::new(Pass::Screenshot, [Cells, Sparks], [Capture]),
+        Node::new(Pass::Ui, [WithMinimap], [WithUi]),
+    ]
+}
+
+fn schedule_frame() -> anyhow::Result<Vec<Pass>> {
+    use FrameResource::*;
+    let external = [
+        Cells,
+        Sparks,
+        Scene,
+        Composited,
+        WithHud,
+        WithMinimap,
+        WithUi,
+        Capture,
+    ];
+    Ok(graph::schedule(&create_frame_graph(), &external)?.order)
+}
+
+// What the scene is drawn at, the post chain's size for it or else the window's `size`
+pub fn scene_size(post: Option<&PostChain>, size: winit::dpi::PhysicalSize<u32>) -> (u32, u32) {
+    post.map_or((size.width, size.height), PostChain::scene_size)
+}
+
+// Start the scene pass, with MSAA it draws into the multisampled view and resolves into `view`.
+// `id_view` is the second target for --picking.
+pub fn begin_render_pass<'a>(
+    encoder: &'a mut wgpu::CommandEncoder,
+    view: &'a wgpu::TextureView,
+    msaa_view: Option<&'a wgpu::TextureView>,
+    depth_view: &'a wgpu::TextureView,
+    id_view: Option<&'a wgpu::TextureView>,
+    clear_color: wgpu::Color,
+    depth_clear: f32,
+) -> wgpu::RenderPass<'a> {
+    let (view, resolve_target) = match msaa_view {
+        Some(msaa_view) => (msaa_view, Some(view)),
+        None => (view, None),
+    };
+    // Trailing empty attachments trip up some GL drivers, so the id target is left out entirely
+    // without --picking
+    let mut color_attachments = vec![Some(wgpu::RenderPassColorAttachment {
+        view,
+        resolve_target,
+        ops: wgpu::Operations {
+            load: wgpu::LoadOp::Clear(clear_color),
+            store: true,
+        },
+    })];
+    color_attachments.extend(id_view.map(|view| {
+        Some(wgpu::RenderPassColorAttachment {
+            view,
+            resolve_target: None,
+            ops: wgpu::Operations {
+                load: wgpu::LoadOp::Clear(wgpu::Color {
+                    r: f64::from(picking::NO_ID),
+                    ..wgpu::Color::TRANSPARENT
+                }),
+                store: true,
+            },
+        })
+    }));
+    encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+        label: None,
+        color_attachments: &color_attachments,
+        depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+            view: depth_view,
+            depth_ops: Some(wgpu::Operations {
+                load: wgpu::LoadOp::Clear(depth_clear),
+                store: true,
+            }),
+            stencil_ops: None,
+        }),
+    })
+}
+
+// Draw the fullscreen shader, pass `push_uniforms` when the pipeline reads its uniforms from
+// push constants. `uniform_offset` picks the copy of the uniforms in the bind group's buffer.
+pub fn draw_scene<'a>(
+    rpass: &mut wgpu::RenderPass<'a>,
+    render_pipeline: &'a wgpu::RenderPipeline,
+    bind_group: &'a wgpu::BindGroup,
+    uniform_offset: u32,
+    push_uniforms: Option<&Uniforms>,
+) {
+    rpass.set_pipeline(render_pipeline);
+    rpass.set_bind_group(0, bind_group, &[uniform_offset]);
+    if let Some(uniforms) = push_uniforms {
+        rpass.set_push_constants(
+            wgpu::ShaderStages::FRAGMENT,
+            0,
+            bytemuck::bytes_of(uniforms),
+        );
+    }
+    rpass.draw(0..6, 0..1);
+}
+
+// Why a shader failed to `action`, in the path:line:column form editors and terminals can jump to,
+// the path being whichever included file the mistake is in
+#[cfg(not(target_arch = "wasm32"))]
+fn log_shader_error(action: &str, path: &Path, error: &anyhow::Error) {
+    match (
+        error.downcast_ref::<SourceLocation>(),
+        error.downcast_ref::<ShaderError>(),
+    ) {
+        (Some(location), Some(ShaderError { message, .. })) => {
+            log::error!("Failed to {action} {location}: {message}")
+        }
+        _ => log::error!("Failed to {action} {}: {error:#}", path.display()),
+    }
+}
+
+// Quick save to the file the world was loaded from, or save.ron next to where it was run
+#[cfg(not(target_arch = "wasm32"))]
+fn save_world(world: &World, path: &Path) {
+    match save::save(world, path) {
+        Ok(()) => log::info!("Saved the world to {}", path.display()),
+        Err(error) => log::error!("{error:#}"),
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn save_screenshot(device: &wgpu::Device, queue: &wgpu::Queue, texture: &wgpu::Texture) {
+    let path = capture::screenshot_path();
+    let result =
+        capture::read_texture(device, queue, texture).and_then(|image| Ok(image.save(&path)?));
+    match result {
+        Ok(()) => log::info!("Saved screenshot to {}", path.display()),
+        Err(error) => log::error!("Failed to save screenshot: {error:#}"),
+    }
+}
+
+// Only the given backends are tried when picking an adapter
+pub fn create_instance(backends: wgpu::Backends) -> wgpu::Instance {
+    wgpu::Instance::new(wgpu::InstanceDescriptor {
+        backends,
+        ..Default::default()
+    })
+}
+
+unsafe fn create_surface(
+    instance: &wgpu::Instance,
+    window: &Window,
+) -> anyhow::Result<wgpu::Surface> {
+    instance
+        .create_surface(window)
+        .context("Failed to create a surface for the window")
+}
+
+// Nothing to render on, not even in software. Tests skip on it rather than fail.
+#[derive(Debug)]
+pub struct NoAdapter(String);
+
+impl std::fmt::Display for NoAdapter {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for NoAdapter {}
+
+// Pass no surface to get a device for offscreen rendering
+pub async fn create_device_queue(
+    instance: &wgpu::Instance,
+    surface: Option<&wgpu::Surface>,
+    options: &Options,
+) -> anyhow::Result<(wgpu::Adapter, wgpu::Device, wgpu::Queue)> {
+    // Browsers don't allow enumerating adapters so the choice only applies natively
+    let adapter = match options.adapter.as_deref() {
+        #[cfg(not(target_arch = "wasm32"))]
+        Some(choice) => select_adapter(instance, surface, choice),
+        _ => request_adapter(instance, surface, false).await,
+    };
+    // Machines without a usable GPU, like most CI boxes, may still have a software rasteriser
+    let adapter = match adapter {
+        Some(adapter) => adapter,
+        None => {
+            log::warn!("No suitable GPU adapter found, trying the software fallback");
+            request_adapter(instance, surface, true)
+                .await
+                .ok_or_else(|| NoAdapter(no_adapter_message(instance)))?
+        }
+    };
+    let info = adapter.get_info();
+    log::info!(
+        "Using adapter {} on the {:?} backend",
+        info.name,
+        info.backend
+    );
+    log::debug!("Adapter details {info:?}");
+
+    // Push constants are optional, the uniform buffer is used when they're missing or too small.
+    // GL emulates them with plain uniforms that can't hold the u32 fields, so it's left out too.
+    let push_constant_size = std::mem::size_of::<Uniforms>() as u32;
+    let push_constants = adapter.features().contains(wgpu::Features::PUSH_CONSTANTS)
+        && adapter.limits().max_push_constant_size >= push_constant_size
+        && info.backend != wgpu::Backend::Gl;
+    let (mut features, max_push_constant_size) = if push_constants {
+        log::info!("Passing uniforms as push constants");
+        (wgpu::Features::PUSH_CONSTANTS, push_constant_size)
+    } else {
+        (wgpu::Features::empty(), 0)
+    };
+    // Lets --stats measure GPU frame time, it falls back to CPU timing without it
+    features |= adapter.features() & wgpu::Features::TIMESTAMP_QUERY;
+    // Binds every --texture as an array, only the first can be sampled without it
+    features |= adapter.features() & wgpu::Features::TEXTURE_BINDING_ARRAY;
+    // Draws the tile map and sprites as lines with --wireframe
+    features |= adapter.features() & wgpu::Features::POLYGON_MODE_LINE;
+    // Clamps depth rather than clipping it with --reverse-z
+    features |= adapter.features() & wgpu::Features::DEPTH_CLIP_CONTROL;
+    // WebGL2's limits have no storage buffers, so ask for more where --compute can use them
+    let base_limits = if compute::supports_compute(&adapter) {
+        wgpu::Limits::downlevel_defaults()
+    } else {
+        wgpu::Limits::downlevel_webgl2_defaults()
+    };
+    // The baseline keeps to what every platform can run, `--limits max` takes all the adapter has
+    let limits = if options.max_limits {
+        adapter.limits()
+    } else {
+        wgpu::Limits {
+            max_push_constant_size,
+            ..base_limits.clone().using_resolution(adapter.limits())
+        }
+    };
+    if options.print_limits {
+        limits::print_limits(&adapter.limits(), &base_limits, &limits);
+    }
+
+    // Create the logical device and command queue
+    let (device, queue) = adapter
+        .request_device(
+            &wgpu::DeviceDescriptor {
+                label: None,
+                features,
+                limits,
+            },
+            None,
+        )
+        .await
+        .context("Failed to create the logical device")?;
+
+    // Anything not caught by an error scope would otherwise abort the whole process
+    device.on_uncaptured_error(Box::new(|error| {
+        log::error!("Uncaptured wgpu error: {error}");
+    }));
+
+    Ok((adapter, device, queue))
+}
+
+async fn request_adapter(
+    instance: &wgpu::Instance,
+    surface: Option<&wgpu::Surface>,
+    force_fallback_adapter: bool,
+) -> Option<wgpu::Adapter> {
+    instance
+        .request_adapter(&wgpu::RequestAdapterOptions {
+            power_preference: wgpu::PowerPreference::HighPerformance,
+            compatible_surface: surface,
+            force_fallback_adapter,
+        })
+        .await
+}
+
+// Explain why no adapter could be used, listing whatever the backends did report
+#[cfg(not(target_arch = "wasm32"))]
+fn no_adapter_message(instance: &wgpu::Instance) -> String {
+    let adapters: Vec<String> = instance
+        .enumerate_adapters(wgpu::Backends::all())
+        .map(|adapter| {
+            let info = adapter.get_info();
+            format!(
+                "  {} ({:?}, {:?})",
+                info.name, info.backend, info.device_type
+            )
+        })
+        .collect();
+    if adapters.is_empty() {
+        "No graphics adapters were found on the enabled backends. Check --backends includes \
+         the one your driver provides, or install Mesa's lavapipe/llvmpipe for software rendering."
+            .to_string()
+    } else {
+        format!(
+            "None of the available adapters can be used:\n{}\n\
+             Pick one with --adapter <index or name>, or update the graphics driver.",
+            adapters.join("\n")
+        )
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+fn no_adapter_message(_instance: &wgpu::Instance) -> String {
+    "No WebGPU adapter available, check the browser supports and has enabled WebGPU".to_string()
+}
+
+// List every adapter able to present to the surface and pick one by index or by name
+#[cfg(not(target_arch = "wasm32"))]
+fn select_adapter(
+    instance: &wgpu::Instance,
+    surface: Option<&wgpu::Surface>,
+    choice: &str,
+) -> Option<wgpu::Adapter> {
+    let adapters: Vec<wgpu::Adapter> = instance
+        .enumerate_adapters(wgpu::Backends::all())
+        .filter(|adapter| surface.is_none_or(|surface| adapter.is_surface_supported(surface)))
+        .collect();
+    for (index, adapter) in adapters.iter().enumerate() {
+        let info = adapter.get_info();
+        log::info!("Adapter {index}: {} ({:?})", info.name, info.backend);
+    }
+
+    let choice_lowercase = choice.to_lowercase();
+    let position = match choice.parse::<usize>() {
+        Ok(index) => (index < adapters.len()).then_some(index),
+        Err(_) => adapters.iter().position(|adapter| {
+            adapter
+                .get_info()
+                .name
+                .to_lowercase()
+                .contains(&choice_lowercase)
+        }),
+    };
+    match position {
+        Some(index) => adapters.into_iter().nth(index),
+        None => {
+            log::error!("No adapter matches {choice:?}");
+            None
+        }
+    }
+}
+
+pub fn supports_push_constants(device: &wgpu::Device) -> bool {
+    device.features().contains(wgpu::Features::PUSH_CONSTANTS)
+}
+
+// Shaders that declare their uniforms like shader.wgsl can be moved onto push constants
+pub fn uses_push_constants(source: &str, settings: &PipelineSettings) -> bool {
+    settings.push_constants && source.contains(UNIFORM_DECLARATION)
+}
+
+fn create_shader(device: &wgpu::Device, source: &str) -> wgpu::ShaderModule {
+    device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: None,
+        source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(source)),
+    })
+}
+
+// A shader given with --shader, or the embedded shader's source file when hot reloading
+pub fn shader_path(options: &Options) -> Option<PathBuf> {
+    options
+        .shader
+        .clone()
+        .or_else(|| options.hot_reload.then(|| SHADER_PATH.into()))
+}
+
+// Read the shader and the files it includes from disk if a path is given, otherwise use the copy
+// embedded at build time
+pub fn load_shader_source(
+    path: Option<&Path>,
+    include_dirs: &[PathBuf],
+) -> anyhow::Result<ShaderSource> {
+    match path {
+        Some(path) => preprocess::preprocess(path, include_dirs),
+        None => Ok(ShaderSource::embedded(include_str!("shader.wgsl"))),
+    }
+}
+
+// The world saved with --load, or the default one
+pub fn load_world(options: &Options) -> anyhow::Result<World> {
+    // Browsers have no files to load from
+    match &options.load {
+        #[cfg(not(target_arch = "wasm32"))]
+        Some(path) => save::load(path),
+        _ => Ok(World::default()),
+    }
+}
+
+// The text the --editor panel starts with, the shader's own file without what it includes
+fn create_editor(options: &Options, shader_path: Option<&Path>) -> Option<String> {
+    if !options.editor {
+        return None;
+    }
+    if cfg!(target_arch = "wasm32") {
+        log::warn!("--editor compiles shaders the way hot reloading does, which browsers can't");
+        return None;
+    }
+    match shader_path {
+        Some(path) => match std::fs::read_to_string(path) {
+            Ok(text) => Some(text),
+            Err(error) => {
+                log::warn!("Failed to read {} to edit: {error}", path.display());
+                None
+            }
+        },
+        None => Some(include_str!("shader.wgsl").to_string()),
+    }
+}
+
+// Loading a save implies wanting to see it
+pub fn draws_world(options: &Options) -> bool {
+    options.tilemap || options.load.is_some()
+}
+
+// The --target-ms scaling, which needs the post chain to scale the scene up and has nothing to do
+// when the scene's size is fixed
+fn create_dynamic_resolution(options: &Options) -> Option<DynamicResolution> {
+    let target_ms = options.target_ms?;
+    if options.single_pass {
+        log::warn!("--target-ms needs the post-processing pass, ignoring it with --single-pass");
+        return None;
+    }
+    if options.internal_res.is_some() {
+        log::warn!("--internal-res fixes the scene's size, ignoring --target-ms");
+        return None;
+    }
+    Some(DynamicResolution::new(target_ms))
+}
+
+// How the post chain sizes the scene
+pub fn create_scaling(options: &Options) -> Scaling {
+    if options.single_pass {
+        if options.ssaa > 1 {
+            log::warn!("--ssaa needs the post-processing pass, ignoring it with --single-pass");
+        }
+        return Scaling::Output;
+    }
+    match (options.internal_res, options.ssaa) {
+        (Some(size), ssaa) => {
+            if ssaa > 1 {
+                log::warn!("--internal-res fixes the scene's size, ignoring --ssaa");
+            }
+            Scaling::Internal(size)
+        }
+        (None, 1) => Scaling::Output,
+        (None, factor) => Scaling::Supersample(factor),
+    }
+}
+
+// The --compute simulation, None without the flag or when the adapter can't run it
+pub async fn create_simulation(
+    adapter: &wgpu::Adapter,
+    device: &wgpu::Device,
+    bind_group_layout: &wgpu::BindGroupLayout,
+    settings: &PipelineSettings,
+    options: &Options,
+) -> anyhow::Result<Option<Simulation>> {
+    if !options.compute {
+        return Ok(None);
+    }
+    if !compute::supports_compute(adapter) {
+        log::warn!("Compute shaders aren't supported by this adapter, ignoring --compute");
+        return Ok(None);
+    }
+    Ok(Some(
+        Simulation::new(device, bind_group_layout, settings).await?,
+    ))
+}
+
+// The --particles system, None without the flag or when the adapter can't run compute shaders
+pub async fn create_particles(
+    adapter: &wgpu::Adapter,
+    device: &wgpu::Device,
+    bind_group_layout: &wgpu::BindGroupLayout,
+    settings: &PipelineSettings,
+    options: &Options,
+) -> anyhow::Result<Option<Particles>> {
+    if !options.particles {
+        return Ok(None);
+    }
+    if !compute::supports_compute(adapter) {
+        log::warn!("Compute shaders aren't supported by this adapter, ignoring --particles");
+        return Ok(None);
+    }
+    Ok(Some(
+        Particles::new(device, bind_group_layout, settings).await?,
+    ))
+}
+
+// The --texture images, each at its own size. With texture arrays there are always
+// MAX_TEXTURES, the slots past the given images are white like the one bound without any.
+// Without them only the first image can be bound.
+pub fn load_input_textures(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    options: &Options,
+) -> anyhow::Result<Vec<wgpu::TextureView>> {
+    let count = if supports_texture_arrays(device) {
+        MAX_TEXTURES
+    } else {
+        if options.textures.len() > 1 {
+            log::warn!(
+                "The device can't bind texture arrays, only the first of the {} textures is used",
+                options.textures.len()
+            );
+        }
+        1
+    };
+    (0..count)
+        .map(|index| {
+            let texture = match options.textures.get(index) {
+                Some(path) => texture::load_texture(device, queue, path, options.linear_texture)?,
+                None => texture::create_placeholder_texture(device, queue),
+            };
+            Ok(texture.create_view(&wgpu::TextureViewDescriptor::default()))
+        })
+        .collect()
+}
+
+// Rebuild the render pipeline from freshly read shader source, returning the error if it
+// fails to compile
+#[cfg(not(target_arch = "wasm32"))]
+fn reload_render_pipeline(
+    device: &wgpu::Device,
+    source: &str,
+    bind_group_layouts: &[&wgpu::BindGroupLayout],
+    buffers: &[wgpu::VertexBufferLayout],
+    settings: &PipelineSettings,
+) -> anyhow::Result<wgpu::RenderPipeline> {
+    pollster::block_on(create_validated_render_pipeline(
+        device,
+        source,
+        bind_group_layouts,
+        buffers,
+        settings,
+    ))
+}
+
+// Check the shader with naga, then compile it and build its pipeline inside an error scope, so
+// WGSL and validation errors come back as a message instead of reaching the uncaptured error
+// handler. `buffers` describes the vertex inputs, the fullscreen shader has none.
+pub async fn create_validated_render_pipeline(
+    device: &wgpu::Device,
+    source: &str,
+    bind_group_layouts: &[&wgpu::BindGroupLayout],
+    buffers: &[wgpu::VertexBufferLayout<'_>],
+    settings: &PipelineSettings,
+) -> anyhow::Result<wgpu::RenderPipeline> {
+    // The layout only gets a push constant range when the shader reads from it, as some
+    // backends fail when a range is declared that the shader never uses
+    let push_constants = uses_push_constants(source, settings);
+    let source = if push_constants {
+        Cow::Owned(source.replace(UNIFORM_DECLARATION, PUSH_CONSTANT_DECLARATION))
+    } else {
+        Cow::Borrowed(source)
+    };
+    // Fails with the line and column before a broken shader gets near the device
+    shader_check::validate_wgsl(&source)?;
+    device.push_error_scope(wgpu::ErrorFilter::Validation);
+    let shader = create_shader(device, &source);
+    let pipeline_layout = create_pipeline_layout(device, bind_group_layouts, push_constants);
+    let render_pipeline =
+        create_render_pipeline(device, &shader, &pipeline_layout, buffers, settings);
+    match device.pop_error_scope().await {
+        // wgpu errors aren't Sync, so only the message can be carried along
+        Some(error) => Err(anyhow::anyhow!("{error}")),
+        None => Ok(render_pipeline),
+    }
+}
+
+// A pipeline with a twin that draws only the edges of its triangles, for looking at the geometry
+// with --wireframe. There's no twin when the device can't draw lines.
+pub struct PipelineWithWireframe {
+    fill: wgpu::RenderPipeline,
+    wireframe: Option<wgpu::RenderPipeline>,
+}
+
+impl PipelineWithWireframe {
+    pub fn get(&self, wireframe: bool) -> &wgpu::RenderPipeline {
+        self.wireframe
+            .as_ref()
+            .filter(|_| wireframe)
+            .unwrap_or(&self.fill)
+    }
+}
+
+// Like `create_validated_render_pipeline`, with the wireframe twin where the device has lines
+pub async fn create_pipeline_with_wireframe(
+    device: &wgpu::Device,
+    source: &str,
+    bind_group_layouts: &[&wgpu::BindGroupLayout],
+    buffers: &[wgpu::VertexBufferLayout<'_>],
+    settings: &PipelineSettings,
+) -> anyhow::Result<PipelineWithWireframe> {
+    let fill =
+        create_validated_render_pipeline(device, source, bind_group_layouts, buffers, settings)
+            .await?;
+    let wireframe = if supports_wireframe(device) {
+        let settings = PipelineSettings {
+            polygon_mode: wgpu::PolygonMode::Line,
+            ..*settings
+        };
+        Some(
+            create_validated_render_pipeline(
+                device,
+                source,
+                bind_group_layouts,
+                buffers,
+                &settings,
+            )
+            .await?,
+        )
+    } else {
+        None
+    };
+    Ok(PipelineWithWireframe { fill, wireframe })
+}
+
+pub fn supports_wireframe(device: &wgpu::Device) -> bool {
+    device
+        .features()
+        .contains(wgpu::Features::POLYGON_MODE_LINE)
+}
+
+// Whether to start with --wireframe, warning when the device can't
+pub fn starts_wireframe(device: &wgpu::Device, options: &Options) -> bool {
+    if options.wireframe && !supports_wireframe(device) {
+        log::warn!("The device can't draw lines, ignoring --wireframe");
+        return false;
+    }
+    options.wireframe
+}
+
+pub fn create_uniforms(
+    size: winit::dpi::PhysicalSize<u32>,
+    scale_factor: f64,
+    frame_time: FrameTime,
+    mouse: Mouse,
+    camera: &Camera,
+    params: [f32; PARAM_COUNT],
+    seed: u32,
+) -> Uniforms {
+    Uniforms {
+        resolution: [size.width as f32, size.height as f32],
+        mouse: mouse.position,
+        time: frame_time.time,
+        dt: frame_time.dt,
+        dpi: scale_factor as f32,
+        zoom: camera.zoom,
+        offset: camera.offset,
+        frame: frame_time.frame,
+        aspect: size.width as f32 / size.height.max(1) as f32,
+        params,
+        look: camera.look,
+        click: mouse.click,
+        mouse_buttons: mouse.buttons,
+        seed,
+        padding: [0; 2],
+        view_projection: camera.to_2d().to_matrix(),
+        inverse_view_projection: camera.to_2d().to_inverse_matrix(),
+        tint: [1.0; 4],
+    }
+}
+
+// Where scrolling by `[x, y]` lines moves the paused clock to from `time`, forward for scrolling
+// up. Windows and macOS often turn the wheel sideways while Shift is held, so with no vertical
+// scroll the horizontal counts instead. It stops at 0, where the shader's clock starts.
+fn scrub(time: f32, [x, y]: [f32; 2], fast: bool) -> f32 {
+    let lines = if y == 0.0 { x } else { y };
+    let steps = if fast { FAST_SCRUB_STEPS } else { 1.0 };
+    (time + lines * steps * STEP_DT).max(0.0)
+}
+
+// The same hash as `pcg_hash` in shader.wgsl
+fn pcg_hash(value: u32) -> u32 {
+    let state = value.wrapping_mul(747796405).wrapping_add(2891336453);
+    let word = ((state >> ((state >> 28) + 4)) ^ state).wrapping_mul(277803737);
+    (word >> 22) ^ word
+}
+
+pub fn create_bind_group_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
+    let mut entries = vec![
+        // The tile map's vertex shader reads the camera from here too, and the particles' compute
+        // pass the cursor
+        wgpu::BindGroupLayoutEntry {
+            binding: 0,
+            visibility: wgpu::ShaderStages::VERTEX_FRAGMENT.union(wgpu::ShaderStages::COMPUTE),
+            // Offset per draw into a buffer of several copies, see UniformSlots
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Uniform,
+                has_dynamic_offset: true,
+                min_binding_size: wgpu::BufferSize::new(std::mem::size_of::<Uniforms>() as u64),
+            },
+            count: None,
+        },
+        wgpu::BindGroupLayoutEntry {
+            binding: 1,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Texture {
+                sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                view_dimension: wgpu::TextureViewDimension::D2,
+                multisampled: false,
+            },
+            count: None,
+        },
+        wgpu::BindGroupLayoutEntry {
+            binding: 2,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+            count: None,
+        },
+        // The frame drawn before this one, for trails and other feedback effects
+        wgpu::BindGroupLayoutEntry {
+            binding: 3,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Texture {
+                sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                view_dimension: wgpu::TextureViewDimension::D2,
+                multisampled: false,
+            },
+            count: None,
+        },
+        wgpu::BindGroupLayoutEntry {
+            binding: 4,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+            count: None,
+        },
+    ];
+    // Every --texture at its own size, sampled with the input sampler
+    if supports_texture_arrays(device) {
+        entries.push(wgpu::BindGroupLayoutEntry {
+            binding: 5,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Texture {
+                sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                view_dimension: wgpu::TextureViewDimension::D2,
+                multisampled: false,
+            },
+            count: std::num::NonZeroU32::new(MAX_TEXTURES as u32),
+        });
+    }
+    device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        label: Some("uniform_bind_group_layout"),
+        entries: &entries,
+    })
+}
+
+// Whether the device can bind arrays of textures, WebGPU, WebGL and GL can't
+pub fn supports_texture_arrays(device: &wgpu::Device) -> bool {
+    device
+        .features()
+        .contains(wgpu::Features::TEXTURE_BINDING_ARRAY)
+}
+
+// Binds one copy of the uniforms at a time, the dynamic offset says which. `textures` are from
+// `load_input_textures`, the first is bound alone and all of them as the array if there is one.
+pub fn create_bind_group(
+    device: &wgpu::Device,
+    bind_group_layout: &wgpu::BindGroupLayout,
+    uniform_buffer: &wgpu::Buffer,
+    textures: &[wgpu::TextureView],
+    sampler: &wgpu::Sampler,
+    previous_frame: &wgpu::TextureView,
+    frame_sampler: &wgpu::Sampler,
+) -> wgpu::BindGroup {
+    let texture_array: Vec<&wgpu::TextureView> = textures.iter().collect();
+    let mut entries = vec![
+        wgpu::BindGroupEntry {
+            binding: 0,
+            resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
+                buffer: uniform_buffer,
+                offset: 0,
+                size: wgpu::BufferSize::new(std::mem::size_of::<Uniforms>() as u64),
+            }),
+        },
+        wgpu::BindGroupEntry {
+            binding: 1,
+            resource: wgpu::BindingResource::TextureView(&textures[0]),
+        },
+        wgpu::BindGroupEntry {
+            binding: 2,
+            resource: wgpu::BindingResource::Sampler(sampler),
+        },
+        wgpu::BindGroupEntry {
+            binding: 3,
+            resource: wgpu::BindingResource::TextureView(previous_frame),
+        },
+        wgpu::BindGroupEntry {
+            binding: 4,
+            resource: wgpu::BindingResource::Sampler(frame_sampler),
+        },
+    ];
+    if supports_texture_arrays(device) {
+        entries.push(wgpu::BindGroupEntry {
+            binding: 5,
+            resource: wgpu::BindingResource::TextureViewArray(&texture_array),
+        });
+    }
+    device.create_bind_group(&wgpu::BindGroupDescriptor {
+        layout: bind_group_layout,
+        entries: &entries,
+        label: Some("uniform_bind_group"),
+    })
+}
+
+fn create_pipeline_layout(
+    device: &wgpu::Device,
+    bind_group_layouts: &[&wgpu::BindGroupLayout],
+    push_constants: bool,
+) -> wgpu::PipelineLayout {
+    let push_constant_range = wgpu::PushConstantRange {
+        stages: wgpu::ShaderStages::FRAGMENT,
+        range: 0..std::mem::size_of::<Uniforms>() as u32,
+    };
+    device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+        label: None,
+        bind_group_layouts,
+        push_constant_ranges: if push_constants {
+            std::slice::from_ref(&push_constant_range)
+        } else {
+            &[]
+        },
+    })
+}
+
+// Surface formats --hdr looks for, most range first. A float surface keeps values past 1 for the
+// display to show brighter than white, where the platform presents it as extended range like
+// DX12's scRGB does. wgpu doesn't expose the surface's colour space, so elsewhere it's clamped
+// but still avoids banding. The 10-bit one adds precision only, and isn't sRGB so the last post
+// pass encodes for it.
+const HDR_FORMATS: [wgpu::TextureFormat; 2] = [
+    wgpu::TextureFormat::Rgba16Float,
+    wgpu::TextureFormat::Rgb10a2Unorm,
+];
+
+// Whether the last post pass has to encode the scene's linear colour itself for `format`
+fn encodes_in_shader(format: wgpu::TextureFormat) -> bool {
+    format == wgpu::TextureFormat::Rgb10a2Unorm
+}
+
+// Surface formats tried in turn without --hdr, for the colour space asked for and then the other
+// one. Past these any format the surface can be rendered into will do.
+const SRGB_FORMATS: [wgpu::TextureFormat; 2] = [
+    wgpu::TextureFormat::Bgra8UnormSrgb,
+    wgpu::TextureFormat::Rgba8UnormSrgb,
+];
+const LINEAR_FORMATS: [wgpu::TextureFormat; 2] = [
+    wgpu::TextureFormat::Bgra8Unorm,
+    wgpu::TextureFormat::Rgba8Unorm,
+];
+
+// The first of the preferred formats the surface offers and `renderable` accepts, then any other
+// it offers. Some GL drivers list formats first that a pipeline can't draw into.
+fn choose_surface_format(
+    offered: &[wgpu::TextureFormat],
+    srgb: bool,
+    renderable: impl Fn(wgpu::TextureFormat) -> bool,
+) -> Option<wgpu::TextureFormat> {
+    let (preferred, other) = if srgb {
+        (SRGB_FORMATS, LINEAR_FORMATS)
+    } else {
+        (LINEAR_FORMATS, SRGB_FORMATS)
+    };
+    preferred
+        .into_iter()
+        .chain(other)
+        .filter(|format| offered.contains(format))
+        .chain(offered.iter().copied())
+        .find(|&format| renderable(format))
+}
+
+// With --hdr the first HDR format the surface has, otherwise or if it has none an 8-bit format in
+// the requested colour space. That's sRGB by default so the same shader looks the same
+// everywhere instead of depending on which format the platform happens to list first. Returns
+// whether the format is an HDR one, and fails when there's nothing the surface can be drawn with.
+fn get_swapchain_format(
+    surface: &wgpu::Surface,
+    adapter: &wgpu::Adapter,
+    options: &Options,
+) -> anyhow::Result<(wgpu::TextureFormat, bool)> {
+    let swapchain_capabilities = surface.get_capabilities(adapter);
+    let renderable = |format| {
+        adapter
+            .get_texture_format_features(format)
+            .allowed_usages
+            .contains(wgpu::TextureUsages::RENDER_ATTACHMENT)
+    };
+    if options.hdr {
+        // Without the post-processing pass nothing would encode for the 10-bit format
+        let hdr_format = HDR_FORMATS
+            .into_iter()
+            .filter(|&format| !(options.single_pass && encodes_in_shader(format)))
+            .find(|&format| swapchain_capabilities.formats.contains(&format) && renderable(format));
+        match hdr_format {
+            Some(format) => {
+                log::info!("Using HDR surface format {format:?}");
+                return Ok((format, true));
+            }
+            None => log::warn!(
+                "The surface has no HDR format, falling back to SDR. It offers {:?}",
+                swapchain_capabilities.formats
+            ),
+        }
+    }
+    let color_space = options.color_space;
+    let srgb = color_space.unwrap_or(ColorSpace::Srgb) == ColorSpace::Srgb;
+    let swapchain_format = choose_surface_format(&swapchain_capabilities.formats, srgb, renderable)
+        .with_context(|| {
+            format!(
+                "The surface offers no format that can be rendered to, only {:?}",
+                swapchain_capabilities.formats
+            )
+        })?;
+    if color_space.is_some() && swapchain_format.is_srgb() != srgb {
+        log::warn!("The surface has no {color_space:?} format, using {swapchain_format:?}");
+    }
+    log::info!("Using surface format {swapchain_format:?}");
+    Ok((swapchain_format, false))
+}
+
+// Windows can only be see-through when the surface composites with premultiplied alpha, which
+// depends on the platform and whether a compositor is running
+fn supports_transparency(surface: &wgpu::Surface, adapter: &wgpu::Adapter) -> bool {
+    let supported = surface
+        .get_capabilities(adapter)
+        .alpha_modes
+        .contains(&wgpu::CompositeAlphaMode::PreMultiplied);
+    if !supported {
+        log::warn!("The surface can't composite with premultiplied alpha, the window stays opaque");
+    }
+    supported
+}
+
+// Fall back to no MSAA when the adapter can't multisample and resolve the format
+pub fn validate_sample_count(
+    adapter: &wgpu::Adapter,
+    format: wgpu::TextureFormat,
+    requested: u32,
+) -> u32 {
+    let flags = adapter.get_texture_format_features(format).flags;
+    if requested <= 1
+        || (flags.sample_count_supported(requested)
+            && flags.contains(wgpu::TextureFormatFeatureFlags::MULTISAMPLE_RESOLVE))
+    {
+        return requested.max(1);
+    }
+    log::warn!("{requested}x MSAA is not supported for {format:?}, disabling it");
+    1
+}
+
+// Where the scene is drawn before it's resolved into the frame or post-processed
+pub struct SceneTargets {
+    // Multisampled colour target that gets resolved into the frame, None without MSAA
+    pub msaa_view: Option<wgpu::TextureView>,
+    pub depth_view: wgpu::TextureView,
+    // The entity ids with --picking, read back on a click
+    pub id_texture: Option<(wgpu::Texture, wgpu::TextureView)>,
+}
+
+pub fn create_scene_targets(
+    device: &wgpu::Device,
+    settings: &PipelineSettings,
+    (width, height): (u32, u32),
+) -> SceneTargets {
+    SceneTargets {
+        msaa_view: create_msaa_view(device, settings, width, height),
+        depth_view: create_depth_texture(device, settings, width, height),
+        id_texture: (settings.pick_target != PickTarget::None).then(|| {
+            let texture = picking::create_id_texture(device, (width, height));
+            let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+            (texture, view)
+        }),
+    }
+}
+
+pub fn create_msaa_view(
+    device: &wgpu::Device,
+    settings: &PipelineSettings,
+    width: u32,
+    height: u32,
+) -> Option<wgpu::TextureView> {
+    if settings.sample_count <= 1 || width == 0 || height == 0 {
+        return None;
+    }
+    let texture = device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("msaa_texture"),
+        size: wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count: settings.sample_count,
+        dimension: wgpu::TextureDimension::D2,
+        format: settings.format,
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+        view_formats: &[],
+    });
+    Some(texture.create_view(&wgpu::TextureViewDescriptor::default()))
+}
+
+// Depth target matching the frame size and sample count, returns the view the pass attaches
+pub fn create_depth_texture(
+    device: &wgpu::Device,
+    settings: &PipelineSettings,
+    width: u32,
+    height: u32,
+) -> wgpu::TextureView {
+    let texture = device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("depth_texture"),
+        size: wgpu::Extent3d {
+            width: width.max(1),
+            height: height.max(1),
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count: settings.sample_count,
+        dimension: wgpu::TextureDimension::D2,
+        format: DEPTH_FORMAT,
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+        view_formats: &[],
+    });
+    texture.create_view(&wgpu::TextureViewDescriptor::default())
+}
+
+fn create_render_pipeline(
+    device: &wgpu::Device,
+    shader: &wgpu::ShaderModule,
+    pipeline_layout: &wgpu::PipelineLayout,
+    buffers: &[wgpu::VertexBufferLayout],
+    settings: &PipelineSettings,
+) -> wgpu::RenderPipeline {
+    // The id target for --picking goes second, only where the pass has one, see
+    // `begin_render_pass`
+    let mut targets = vec![Some(wgpu::ColorTargetState {
+        format: settings.format,
+        blend: settings.blend,
+        write_mask: wgpu::ColorWrites::ALL,
+    })];
+    targets.extend(settings.pick_target.target().map(Some));
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: None,
+        layout: Some(pipeline_layout),
+        vertex: wgpu::VertexState {
+            module: shader,
+            entry_point: "vs_main",
+            buffers,
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: shader,
+            entry_point: "fs_main",
+            targets: &targets,
+        }),
+        primitive: wgpu::PrimitiveState {
+            polygon_mode: settings.polygon_mode,
+            // The far plane is at 0 with --reverse-z, where rounding just past it would clip
+            // geometry away, so it's clamped onto the plane instead where the device allows
+            unclipped_depth: settings.reverse_z
+                && device
+                    .features()
+                    .contains(wgpu::Features::DEPTH_CLIP_CONTROL),
+            ..Default::default()
+        },
+        depth_stencil: settings.depth_test.then_some(wgpu::DepthStencilState {
+            format: DEPTH_FORMAT,
+            depth_write_enabled: true,
+            // Equal depths pass so later draws in the pass land on top of the fullscreen shader
+            depth_compare: if settings.reverse_z {
+                wgpu::CompareFunction::GreaterEqual
+            } else {
+                wgpu::CompareFunction::LessEqual
+            },
+            stencil: wgpu::StencilState::default(),
+            bias: wgpu::DepthBiasState::default(),
+        }),
+        multisample: wgpu::MultisampleState {
+            count: settings.sample_count,
+            ..Default::default()
+        },
+        multiview: None,
+    })
+}
+
+fn create_surface_config(
+    swapchain_capabilities: &wgpu::SurfaceCapabilities,
+    swapchain_format: wgpu::TextureFormat,
+    size: winit::dpi::PhysicalSize<u32>,
+    present_mode: Option<wgpu::PresentMode>,
+    transparent: bool,
+) -> wgpu::SurfaceConfiguration {
+    // Fifo is the only mode every surface is guaranteed to support
+    let present_mode = match present_mode {
+        Some(mode) if swapchain_capabilities.present_modes.contains(&mode) => mode,
+        Some(mode) => {
+            log::warn!("Present mode {mode:?} is not supported, falling back to Fifo");
+            wgpu::PresentMode::Fifo
+        }
+        None => wgpu::PresentMode::Fifo,
+    };
+    wgpu::SurfaceConfiguration {
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+        format: swapchain_format,
+        width: size.width,
+        height: size.height,
+        present_mode,
+        alpha_mode: if transparent
+            && swapchain_capabilities
+                .alpha_modes
+                .contains(&wgpu::CompositeAlphaMode::PreMultiplied)
+        {
+            wgpu::CompositeAlphaMode::PreMultiplied
+        } else {
+            swapchain_capabilities.alpha_modes[0]
+        },
+        view_formats: vec![],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn surface_formats_fall_back_to_ones_that_render() {
+        use wgpu::TextureFormat::*;
+        let all = |_| true;
+        // The preferred one over whatever's listed first
+        let offered = [Rgb10a2Unorm, Rgba8Unorm, Rgba8UnormSrgb];
+        assert_eq!(
+            choose_surface_format(&offered, true, all),
+            Some(Rgba8UnormSrgb)
+        );
+        assert_eq!(
+            choose_surface_format(&offered, false, all),
+            Some(Rgba8Unorm)
+        );
+        // Linear 8-bit when sRGB can't be drawn into, then anything that can
+        let srgb_broken = |format: wgpu::TextureFormat| !format.is_srgb();
+        assert_eq!(
+            choose_surface_format(&offered, true, srgb_broken),
+            Some(Rgba8Unorm)
+        );
+        let only_10_bit = |format| format == Rgb10a2Unorm;
+        assert_eq!(
+            choose_surface_format(&offered, true, only_10_bit),
+            Some(Rgb10a2Unorm)
+        );
+        assert_eq!(choose_surface_format(&offered, true, |_| false), None);
+        assert_eq!(choose_surface_format(&[], true, all), None);
+    }
+
+    #[test]
+    fn the_frame_runs_in_the_order_it_is_timed() {
+        assert_eq!(schedule_frame().unwrap(), Pass::ALL);
+    }
+
+    #[test]
+    fn scrubbing_moves_the_clock_by_steps_and_stops_at_zero() {
+        assert_eq!(scrub(1.0, [0.0, 3.0], false), 1.0 + 3.0 * STEP_DT);
+        assert_eq!(
+            scrub(1.0, [0.0, -1.0], true),
+            1.0 - FAST_SCRUB_STEPS * STEP_DT
+        );
+        assert_eq!(scrub(0.05, [0.0, -10.0], false), 0.0);
+        // Shift+wheel arriving sideways still scrubs
+        assert_eq!(
+            scrub(1.0, [-1.0, 0.0], true),
+            1.0 - FAST_SCRUB_STEPS * STEP_DT
+        );
+    }
+
+    // Where each field is in bytes, in the order they're declared
+    fn uniform_offsets() -> Vec<(String, usize)> {
+        use std::mem::offset_of;
+        [
+            ("resolution", offset_of!(Uniforms, resolution)),
+            ("mouse", offset_of!(Uniforms, mouse)),
+            ("time", offset_of!(Uniforms, time)),
+            ("dt", offset_of!(Uniforms, dt)),
+            ("dpi", offset_of!(Uniforms, dpi)),
+            ("zoom", offset_of!(Uniforms, zoom)),
+            ("offset", offset_of!(Uniforms, offset)),
+            ("frame", offset_of!(Uniforms, frame)),
+            ("aspect", offset_of!(Uniforms, aspect)),
+            ("params", offset_of!(Uniforms, params)),
+            ("look", offset_of!(Uniforms, look)),
+            ("click", offset_of!(Uniforms, click)),
+            ("mouse_buttons", offset_of!(Uniforms, mouse_buttons)),
+            ("seed", offset_of!(Uniforms, seed)),
+            ("padding", offset_of!(Uniforms, padding)),
+            ("view_projection", offset_of!(Uniforms, view_projection)),
+            (
+                "inverse_view_projection",
+                offset_of!(Uniforms, inverse_view_projection),
+            ),
+            ("tint", offset_of!(Uniforms, tint)),
+        ]
+        .map(|(name, offset)| (name.to_string(), offset))
+        .into()
+    }
+
+    // Where naga lays out each field of the `Uniforms` struct in `source`, and its size
+    fn wgsl_uniform_layout(source: &str) -> (Vec<(String, usize)>, usize) {
+        let module = naga::front::wgsl::parse_str(source).unwrap();
+        let layout = module
+            .types
+            .iter()
+            .find_map(|(_, ty)| match &ty.inner {
+                naga::TypeInner::Struct { members, span }
+                    if ty.name.as_deref() == Some("Uniforms") =>
+                {
+                    let fields = members
+                        .iter()
+                        .map(|member| {
+                            (
+                                member.name.clone().unwrap_or_default(),
+                                member.offset as usize,
+                            )
+                        })
+                        .collect();
+                    Some((fields, *span as usize))
+                }
+                _ => None,
+            })
+            .expect("No Uniforms struct in the shader");
+        layout
+    }
+
+    #[test]
+    fn uniforms_are_laid_out_like_the_wgsl() {
+        let offsets = uniform_offsets();
+        let (fields, size) = wgsl_uniform_layout(include_str!("shader.wgsl"));
+        assert_eq!(fields, offsets);
+        assert_eq!(size, std::mem::size_of::<Uniforms>());
+        // The other shaders' copies can stop short, but what they have has to line up
+        for (name, source) in [
+            ("cells", include_str!("cells.wgsl")),
+            ("grid", include_str!("grid.wgsl")),
+            ("particles", include_str!("particles.wgsl")),
+            ("particles_step", include_str!("particles_step.wgsl")),
+            ("sprite", include_str!("sprite.wgsl")),
+            ("text", include_str!("text.wgsl")),
+            ("tilemap", include_str!("tilemap.wgsl")),
+        ] {
+            let (fields, _) = wgsl_uniform_layout(source);
+            assert_eq!(fields, offsets[..fields.len()], "{name}.wgsl");
+        }
+    }
+}
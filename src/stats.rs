@@ -0,0 +1,149 @@
+// Ring buffer of recent frame times, summarised to the log once a second
+use crate::gpu_timer::{GpuTime, Pass};
+
+pub struct FrameStats {
+    samples: Vec<f32>,
+    next: usize,
+    frames_since_report: usize,
+    last_report: instant::Instant,
+    // Time spent building and submitting frames since the last report
+    cpu_time: Average,
+    // GPU time from timestamp queries, None when the device can't measure it
+    gpu_time: Option<Average>,
+    // Each pass's share of it with --profile, in the order of `Pass::ALL`
+    pass_times: Option<[Average; Pass::ALL.len()]>,
+    // What --target-ms scales the scene's resolution by, None without it
+    render_scale: Option<f32>,
+}
+
+// Running total for averaging the samples between reports
+#[derive(Default)]
+struct Average {
+    total: f32,
+    count: usize,
+}
+
+impl Average {
+    fn add(&mut self, sample: f32) {
+        self.total += sample;
+        self.count += 1;
+    }
+
+    // Average in milliseconds, resetting for the next report
+    fn take_ms(&mut self) -> f32 {
+        let average = self.total / self.count.max(1) as f32 * 1000.0;
+        *self = Self::default();
+        average
+    }
+}
+
+impl FrameStats {
+    const CAPACITY: usize = 1024;
+
+    pub fn new(gpu_timing: bool, profile: bool) -> Self {
+        Self {
+            samples: Vec::with_capacity(Self::CAPACITY),
+            next: 0,
+            frames_since_report: 0,
+            last_report: instant::Instant::now(),
+            cpu_time: Average::default(),
+            gpu_time: gpu_timing.then(Average::default),
+            pass_times: (gpu_timing && profile).then(Default::default),
+            render_scale: None,
+        }
+    }
+
+    pub fn set_render_scale(&mut self, scale: f32) {
+        self.render_scale = Some(scale);
+    }
+
+    pub fn record_cpu_time(&mut self, cpu_time: f32) {
+        self.cpu_time.add(cpu_time);
+    }
+
+    pub fn record_gpu_time(&mut self, gpu_time: &GpuTime) {
+        if let Some(average) = &mut self.gpu_time {
+            average.add(gpu_time.frame);
+        }
+        if let (Some(averages), Some(passes)) = (&mut self.pass_times, &gpu_time.passes) {
+            for (average, &time) in averages.iter_mut().zip(passes) {
+                average.add(time);
+            }
+        }
+    }
+
+    pub fn record(&mut self, frame_time: f32) {
+        if self.samples.len() < Self::CAPACITY {
+            self.samples.push(frame_time);
+        } else {
+            self.samples[self.next] = frame_time;
+        }
+        self.next = (self.next + 1) % Self::CAPACITY;
+        self.frames_since_report += 1;
+
+        if self.last_report.elapsed() >= std::time::Duration::from_secs(1) {
+            self.report();
+            self.frames_since_report = 0;
+            self.last_report = instant::Instant::now();
+        }
+    }
+
+    fn report(&mut self) {
+        // Collect the frames recorded since the last report, slowest first
+        let len = self.samples.len();
+        let count = self.frames_since_report.min(len);
+        let mut recent: Vec<f32> = (1..=count)
+            .map(|i| self.samples[(self.next + len - i) % len])
+            .collect();
+        recent.sort_by(|a, b| b.total_cmp(a));
+
+        let average = recent.iter().sum::<f32>() / count as f32;
+        let one_percent_low = recent[count / 100];
+        let gpu_time = match &mut self.gpu_time {
+            Some(gpu_time) => format!("{:.2} ms GPU", gpu_time.take_ms()),
+            None => "GPU time unavailable, no timestamp query support".to_string(),
+        };
+        let render_scale = self
+            .render_scale
+            .map(|scale| format!(", scene at {:.0}%", scale * 100.0))
+            .unwrap_or_default();
+        log::info!(
+            "{:.1} fps, {:.2} ms average, {:.2} ms 1% low, {:.2} ms CPU, {gpu_time}{render_scale}",
+            1.0 / average,
+            average * 1000.0,
+            one_percent_low * 1000.0,
+            self.cpu_time.take_ms()
+        );
+        if let Some(averages) = &mut self.pass_times {
+            let passes: Vec<String> = Pass::ALL
+                .iter()
+                .zip(averages)
+                .map(|(pass, average)| format!("{} {:.2} ms", pass.name(), average.take_ms()))
+                .collect();
+            log::info!("GPU passes: {}", passes.join(", "));
+        }
+    }
+}
+
+// Frame rate averaged over roughly a second, for showing somewhere cheap like the window title
+#[derive(Default)]
+pub struct FpsCounter {
+    frames: u32,
+    elapsed: f32,
+}
+
+impl FpsCounter {
+    const INTERVAL: f32 = 1.0;
+
+    // Count a frame that took `frame_time` seconds, returns the frame rate once per interval
+    pub fn tick(&mut self, frame_time: f32) -> Option<f32> {
+        self.frames += 1;
+        self.elapsed += frame_time;
+        if self.elapsed < Self::INTERVAL {
+            return None;
+        }
+        let fps = self.frames as f32 / self.elapsed;
+        *self = Self::default();
+        Some(fps)
+    }
+}
@@ -0,0 +1,319 @@
+// Antialiased HUD text, a signed distance field baked from an 8x8 bitmap font and drawn with the
+// sprite batch in its own pass over the finished frame
+use crate::atlas::Atlas;
+use crate::picking::{PickTarget, NO_ID};
+use crate::post::Viewport;
+use crate::sprite::{Sprite, SpriteBatch};
+use crate::state::PipelineSettings;
+
+// Printable ASCII from space to tilde, anything else is drawn as a question mark
+const FIRST_GLYPH: u8 = b' ';
+const GLYPH_COUNT: u32 = 95;
+// Glyphs per row of the atlas
+const ATLAS_COLUMNS: u32 = 16;
+const ATLAS_ROWS: u32 = GLYPH_COUNT.div_ceil(ATLAS_COLUMNS);
+// Texels per font pixel, and empty texels around each glyph so the field can fall off
+const TEXEL_SCALE: u32 = 3;
+const PADDING: u32 = 4;
+const CELL_TEXELS: u32 = 8 * TEXEL_SCALE + 2 * PADDING;
+// Furthest distance in texels the field encodes either side of the edge
+const SPREAD: f32 = PADDING as f32;
+
+pub struct TextRenderer {
+    atlas: Atlas,
+    sprites: SpriteBatch,
+}
+
+impl TextRenderer {
+    // `bind_group_layout` is the scene's, the glyphs read the resolution from its uniforms.
+    // `format` is the window's, text goes on after post-processing.
+    pub async fn new(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        bind_group_layout: &wgpu::BindGroupLayout,
+        format: wgpu::TextureFormat,
+    ) -> anyhow::Result<Self> {
+        // Linear filtering is what makes a distance field smooth when scaled up
+        let atlas = Atlas::from_pixels(
+            device,
+            queue,
+            [ATLAS_COLUMNS * CELL_TEXELS, ATLAS_ROWS * CELL_TEXELS],
+            wgpu::TextureFormat::Rgba8Unorm,
+            &create_font_pixels(),
+            wgpu::FilterMode::Linear,
+        );
+        let settings = PipelineSettings {
+            format,
+            sample_count: 1,
+            push_constants: false,
+            blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+            depth_test: false,
+            polygon_mode: wgpu::PolygonMode::Fill,
+            reverse_z: false,
+            pick_target: PickTarget::None,
+        };
+        let sprites = SpriteBatch::with_shader(
+            device,
+            bind_group_layout,
+            &atlas,
+            include_str!("text.wgsl"),
+            &settings,
+        )
+        .await?;
+        Ok(Self { atlas, sprites })
+    }
+
+    // Queue `text` for the next upload with its top left corner `x`, `y` pixels from the top
+    // left of the window. Lines are `size` pixels tall and `\n` starts a new one.
+    pub fn draw_text(&mut self, text: &str, x: f32, y: f32, size: f32) {
+        for sprite in layout_text(text, x, y, size) {
+            self.sprites.add(sprite);
+        }
+    }
+
+    // Copy the text queued since last time to the GPU, it's drawn until the next upload
+    pub fn upload(&mut self, device: &wgpu::Device, queue: &wgpu::Queue) {
+        self.sprites.upload(device, queue);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.sprites.is_empty()
+    }
+
+    // Draw the uploaded text over what's already in `view`. The text is laid out in the scene's
+    // pixels, so when the scene was scaled up from an internal resolution it goes in `viewport`
+    // with it.
+    pub fn encode(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        view: &wgpu::TextureView,
+        bind_group: &wgpu::BindGroup,
+        uniform_offset: u32,
+        viewport: Option<Viewport>,
+    ) {
+        if self.is_empty() {
+            return;
+        }
+        let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("text_pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: true,
+                },
+            })],
+            depth_stencil_attachment: None,
+        });
+        if let Some(viewport) = viewport {
+            viewport.apply(&mut rpass);
+        }
+        self.sprites
+            .flush(&mut rpass, bind_group, uniform_offset, &self.atlas, false);
+    }
+}
+
+// One sprite per visible character, the quads cover the glyph's padding too
+fn layout_text(text: &str, x: f32, y: f32, size: f32) -> Vec<Sprite> {
+    let quad_size = size * CELL_TEXELS as f32 / (8 * TEXEL_SCALE) as f32;
+    text.lines()
+        .enumerate()
+        .flat_map(|(row, line)| {
+            line.chars()
+                .enumerate()
+                .filter(|(_, character)| *character != ' ')
+                .map(move |(column, character)| Sprite {
+                    position: [
+                        x + (column as f32 + 0.5) * size,
+                        y + (row as f32 + 0.5) * size,
+                    ],
+                    size: [quad_size, quad_size],
+                    rotation: 0.0,
+                    atlas_rect: glyph_rect(character),
+                    id: NO_ID,
+                })
+        })
+        .collect()
+}
+
+// Where a character's cell sits in the atlas as x, y, width and height in texture coordinates
+fn glyph_rect(character: char) -> [f32; 4] {
+    let index = glyph_index(character);
+    let (width, height) = (1.0 / ATLAS_COLUMNS as f32, 1.0 / ATLAS_ROWS as f32);
+    [
+        (index % ATLAS_COLUMNS) as f32 * width,
+        (index / ATLAS_COLUMNS) as f32 * height,
+        width,
+        height,
+    ]
+}
+
+fn glyph_index(character: char) -> u32 {
+    match character {
+        ' '..='~' => character as u32 - u32::from(FIRST_GLYPH),
+        _ => u32::from(b'?' - FIRST_GLYPH),
+    }
+}
+
+// The distance field of every glyph, 0.5 on the edge and brighter inside. Each font pixel is a
+// filled square, so a texel's distance is to the nearest square of the other kind.
+fn create_font_pixels() -> Vec<u8> {
+    let width = ATLAS_COLUMNS * CELL_TEXELS;
+    let height = ATLAS_ROWS * CELL_TEXELS;
+    let mut pixels = vec![0; (width * height * 4) as usize];
+    for index in 0..GLYPH_COUNT {
+        let bitmap = font8x8::legacy::BASIC_LEGACY[(index + u32::from(FIRST_GLYPH)) as usize];
+        // Rows are top first with the lowest bit on the left
+        let filled = |x: i32, y: i32| {
+            (0..8).contains(&x) && (0..8).contains(&y) && bitmap[y as usize] & (1 << x) != 0
+        };
+        let (cell_x, cell_y) = (
+            (index % ATLAS_COLUMNS) * CELL_TEXELS,
+            (index / ATLAS_COLUMNS) * CELL_TEXELS,
+        );
+        for ty in 0..CELL_TEXELS {
+            for tx in 0..CELL_TEXELS {
+                // Texel centre in font pixels
+                let point = [
+                    (tx as f32 + 0.5 - PADDING as f32) / TEXEL_SCALE as f32,
+                    (ty as f32 + 0.5 - PADDING as f32) / TEXEL_SCALE as f32,
+                ];
+                let inside = filled(point[0].floor() as i32, point[1].floor() as i32);
+                let distance = distance_to_edge(point, |x, y| filled(x, y) != inside);
+                let signed = if inside { distance } else { -distance };
+                let value = (0.5 + signed * TEXEL_SCALE as f32 / (2.0 * SPREAD)).clamp(0.0, 1.0);
+                let offset = (((cell_y + ty) * width + cell_x + tx) * 4) as usize;
+                pixels[offset..offset + 4].fill((value * 255.0).round() as u8);
+            }
+        }
+    }
+    pixels
+}
+
+// Distance in font pixels from `point` to the nearest square where `other` holds, only looking
+// as far as the field spreads
+fn distance_to_edge(point: [f32; 2], other: impl Fn(i32, i32) -> bool) -> f32 {
+    let reach = (SPREAD / TEXEL_SCALE as f32).ceil() as i32 + 1;
+    let (px, py) = (point[0].floor() as i32, point[1].floor() as i32);
+    let mut nearest = f32::MAX;
+    for y in py - reach..=py + reach {
+        for x in px - reach..=px + reach {
+            if other(x, y) {
+                let dx = (x as f32 - point[0])
+                    .max(point[0] - (x + 1) as f32)
+                    .max(0.0);
+                let dy = (y as f32 - point[1])
+                    .max(point[1] - (y + 1) as f32)
+                    .max(0.0);
+                nearest = nearest.min(dx.hypot(dy));
+            }
+        }
+    }
+    nearest
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::camera::Camera;
+    use crate::options::Options;
+    use crate::state::{self, FrameTime};
+    use crate::ui::PARAM_COUNT;
+    use crate::uniform_slots::UniformSlots;
+    use crate::{capture, post, texture};
+
+    #[test]
+    fn newlines_start_a_new_row_at_x() {
+        let sprites = layout_text("ab\n c", 10.0, 20.0, 8.0);
+        let positions: Vec<_> = sprites.iter().map(|sprite| sprite.position).collect();
+        assert_eq!(positions, [[14.0, 24.0], [22.0, 24.0], [22.0, 32.0]]);
+    }
+
+    #[test]
+    fn unknown_characters_use_the_question_mark() {
+        assert_eq!(glyph_rect('é'), glyph_rect('?'));
+        assert_ne!(glyph_rect('a'), glyph_rect('?'));
+    }
+
+    // Renders on whatever adapter is around, including a software one, and skips without one
+    #[test]
+    fn hello_is_drawn_in_white() {
+        let instance = state::create_instance(wgpu::Backends::all());
+        let Ok((_, device, queue)) = pollster::block_on(state::create_device_queue(
+            &instance,
+            None,
+            &Options::default(),
+        )) else {
+            eprintln!("No adapter, skipping");
+            return;
+        };
+        let (width, height) = (96, 32);
+        let format = wgpu::TextureFormat::Rgba8Unorm;
+        let uniforms = state::create_uniforms(
+            winit::dpi::PhysicalSize::new(width, height),
+            1.0,
+            FrameTime::default(),
+            state::Mouse::default(),
+            &Camera::default(),
+            [0.0; PARAM_COUNT],
+            0,
+        );
+        let uniform_slots = UniformSlots::new(&device, 1);
+        uniform_slots.write(&queue, 0, &uniforms);
+        let textures = state::load_input_textures(&device, &queue, &Options::default()).unwrap();
+        let bind_group_layout = state::create_bind_group_layout(&device);
+        let bind_group = state::create_bind_group(
+            &device,
+            &bind_group_layout,
+            uniform_slots.buffer(),
+            &textures,
+            &texture::create_sampler(&device),
+            &post::create_empty_frame(&device),
+            &post::create_frame_sampler(&device),
+        );
+
+        let mut text = pollster::block_on(TextRenderer::new(
+            &device,
+            &queue,
+            &bind_group_layout,
+            format,
+        ))
+        .unwrap();
+        text.draw_text("hello", 8.0, 8.0, 16.0);
+        text.upload(&device, &queue);
+        let target = capture::create_capture_texture(&device, format, width, height);
+        let view = target.create_view(&wgpu::TextureViewDescriptor::default());
+        let mut encoder =
+            device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+        encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: None,
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: &view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                    store: true,
+                },
+            })],
+            depth_stencil_attachment: None,
+        });
+        text.encode(&mut encoder, &view, &bind_group, 0, None);
+        queue.submit(Some(encoder.finish()));
+        let image = capture::read_texture(&device, &queue, &target).unwrap();
+
+        // Five glyphs 16 pixels apart from x = 8, with nothing drawn outside them
+        let lit = |x0: u32, x1: u32, y0: u32, y1: u32| {
+            (y0..y1)
+                .flat_map(|y| (x0..x1).map(move |x| (x, y)))
+                .filter(|&(x, y)| image.get_pixel(x, y)[0] > 200)
+                .count()
+        };
+        for glyph in 0..5 {
+            let x = 8 + glyph * 16;
+            assert!(lit(x, x + 16, 8, 24) > 10, "glyph {glyph} is missing");
+        }
+        assert_eq!(lit(0, width, 0, 6), 0);
+        assert_eq!(lit(0, width, 26, height), 0);
+    }
+}
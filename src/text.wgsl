@@ -0,0 +1,59 @@
+// HUD text, sprites placed in pixels from the top left and cut out of the font's distance field
+
+// Same layout as shader.wgsl, only the resolution is used here
+struct Uniforms {
+    resolution: vec2<f32>,
+    mouse: vec2<f32>,
+    time: f32,
+    dt: f32,
+    dpi: f32,
+    zoom: f32,
+    offset: vec2<f32>,
+    frame: u32,
+    aspect: f32,
+    params: vec4<f32>,
+    look: vec2<f32>,
+    click: vec2<f32>,
+    mouse_buttons: u32,
+    seed: u32,
+    padding: vec2<u32>,
+    view_projection: mat4x4<f32>,
+    inverse_view_projection: mat4x4<f32>,
+};
+@group(0) @binding(0) var<uniform> uniforms: Uniforms;
+@group(1) @binding(0)
+var font_texture: texture_2d<f32>;
+@group(1) @binding(1)
+var font_sampler: sampler;
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) tex_coords: vec2<f32>,
+};
+
+// Glyphs never rotate so the sprite's rotation at location 3 isn't read
+@vertex
+fn vs_main(
+    @location(0) corner: vec2<f32>,
+    @location(1) position: vec2<f32>,
+    @location(2) size: vec2<f32>,
+    @location(4) atlas_rect: vec4<f32>,
+) -> VertexOutput {
+    // Pixel rows go down the screen, the quad's corners go up
+    let pixel = position + vec2<f32>(corner.x - 0.5, 0.5 - corner.y) * size;
+    let ndc = pixel / uniforms.resolution * 2.0 - 1.0;
+    var out: VertexOutput;
+    out.position = vec4<f32>(ndc.x, -ndc.y, 0.0, 1.0);
+    out.tex_coords = atlas_rect.xy + vec2<f32>(corner.x, 1.0 - corner.y) * atlas_rect.zw;
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    // The edge sits at 0.5. Smoothing over however much the distance changes across a pixel
+    // keeps it about a pixel wide at any text size.
+    let distance = textureSample(font_texture, font_sampler, in.tex_coords).r;
+    let width = max(fwidth(distance), 0.0001);
+    let alpha = smoothstep(0.5 - width, 0.5 + width, distance);
+    return vec4<f32>(1.0, 1.0, 1.0, alpha);
+}
@@ -0,0 +1,90 @@
+// Loading images into textures the shader can sample
+
+use std::path::Path;
+
+use anyhow::Context;
+
+// Load an image file, colour images should be sRGB while data like normal maps is linear
+pub fn load_texture(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    path: &Path,
+    linear: bool,
+) -> anyhow::Result<wgpu::Texture> {
+    let image = image::open(path)
+        .with_context(|| format!("Failed to load texture {}", path.display()))?
+        .to_rgba8();
+    let format = if linear {
+        wgpu::TextureFormat::Rgba8Unorm
+    } else {
+        wgpu::TextureFormat::Rgba8UnormSrgb
+    };
+    Ok(create_texture(
+        device,
+        queue,
+        image.width(),
+        image.height(),
+        format,
+        &image,
+    ))
+}
+
+// Opaque white 1x1 texture bound when no image is given, so sampling it is a no-op tint
+pub fn create_placeholder_texture(device: &wgpu::Device, queue: &wgpu::Queue) -> wgpu::Texture {
+    create_texture(
+        device,
+        queue,
+        1,
+        1,
+        wgpu::TextureFormat::Rgba8UnormSrgb,
+        &[255, 255, 255, 255],
+    )
+}
+
+// Non power of two sizes are fine, only mipmapping would care and we don't generate mips
+pub fn create_texture(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    width: u32,
+    height: u32,
+    format: wgpu::TextureFormat,
+    pixels: &[u8],
+) -> wgpu::Texture {
+    let size = wgpu::Extent3d {
+        width,
+        height,
+        depth_or_array_layers: 1,
+    };
+    let texture = device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("input_texture"),
+        size,
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format,
+        usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+        view_formats: &[],
+    });
+    queue.write_texture(
+        texture.as_image_copy(),
+        pixels,
+        wgpu::ImageDataLayout {
+            offset: 0,
+            bytes_per_row: Some(width * 4),
+            rows_per_image: Some(height),
+        },
+        size,
+    );
+    texture
+}
+
+pub fn create_sampler(device: &wgpu::Device) -> wgpu::Sampler {
+    device.create_sampler(&wgpu::SamplerDescriptor {
+        label: Some("input_sampler"),
+        address_mode_u: wgpu::AddressMode::Repeat,
+        address_mode_v: wgpu::AddressMode::Repeat,
+        mag_filter: wgpu::FilterMode::Linear,
+        min_filter: wgpu::FilterMode::Linear,
+        ..Default::default()
+    })
+}
@@ -0,0 +1,119 @@
+// A grid of tiles drawn with one instanced quad per tile, sampling a texture atlas
+use crate::atlas::{self, Atlas};
+use crate::quad;
+use crate::state::{create_pipeline_with_wireframe, PipelineSettings, PipelineWithWireframe};
+use anyhow::Context;
+use bytemuck::{Pod, Zeroable};
+use wgpu::util::DeviceExt;
+
+// Per-instance data, mirrors the instance inputs of vs_main in tilemap.wgsl
+#[repr(C)]
+#[derive(Debug, Copy, Clone, Pod, Zeroable)]
+struct TileInstance {
+    // Column and row in the map, rows count up from the bottom
+    position: [f32; 2],
+    // Which tile of the atlas to draw
+    tile: u32,
+}
+
+pub struct Tilemap {
+    render_pipeline: PipelineWithWireframe,
+    quad_buffer: wgpu::Buffer,
+    instance_buffer: wgpu::Buffer,
+    instance_count: u32,
+}
+
+impl Tilemap {
+    // `bind_group_layout` is the scene's, the tiles read the camera from its uniforms. `tiles`
+    // has a string per row, top first, with a character per tile.
+    pub async fn new(
+        device: &wgpu::Device,
+        bind_group_layout: &wgpu::BindGroupLayout,
+        atlas: &Atlas,
+        tiles: &[String],
+        settings: &PipelineSettings,
+    ) -> anyhow::Result<Self> {
+        // The uniform buffer is always written while there's a tile map, so it stays off push
+        // constants whatever the scene shader does
+        let settings = PipelineSettings {
+            push_constants: false,
+            ..*settings
+        };
+        let render_pipeline = create_pipeline_with_wireframe(
+            device,
+            include_str!("tilemap.wgsl"),
+            &[bind_group_layout, &atlas.bind_group_layout],
+            &[
+                quad::CORNER_LAYOUT,
+                wgpu::VertexBufferLayout {
+                    array_stride: std::mem::size_of::<TileInstance>() as wgpu::BufferAddress,
+                    step_mode: wgpu::VertexStepMode::Instance,
+                    attributes: &wgpu::vertex_attr_array![1 => Float32x2, 2 => Uint32],
+                },
+            ],
+            &settings,
+        )
+        .await
+        .context("Failed to build the tile map pipeline")?;
+
+        let quad_buffer = quad::create_quad_buffer(device);
+        let instances = create_instances(tiles);
+        let instance_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("tile_instance_buffer"),
+            contents: bytemuck::cast_slice(&instances),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+
+        Ok(Self {
+            render_pipeline,
+            quad_buffer,
+            instance_buffer,
+            instance_count: instances.len() as u32,
+        })
+    }
+
+    // Draw every tile in one call, inside the scene's pass so it shares the depth and MSAA
+    // targets
+    pub fn draw<'a>(
+        &'a self,
+        rpass: &mut wgpu::RenderPass<'a>,
+        bind_group: &'a wgpu::BindGroup,
+        uniform_offset: u32,
+        atlas: &'a Atlas,
+        wireframe: bool,
+    ) {
+        rpass.set_pipeline(self.render_pipeline.get(wireframe));
+        rpass.set_bind_group(0, bind_group, &[uniform_offset]);
+        rpass.set_bind_group(1, &atlas.bind_group, &[]);
+        rpass.set_vertex_buffer(0, self.quad_buffer.slice(..));
+        rpass.set_vertex_buffer(1, self.instance_buffer.slice(..));
+        rpass.draw(0..6, 0..self.instance_count);
+    }
+}
+
+// Atlas column for a map character, None leaves the cell empty so the scene shows through
+fn tile_index(cell: char) -> Option<u32> {
+    match cell {
+        ',' => Some(atlas::GRASS),
+        '~' => Some(atlas::WATER),
+        '#' => Some(atlas::STONE),
+        ':' => Some(atlas::SAND),
+        _ => None,
+    }
+}
+
+// The last row is drawn at the bottom of the map
+fn create_instances(map: &[String]) -> Vec<TileInstance> {
+    map.iter()
+        .rev()
+        .enumerate()
+        .flat_map(|(row, line)| {
+            line.chars().enumerate().filter_map(move |(column, cell)| {
+                tile_index(cell).map(|tile| TileInstance {
+                    position: [column as f32, row as f32],
+                    tile,
+                })
+            })
+        })
+        .collect()
+}
@@ -0,0 +1,60 @@
+// Textured quads for a grid of tiles, one instance per tile
+
+// Side of a tile in the world units the scene shader's camera works in, a 16 tile map fills the
+// screen at the default zoom. MAP_ORIGIN is where tile (0, 0) has its bottom left corner.
+const TILE_SIZE: f32 = 0.0625;
+const MAP_ORIGIN: vec2<f32> = vec2<f32>(0.0, 0.0);
+// Tiles are laid out side by side in a single row of the atlas, see atlas.rs
+const ATLAS_TILES: f32 = 5.0;
+
+// Same layout as shader.wgsl, only the camera is used here
+struct Uniforms {
+    resolution: vec2<f32>,
+    mouse: vec2<f32>,
+    time: f32,
+    dt: f32,
+    dpi: f32,
+    zoom: f32,
+    offset: vec2<f32>,
+    frame: u32,
+    aspect: f32,
+    params: vec4<f32>,
+    look: vec2<f32>,
+    click: vec2<f32>,
+    mouse_buttons: u32,
+    seed: u32,
+    padding: vec2<u32>,
+    view_projection: mat4x4<f32>,
+    inverse_view_projection: mat4x4<f32>,
+};
+@group(0) @binding(0) var<uniform> uniforms: Uniforms;
+@group(1) @binding(0)
+var atlas_texture: texture_2d<f32>;
+@group(1) @binding(1)
+var atlas_sampler: sampler;
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) tex_coords: vec2<f32>,
+};
+
+@vertex
+fn vs_main(
+    @location(0) corner: vec2<f32>,
+    @location(1) tile_position: vec2<f32>,
+    @location(2) tile: u32,
+) -> VertexOutput {
+    let world = MAP_ORIGIN + (tile_position + corner) * TILE_SIZE;
+    // The camera's transform, which the scene shader looks through too, so tiles scroll with
+    // what it shows
+    var out: VertexOutput;
+    out.position = uniforms.view_projection * vec4<f32>(world, 0.0, 1.0);
+    // Texture rows go down while the world goes up
+    out.tex_coords = vec2<f32>((f32(tile) + corner.x) / ATLAS_TILES, 1.0 - corner.y);
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    return textureSample(atlas_texture, atlas_sampler, in.tex_coords);
+}
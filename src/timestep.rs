@@ -0,0 +1,93 @@
+// Game logic runs in fixed steps however fast frames are drawn, so it plays out the same on every
+// machine. Frames carry the leftover time and draw partway between the last two steps.
+
+// Seconds of game time each logic step covers
+pub const LOGIC_DT: f32 = 1.0 / 60.0;
+
+// Most steps a single frame catches up on, after a stall the rest is dropped rather than making
+// the next frame slower still
+const MAX_STEPS: u32 = 8;
+
+#[derive(Debug, Clone, Copy)]
+pub struct FixedTimestep {
+    step: f32,
+    // Time not yet covered by a step, under a step's worth apart from rounding
+    accumulator: f32,
+}
+
+impl Default for FixedTimestep {
+    fn default() -> Self {
+        Self::new(LOGIC_DT)
+    }
+}
+
+impl FixedTimestep {
+    pub fn new(step: f32) -> Self {
+        Self {
+            step,
+            accumulator: 0.0,
+        }
+    }
+
+    // Let `dt` seconds pass, returns how many steps to run for them, zero on a fast frame
+    pub fn advance(&mut self, dt: f32) -> u32 {
+        self.accumulator += dt;
+        let steps = (self.accumulator / self.step).floor();
+        self.accumulator -= steps * self.step;
+        // Rounding can leave a hair under zero
+        self.accumulator = self.accumulator.max(0.0);
+        let steps = steps as u32;
+        if steps > MAX_STEPS {
+            self.accumulator = 0.0;
+            return MAX_STEPS;
+        }
+        steps
+    }
+
+    // How far the present is from the last step towards the next, from 0 to 1
+    pub fn alpha(&self) -> f32 {
+        (self.accumulator / self.step).min(1.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn steps_match_elapsed_time() {
+        let mut timestep = FixedTimestep::default();
+        // Frames at uneven rates, both faster and slower than the logic
+        let frames = [0.004, 0.016, 0.017, 0.033, 0.001, 0.05, 0.0167];
+        let mut elapsed = 0.0f64;
+        let mut steps = 0u64;
+        for dt in frames.iter().cycle().take(10_000) {
+            elapsed += f64::from(*dt);
+            steps += u64::from(timestep.advance(*dt));
+        }
+        let expected = elapsed / f64::from(LOGIC_DT);
+        assert!(
+            (steps as f64 - expected).abs() <= 1.0,
+            "{steps} steps for {expected} steps of time"
+        );
+        let alpha = timestep.alpha();
+        assert!((0.0..1.0).contains(&alpha));
+    }
+
+    #[test]
+    fn fast_frames_accumulate_into_a_step() {
+        let mut timestep = FixedTimestep::new(0.1);
+        assert_eq!(timestep.advance(0.04), 0);
+        assert!((timestep.alpha() - 0.4).abs() < 1e-5);
+        assert_eq!(timestep.advance(0.04), 0);
+        assert_eq!(timestep.advance(0.04), 1);
+        assert!((timestep.alpha() - 0.2).abs() < 1e-4);
+    }
+
+    #[test]
+    fn stalls_drop_time_past_the_limit() {
+        let mut timestep = FixedTimestep::new(0.1);
+        assert_eq!(timestep.advance(5.0), MAX_STEPS);
+        assert_eq!(timestep.alpha(), 0.0);
+    }
+}
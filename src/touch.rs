@@ -0,0 +1,151 @@
+// Fingers on a touchscreen or trackpad, one drags like the mouse with its left button held and
+// two pinch to zoom. Without a touch device no events arrive and none of this runs.
+use std::collections::HashMap;
+use winit::event::TouchPhase;
+
+// What a touch event means for the cursor and camera, positions are physical pixels
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TouchAction {
+    // The first finger went down, like pressing the left button there
+    Press([f32; 2]),
+    // The only finger moved
+    Move([f32; 2]),
+    // The last finger lifted, or a second one started a pinch
+    Release,
+    // Two fingers moved `scale` times further apart, around their midpoint
+    Pinch { scale: f32, centre: [f32; 2] },
+    None,
+}
+
+#[derive(Debug, Default)]
+pub struct Touches {
+    // Where each finger is, by winit's touch id
+    points: HashMap<u64, [f32; 2]>,
+    // How far apart the fingers were at the last event of a pinch
+    pinch_distance: Option<f32>,
+    // Whether a finger holds the left button down, only the first does until it's joined
+    pressing: bool,
+}
+
+impl Touches {
+    pub fn update(&mut self, id: u64, phase: TouchPhase, position: [f32; 2]) -> TouchAction {
+        match phase {
+            TouchPhase::Started => {
+                self.points.insert(id, position);
+                match self.points.len() {
+                    1 => {
+                        self.pressing = true;
+                        TouchAction::Press(position)
+                    }
+                    // Let go of what the first finger held so the pinch doesn't drag it too
+                    2 => {
+                        self.pinch_distance = self.pinch().map(|(distance, _)| distance);
+                        self.release()
+                    }
+                    _ => TouchAction::None,
+                }
+            }
+            TouchPhase::Moved => {
+                let Some(point) = self.points.get_mut(&id) else {
+                    return TouchAction::None;
+                };
+                *point = position;
+                match (self.points.len(), self.pinch_distance, self.pinch()) {
+                    (1, ..) => TouchAction::Move(position),
+                    (2, Some(previous), Some((distance, centre))) if previous > 0.0 => {
+                        self.pinch_distance = Some(distance);
+                        TouchAction::Pinch {
+                            scale: distance / previous,
+                            centre,
+                        }
+                    }
+                    _ => TouchAction::None,
+                }
+            }
+            TouchPhase::Ended | TouchPhase::Cancelled => {
+                if self.points.remove(&id).is_none() {
+                    return TouchAction::None;
+                }
+                // A finger left over from a pinch moves the cursor without pressing again
+                if self.points.len() < 2 {
+                    self.pinch_distance = None;
+                }
+                if self.points.is_empty() {
+                    self.release()
+                } else {
+                    TouchAction::None
+                }
+            }
+        }
+    }
+
+    fn release(&mut self) -> TouchAction {
+        if std::mem::take(&mut self.pressing) {
+            TouchAction::Release
+        } else {
+            TouchAction::None
+        }
+    }
+
+    // Distance between and midpoint of the two fingers, None unless exactly two are down
+    fn pinch(&self) -> Option<(f32, [f32; 2])> {
+        let mut points = self.points.values();
+        let (Some(a), Some(b), None) = (points.next(), points.next(), points.next()) else {
+            return None;
+        };
+        let distance = (a[0] - b[0]).hypot(a[1] - b[1]);
+        Some((distance, [(a[0] + b[0]) / 2.0, (a[1] + b[1]) / 2.0]))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn one_finger_drags_like_the_mouse() {
+        let mut touches = Touches::default();
+        assert_eq!(
+            touches.update(7, TouchPhase::Started, [10.0, 20.0]),
+            TouchAction::Press([10.0, 20.0])
+        );
+        assert_eq!(
+            touches.update(7, TouchPhase::Moved, [15.0, 20.0]),
+            TouchAction::Move([15.0, 20.0])
+        );
+        assert_eq!(
+            touches.update(7, TouchPhase::Ended, [15.0, 20.0]),
+            TouchAction::Release
+        );
+    }
+
+    #[test]
+    fn two_fingers_pinch() {
+        let mut touches = Touches::default();
+        touches.update(1, TouchPhase::Started, [0.0, 0.0]);
+        assert_eq!(
+            touches.update(2, TouchPhase::Started, [10.0, 0.0]),
+            TouchAction::Release
+        );
+        assert_eq!(
+            touches.update(2, TouchPhase::Moved, [20.0, 0.0]),
+            TouchAction::Pinch {
+                scale: 2.0,
+                centre: [10.0, 0.0]
+            }
+        );
+        assert_eq!(
+            touches.update(2, TouchPhase::Ended, [20.0, 0.0]),
+            TouchAction::None
+        );
+        // The finger that's left moves the cursor but doesn't press or release
+        assert_eq!(
+            touches.update(1, TouchPhase::Moved, [1.0, 0.0]),
+            TouchAction::Move([1.0, 0.0])
+        );
+        assert_eq!(
+            touches.update(1, TouchPhase::Ended, [1.0, 0.0]),
+            TouchAction::None
+        );
+    }
+}
@@ -0,0 +1,208 @@
+// Debug overlay drawn with egui on top of the shader, for tweaking parameters live
+use crate::perf_graph::PerfGraph;
+use winit::{
+    event::{ElementState, KeyboardInput, WindowEvent},
+    window::Window,
+};
+
+// Number of generic shader parameters exposed as sliders, matches `params` in shader.wgsl
+pub const PARAM_COUNT: usize = 4;
+
+// What the --editor panel asked for this frame
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EditorRequest {
+    // Compile the shader from the text being edited, with Ctrl+Enter or the button
+    Apply,
+    // Write the text back to the shader's file, with Ctrl+S or the button
+    Save,
+}
+
+pub struct Ui {
+    context: egui::Context,
+    state: egui_winit::State,
+    renderer: egui_wgpu::Renderer,
+    // Output of the last UI update, waiting to be painted by `render`
+    paint_jobs: Vec<egui::ClippedPrimitive>,
+    textures_delta: egui::TexturesDelta,
+}
+
+impl Ui {
+    pub fn new(window: &Window, device: &wgpu::Device, format: wgpu::TextureFormat) -> Self {
+        let mut state = egui_winit::State::new(window);
+        state.set_pixels_per_point(window.scale_factor() as f32);
+        state.set_max_texture_side(device.limits().max_texture_dimension_2d as usize);
+        Self {
+            context: egui::Context::default(),
+            state,
+            renderer: egui_wgpu::Renderer::new(device, format, None, 1),
+            paint_jobs: Vec::new(),
+            textures_delta: egui::TexturesDelta::default(),
+        }
+    }
+
+    // Feed an event to egui, returns true if the panel captured it and nothing else should see it
+    pub fn input(&mut self, window: &Window, event: &WindowEvent) -> bool {
+        let response = self.state.on_event(&self.context, event);
+        if response.repaint {
+            window.request_redraw();
+        }
+        // Key releases always get through, otherwise a key held while focusing the panel sticks
+        let key_released = matches!(
+            event,
+            WindowEvent::KeyboardInput {
+                input: KeyboardInput {
+                    state: ElementState::Released,
+                    ..
+                },
+                ..
+            }
+        );
+        response.consumed && !key_released
+    }
+
+    // Lay out this frame's panel, the sliders edit `params` in place and the editor `editor`'s
+    // shader source with --editor. A shader error is shown over the frame from the last shader
+    // that compiled, or under the editor's buttons, and the frame time graph in the corner if
+    // it's given.
+    pub fn update(
+        &mut self,
+        window: &Window,
+        params: &mut [f32; PARAM_COUNT],
+        shader_error: Option<&str>,
+        editor: Option<&mut String>,
+        perf_graph: Option<&PerfGraph>,
+    ) -> Option<EditorRequest> {
+        let input = self.state.take_egui_input(window);
+        let mut request = None;
+        let output = self.context.run(input, |context| {
+            egui::Window::new("Parameters").show(context, |ui| {
+                for (index, param) in params.iter_mut().enumerate() {
+                    ui.add(egui::Slider::new(param, 0.0..=1.0).text(format!("params[{index}]")));
+                }
+            });
+            if let Some(perf_graph) = perf_graph {
+                perf_graph.show(context);
+            }
+            if let Some(text) = editor {
+                request = show_editor(context, text, shader_error);
+            } else if let Some(error) = shader_error {
+                egui::Window::new("Shader error")
+                    .anchor(egui::Align2::CENTER_BOTTOM, [0.0, -16.0])
+                    .collapsible(false)
+                    .resizable(false)
+                    .show(context, |ui| {
+                        ui.label(
+                            egui::RichText::new(error)
+                                .monospace()
+                                .color(egui::Color32::LIGHT_RED),
+                        );
+                    });
+            }
+        });
+        self.state
+            .handle_platform_output(window, &self.context, output.platform_output);
+        self.textures_delta.append(output.textures_delta);
+        self.paint_jobs = self.context.tessellate(output.shapes);
+        request
+    }
+
+    // Draw the panel over what's already in `view`, the returned command buffers must be
+    // submitted before the encoder
+    pub fn render(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        encoder: &mut wgpu::CommandEncoder,
+        view: &wgpu::TextureView,
+        size_in_pixels: [u32; 2],
+    ) -> Vec<wgpu::CommandBuffer> {
+        let screen_descriptor = egui_wgpu::renderer::ScreenDescriptor {
+            size_in_pixels,
+            pixels_per_point: self.state.pixels_per_point(),
+        };
+        let textures_delta = std::mem::take(&mut self.textures_delta);
+        for (id, image_delta) in &textures_delta.set {
+            self.renderer
+                .update_texture(device, queue, *id, image_delta);
+        }
+        let command_buffers = self.renderer.update_buffers(
+            device,
+            queue,
+            encoder,
+            &self.paint_jobs,
+            &screen_descriptor,
+        );
+
+        {
+            let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("ui_pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Load,
+                        store: true,
+                    },
+                })],
+                depth_stencil_attachment: None,
+            });
+            self.renderer
+                .render(&mut rpass, &self.paint_jobs, &screen_descriptor);
+        }
+
+        for id in &textures_delta.free {
+            self.renderer.free_texture(id);
+        }
+        command_buffers
+    }
+}
+
+// The --editor panel, a text box for the shader's source with buttons to compile and save it
+fn show_editor(
+    context: &egui::Context,
+    text: &mut String,
+    shader_error: Option<&str>,
+) -> Option<EditorRequest> {
+    let mut request = None;
+    egui::Window::new("Shader")
+        .default_size([520.0, 420.0])
+        .show(context, |ui| {
+            let id = egui::Id::new("shader_editor");
+            // Taken before the text box sees them, so Ctrl+Enter doesn't add a line. Only while
+            // it has focus, otherwise Ctrl+S goes to the world like it always has.
+            if ui.memory(|memory| memory.has_focus(id)) {
+                let shortcut = |key| egui::KeyboardShortcut::new(egui::Modifiers::COMMAND, key);
+                if ui.input_mut(|input| input.consume_shortcut(&shortcut(egui::Key::Enter))) {
+                    request = Some(EditorRequest::Apply);
+                }
+                if ui.input_mut(|input| input.consume_shortcut(&shortcut(egui::Key::S))) {
+                    request = Some(EditorRequest::Save);
+                }
+            }
+            ui.horizontal(|ui| {
+                if ui.button("Apply").on_hover_text("Ctrl+Enter").clicked() {
+                    request = Some(EditorRequest::Apply);
+                }
+                if ui.button("Save").on_hover_text("Ctrl+S").clicked() {
+                    request = Some(EditorRequest::Save);
+                }
+            });
+            if let Some(error) = shader_error {
+                ui.label(
+                    egui::RichText::new(error)
+                        .monospace()
+                        .color(egui::Color32::LIGHT_RED),
+                );
+            }
+            egui::ScrollArea::vertical().show(ui, |ui| {
+                ui.add(
+                    egui::TextEdit::multiline(text)
+                        .id(id)
+                        .code_editor()
+                        .desired_rows(24)
+                        .desired_width(f32::INFINITY),
+                );
+            });
+        });
+    request
+}
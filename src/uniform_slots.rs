@@ -0,0 +1,68 @@
+// One uniform buffer holding a copy of the uniforms per draw variant, like each window. The
+// bind group is made once and a dynamic offset picks the copy for each draw.
+use crate::state::Uniforms;
+
+pub struct UniformSlots {
+    buffer: wgpu::Buffer,
+    // Bytes from one copy to the next, rounded up to the device's offset alignment
+    stride: u32,
+    capacity: u32,
+}
+
+impl UniformSlots {
+    pub fn new(device: &wgpu::Device, capacity: u32) -> Self {
+        let stride = slot_stride(device.limits().min_uniform_buffer_offset_alignment);
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("uniform_buffer"),
+            size: u64::from(stride * capacity),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        Self {
+            buffer,
+            stride,
+            capacity,
+        }
+    }
+
+    pub fn buffer(&self) -> &wgpu::Buffer {
+        &self.buffer
+    }
+
+    pub fn capacity(&self) -> u32 {
+        self.capacity
+    }
+
+    // What to pass to `set_bind_group` to read `slot`
+    pub fn offset(&self, slot: u32) -> u32 {
+        slot * self.stride
+    }
+
+    pub fn write(&self, queue: &wgpu::Queue, slot: u32, uniforms: &Uniforms) {
+        queue.write_buffer(
+            &self.buffer,
+            u64::from(self.offset(slot)),
+            bytemuck::bytes_of(uniforms),
+        );
+    }
+}
+
+// Dynamic offsets have to be a multiple of the alignment, which is a power of two
+fn slot_stride(alignment: u32) -> u32 {
+    (std::mem::size_of::<Uniforms>() as u32).next_multiple_of(alignment)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn slots_are_aligned_and_hold_the_uniforms() {
+        for alignment in [16, 64, 256] {
+            let stride = slot_stride(alignment);
+            assert_eq!(stride % alignment, 0);
+            assert!(stride as usize >= std::mem::size_of::<Uniforms>());
+        }
+        assert_eq!(slot_stride(256), 256);
+    }
+}
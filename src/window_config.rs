@@ -0,0 +1,109 @@
+// Remembering the window's position and size between runs
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use winit::{
+    dpi::{PhysicalPosition, PhysicalSize},
+    event_loop::EventLoop,
+    window::{Window, WindowBuilder},
+};
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct WindowConfig {
+    // Outer position and inner size, both in physical pixels
+    pub position: (i32, i32),
+    pub size: (u32, u32),
+}
+
+// ~/.config/danrpg, or the same under wherever XDG_CONFIG_HOME or APPDATA point
+pub fn config_dir() -> Option<PathBuf> {
+    let config_dir = std::env::var_os("XDG_CONFIG_HOME")
+        .or_else(|| std::env::var_os("APPDATA"))
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+    Some(config_dir.join("danrpg"))
+}
+
+fn config_path() -> Option<PathBuf> {
+    Some(config_dir()?.join("window.ron"))
+}
+
+// The last saved placement, None on the first run or if the file can't be read
+pub fn load() -> Option<WindowConfig> {
+    let path = config_path()?;
+    let text = std::fs::read_to_string(&path).ok()?;
+    ron::from_str(&text)
+        .map_err(|error| log::warn!("Ignoring invalid {}: {error}", path.display()))
+        .ok()
+}
+
+// Skipped in fullscreen so the windowed placement from last time is kept
+pub fn save(window: &Window) {
+    if window.fullscreen().is_some() {
+        return;
+    }
+    let Ok(position) = window.outer_position() else {
+        // Wayland doesn't expose window positions, so there's nothing worth saving
+        return;
+    };
+    let size = window.inner_size();
+    let config = WindowConfig {
+        position: (position.x, position.y),
+        size: (size.width, size.height),
+    };
+    let Some(path) = config_path() else {
+        return;
+    };
+    let result = ron::to_string(&config)
+        .map_err(anyhow::Error::from)
+        .and_then(|text| {
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            Ok(std::fs::write(&path, text)?)
+        });
+    if let Err(error) = result {
+        log::warn!(
+            "Failed to save window placement to {}: {error:#}",
+            path.display()
+        );
+    }
+}
+
+// Place the window where it was last time, moved back onto a monitor if that one's gone
+pub fn restore(
+    builder: WindowBuilder,
+    config: &WindowConfig,
+    event_loop: &EventLoop<()>,
+) -> WindowBuilder {
+    let monitors: Vec<(PhysicalPosition<i32>, PhysicalSize<u32>)> = event_loop
+        .available_monitors()
+        .map(|monitor| (monitor.position(), monitor.size()))
+        .collect();
+    let (x, y) = config.position;
+    let (width, height) = config.size;
+    let on_screen = |(origin, size): &(PhysicalPosition<i32>, PhysicalSize<u32>)| {
+        x >= origin.x
+            && y >= origin.y
+            && i64::from(x) < i64::from(origin.x) + i64::from(size.width)
+            && i64::from(y) < i64::from(origin.y) + i64::from(size.height)
+    };
+    let Some((origin, size)) = monitors
+        .iter()
+        .find(|monitor| on_screen(monitor))
+        .or_else(|| monitors.first())
+    else {
+        return builder.with_inner_size(PhysicalSize::new(width, height));
+    };
+
+    // Keep the whole window on the monitor, shrinking it if the monitor is now smaller
+    let width = width.min(size.width);
+    let height = height.min(size.height);
+    let max_x = origin.x + (size.width - width) as i32;
+    let max_y = origin.y + (size.height - height) as i32;
+    builder
+        .with_inner_size(PhysicalSize::new(width, height))
+        .with_position(PhysicalPosition::new(
+            x.clamp(origin.x, max_x),
+            y.clamp(origin.y, max_y),
+        ))
+}
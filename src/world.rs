@@ -0,0 +1,129 @@
+// Draws the game world over the scene with --tilemap, tiles first and sprites on top
+use crate::atlas::{self, Atlas};
+use crate::ecs;
+use crate::picking;
+use crate::save::World;
+use crate::sprite::{Sprite, SpriteBatch};
+use crate::state::PipelineSettings;
+use crate::tilemap::Tilemap;
+
+// Side of a sprite in world units, the same as a tile
+const SPRITE_SIZE: f32 = 0.0625;
+
+pub struct WorldRenderer {
+    atlas: Atlas,
+    tilemap: Tilemap,
+    sprites: SpriteBatch,
+    // A dot of this many world units for each sprite with --minimap, laid out alongside them
+    dots: Option<(SpriteBatch, f32)>,
+}
+
+impl WorldRenderer {
+    // `bind_group_layout` is the scene's, everything reads the camera from its uniforms. The
+    // tiles are fixed from here on, entities and the player are picked up every frame.
+    pub async fn new(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        bind_group_layout: &wgpu::BindGroupLayout,
+        world: &World,
+        settings: &PipelineSettings,
+    ) -> anyhow::Result<Self> {
+        let atlas = Atlas::new(device, queue);
+        let tilemap =
+            Tilemap::new(device, bind_group_layout, &atlas, &world.tiles, settings).await?;
+        let sprites = SpriteBatch::new(device, bind_group_layout, &atlas, settings).await?;
+        Ok(Self {
+            atlas,
+            tilemap,
+            sprites,
+            dots: None,
+        })
+    }
+
+    // Lay out dots of `dot_size` for the minimap from here on
+    pub async fn add_dots(
+        &mut self,
+        device: &wgpu::Device,
+        bind_group_layout: &wgpu::BindGroupLayout,
+        settings: &PipelineSettings,
+        dot_size: f32,
+    ) -> anyhow::Result<()> {
+        let dots = SpriteBatch::new(device, bind_group_layout, &self.atlas, settings).await?;
+        self.dots = Some((dots, dot_size));
+        Ok(())
+    }
+
+    // Lay out this frame's sprites, with the player over the entities
+    pub fn update(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        entities: &ecs::World,
+        player: [f32; 2],
+        alpha: f32,
+    ) {
+        for (entity, transform, sprite) in entities.sprites(alpha) {
+            let size = SPRITE_SIZE * transform.scale;
+            let sprite = Sprite {
+                position: transform.position,
+                size: [size, size],
+                rotation: transform.rotation,
+                atlas_rect: atlas::tile_rect(sprite.tile),
+                id: entity.pick_id(),
+            };
+            self.sprites.add(sprite);
+            if let Some((dots, dot_size)) = &mut self.dots {
+                dots.add(as_dot(sprite, *dot_size));
+            }
+        }
+        let player = Sprite {
+            position: player,
+            size: [SPRITE_SIZE, SPRITE_SIZE],
+            rotation: 0.0,
+            atlas_rect: atlas::tile_rect(atlas::CHARACTER),
+            id: picking::PLAYER_ID,
+        };
+        self.sprites.add(player);
+        self.sprites.upload(device, queue);
+        if let Some((dots, dot_size)) = &mut self.dots {
+            dots.add(as_dot(player, *dot_size));
+            dots.upload(device, queue);
+        }
+    }
+
+    pub fn draw<'a>(
+        &'a self,
+        rpass: &mut wgpu::RenderPass<'a>,
+        bind_group: &'a wgpu::BindGroup,
+        uniform_offset: u32,
+        wireframe: bool,
+    ) {
+        self.tilemap
+            .draw(rpass, bind_group, uniform_offset, &self.atlas, wireframe);
+        self.sprites
+            .flush(rpass, bind_group, uniform_offset, &self.atlas, wireframe);
+    }
+
+    // The tiles and a dot for each sprite, through the minimap's uniforms at `uniform_offset`
+    pub fn draw_minimap<'a>(
+        &'a self,
+        rpass: &mut wgpu::RenderPass<'a>,
+        bind_group: &'a wgpu::BindGroup,
+        uniform_offset: u32,
+    ) {
+        self.tilemap
+            .draw(rpass, bind_group, uniform_offset, &self.atlas, false);
+        if let Some((dots, _)) = &self.dots {
+            dots.flush(rpass, bind_group, uniform_offset, &self.atlas, false);
+        }
+    }
+}
+
+// The same sprite as a dot whatever its size, upright so the texture's corners don't turn
+fn as_dot(sprite: Sprite, dot_size: f32) -> Sprite {
+    Sprite {
+        size: [dot_size, dot_size],
+        rotation: 0.0,
+        ..sprite
+    }
+}